@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use bird_flock::flock::{Bird, Flock, FlockConfig, NeighbourSearch, NEIGHBOUR_RADIUS};
+
+const SEED: u64 = 42;
+const SIZES: [usize; 3] = [1000, 10000, 100000];
+
+fn update_step(c: &mut Criterion)
+{
+    bench_search(c, "flock_update", NeighbourSearch::BruteForce);
+    bench_search(c, "flock_update_octree", NeighbourSearch::Octree);
+    bench_search(c, "flock_update_grid", NeighbourSearch::Grid);
+    bench_search(c, "flock_update_barnes_hut", NeighbourSearch::BarnesHut);
+}
+
+fn steady_state(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("flock_update_steady");
+
+    for &size in SIZES.iter() {
+        let config = FlockConfig { num_birds: size, ..FlockConfig::default() };
+        let mut flock = Flock::with_seed(config, SEED);
+        flock.update();
+
+        if size >= 10000 {
+            group.sample_size(10);
+        }
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| flock.update());
+        });
+    }
+
+    group.finish();
+}
+
+fn neighbour_scan(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("neighbour_scan");
+    let size = 10000;
+    let flock = Flock::with_seed(FlockConfig { num_birds: size, ..FlockConfig::default() }, SEED);
+    let positions: Vec<[f32; 4]> = flock.birds.iter().map(|bird| [bird.position.x, bird.position.y, bird.position.z, 0.0]).collect();
+    let radius_squared = NEIGHBOUR_RADIUS * NEIGHBOUR_RADIUS;
+
+    group.throughput(Throughput::Elements(size as u64));
+    group.bench_function("birds", |b| {
+        b.iter(|| count_neighbours_aos(black_box(&flock.birds), &flock.birds[0], radius_squared));
+    });
+    group.bench_function("positions", |b| {
+        b.iter(|| count_neighbours_soa(black_box(&positions), positions[0], radius_squared));
+    });
+
+    group.finish();
+}
+
+fn count_neighbours_aos(birds: &[Bird], bird: &Bird, radius_squared: f32) -> usize
+{
+    birds.iter().filter(|other| (other.position - bird.position).norm_squared() < radius_squared).count()
+}
+
+fn count_neighbours_soa(positions: &[[f32; 4]], point: [f32; 4], radius_squared: f32) -> usize
+{
+    positions.iter().filter(|other| {
+        let (dx, dy, dz) = (other[0] - point[0], other[1] - point[1], other[2] - point[2]);
+        dx * dx + dy * dy + dz * dz < radius_squared
+    }).count()
+}
+
+fn bench_search(c: &mut Criterion, name: &str, neighbour_search: NeighbourSearch)
+{
+    let mut group = c.benchmark_group(name);
+
+    for &size in SIZES.iter() {
+        let config = FlockConfig { num_birds: size, neighbour_search, ..FlockConfig::default() };
+        let initial = Flock::with_seed(config, SEED);
+
+        if size >= 10000 {
+            group.sample_size(10);
+        }
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &initial, |b, initial| {
+            b.iter_batched_ref(|| initial.clone(), |flock| flock.update(), BatchSize::LargeInput);
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, update_step, steady_state, neighbour_scan);
+criterion_main!(benches);
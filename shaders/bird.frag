@@ -0,0 +1,19 @@
+#version 140
+
+in vec3 v_normal;
+in vec3 v_colour;
+in float v_distance;
+out vec4 color;
+
+uniform vec3 light_dir;
+uniform vec3 light_color;
+uniform float ambient;
+uniform vec3 fog_color;
+uniform float fog_density;
+
+void main() {
+    float diffuse = max(dot(normalize(v_normal), light_dir), 0.0);
+    vec3 lit = v_colour * (ambient + (1.0 - ambient) * diffuse * light_color);
+    float fog = exp(-pow(fog_density * v_distance, 2.0));
+    color = vec4(mix(fog_color, lit, fog), 1.0);
+}
@@ -0,0 +1,25 @@
+#version 140
+
+in vec3 position;
+in vec3 normal;
+in vec3 offset;
+in mat3 orientation;
+in vec3 colour;
+in vec2 wing;
+
+out vec3 v_normal;
+out vec3 v_colour;
+out float v_distance;
+
+uniform mat4 view;
+uniform mat4 projection;
+uniform float flap_time;
+
+void main() {
+    float flap = sin(wing.x + wing.y * flap_time) * abs(position.x) * 0.8;
+    v_normal = transpose(inverse(orientation)) * normal;
+    v_colour = colour;
+    vec4 eye = view * vec4(orientation * (position + vec3(0.0, 0.0, flap)) + offset, 1.0);
+    v_distance = length(eye.xyz);
+    gl_Position = projection * eye;
+}
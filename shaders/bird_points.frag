@@ -0,0 +1,16 @@
+#version 140
+
+in vec3 v_colour;
+in float v_distance;
+out vec4 color;
+
+uniform vec3 light_color;
+uniform float ambient;
+uniform vec3 fog_color;
+uniform float fog_density;
+
+void main() {
+    vec3 lit = v_colour * (ambient + (1.0 - ambient) * 0.5 * light_color);
+    float fog = exp(-pow(fog_density * v_distance, 2.0));
+    color = vec4(mix(fog_color, lit, fog), 1.0);
+}
@@ -0,0 +1,17 @@
+#version 140
+
+in vec3 offset;
+in vec3 colour;
+
+out vec3 v_colour;
+out float v_distance;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    v_colour = colour;
+    vec4 eye = view * vec4(offset, 1.0);
+    v_distance = length(eye.xyz);
+    gl_Position = projection * eye;
+}
@@ -0,0 +1,28 @@
+#version 140
+
+in vec3 v_position;
+in vec3 v_normal;
+in float v_distance;
+out vec4 color;
+
+uniform vec3 light_dir;
+uniform vec3 light_color;
+uniform float ambient;
+uniform float base;
+uniform float relief;
+uniform vec3 fog_color;
+uniform float fog_density;
+
+void main() {
+    float t = relief > 0.0 ? clamp((v_position.y - base) / relief, 0.0, 1.0) : 0.0;
+    vec3 albedo = mix(vec3(0.18, 0.3, 0.15), vec3(0.45, 0.38, 0.28), t);
+
+    vec2 cell = abs(fract(v_position.xz - 0.5) - 0.5) / fwidth(v_position.xz);
+    float line = 1.0 - min(min(cell.x, cell.y), 1.0);
+    albedo = mix(albedo, vec3(0.6), line * 0.4);
+
+    float diffuse = max(dot(normalize(v_normal), light_dir), 0.0);
+    vec3 lit = albedo * (ambient + (1.0 - ambient) * diffuse * light_color);
+    float fog = exp(-pow(fog_density * v_distance, 2.0));
+    color = vec4(mix(fog_color, lit, fog), 1.0);
+}
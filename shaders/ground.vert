@@ -0,0 +1,19 @@
+#version 140
+
+in vec3 position;
+in vec3 normal;
+
+out vec3 v_position;
+out vec3 v_normal;
+out float v_distance;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    v_position = position;
+    v_normal = normal;
+    vec4 eye = view * vec4(position, 1.0);
+    v_distance = length(eye.xyz);
+    gl_Position = projection * eye;
+}
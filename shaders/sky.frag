@@ -0,0 +1,11 @@
+#version 140
+
+in vec3 v_direction;
+out vec4 color;
+
+uniform samplerCube sky;
+uniform float brightness;
+
+void main() {
+    color = vec4(texture(sky, normalize(v_direction)).rgb * brightness, 1.0);
+}
@@ -0,0 +1,14 @@
+#version 140
+
+in vec3 position;
+
+out vec3 v_direction;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    v_direction = position;
+    vec4 clip = projection * mat4(mat3(view)) * vec4(position, 1.0);
+    gl_Position = clip.xyww;
+}
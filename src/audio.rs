@@ -0,0 +1,109 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::Sample;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bird_flock::modulation::{BandAnalyser, BandEnergies};
+
+enum Source
+{
+    Device { _stream: cpal::Stream, buffer: Arc<Mutex<Vec<f32>>> },
+    File { samples: Vec<f32>, cursor: f64 },
+}
+
+pub struct AudioInput
+{
+    source: Source,
+    sample_rate: u32,
+    analyser: BandAnalyser,
+    chunk: Vec<f32>,
+}
+
+fn input_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, sink: Arc<Mutex<Vec<f32>>>) -> Result<cpal::Stream, String>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            if let Ok(mut samples) = sink.lock() {
+                samples.extend(data.chunks(channels).map(|frame| frame.iter().map(|&s| s.to_sample::<f32>()).sum::<f32>() / channels as f32));
+            }
+        },
+        |err| eprintln!("warning: audio input error: {}", err),
+        None,
+    ).map_err(|err| format!("could not open the audio input: {}", err))
+}
+
+impl AudioInput
+{
+    pub fn open_device() -> Result<AudioInput, String>
+    {
+        let device = cpal::default_host().default_input_device().ok_or_else(|| "no audio input device is available".to_string())?;
+        let supported = device.default_input_config().map_err(|err| format!("could not query the audio input: {}", err))?;
+        let sample_rate = supported.sample_rate().0;
+        let format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let stream = match format {
+            cpal::SampleFormat::F32 => input_stream::<f32>(&device, &config, buffer.clone())?,
+            cpal::SampleFormat::I16 => input_stream::<i16>(&device, &config, buffer.clone())?,
+            cpal::SampleFormat::U16 => input_stream::<u16>(&device, &config, buffer.clone())?,
+            format => return Err(format!("unsupported audio sample format {:?}", format)),
+        };
+        stream.play().map_err(|err| format!("could not start the audio input: {}", err))?;
+
+        Ok(AudioInput::new(Source::Device { _stream: stream, buffer }, sample_rate))
+    }
+
+    pub fn open_file<P: AsRef<Path>>(path: P) -> Result<AudioInput, String>
+    {
+        let path = path.as_ref();
+        let mut reader = hound::WavReader::open(path).map_err(|err| format!("could not open {}: {}", path.display(), err))?;
+        let spec = reader.spec();
+        let interleaved: Result<Vec<f32>, hound::Error> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect(),
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader.samples::<i32>().map(|sample| sample.map(|sample| sample as f32 / scale)).collect()
+            },
+        };
+        let interleaved = interleaved.map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+
+        let channels = spec.channels.max(1) as usize;
+        let samples: Vec<f32> = interleaved.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+        if samples.is_empty() {
+            return Err(format!("{} has no audio samples", path.display()));
+        }
+        Ok(AudioInput::new(Source::File { samples, cursor: 0.0 }, spec.sample_rate))
+    }
+
+    fn new(source: Source, sample_rate: u32) -> AudioInput
+    {
+        AudioInput { source, sample_rate, analyser: BandAnalyser::new(sample_rate), chunk: Vec::new() }
+    }
+
+    pub fn update(&mut self, elapsed: Duration) -> BandEnergies
+    {
+        self.chunk.clear();
+        match &mut self.source {
+            Source::Device { buffer, .. } => {
+                if let Ok(mut samples) = buffer.lock() {
+                    self.chunk.append(&mut samples);
+                }
+            },
+            Source::File { samples, cursor } => {
+                let count = (elapsed.as_secs_f64() * self.sample_rate as f64).min(samples.len() as f64);
+                let start = *cursor as usize;
+                *cursor = (*cursor + count) % samples.len() as f64;
+                self.chunk.extend(samples.iter().cycle().skip(start).take(count as usize));
+            },
+        }
+        self.analyser.push(&self.chunk);
+        self.analyser.energies()
+    }
+}
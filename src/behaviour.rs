@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bird::{Bird, BirdState};
+use crate::flock::Attractor;
+use crate::food::FoodSource;
+use crate::predator::{Predator, FEAR_RADIUS};
+
+pub const CALM_RADIUS: f32 = FEAR_RADIUS * 1.5;
+pub const HUNGRY_ENERGY: f32 = 0.9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BehaviourState
+{
+    #[default]
+    Flocking,
+    Fleeing,
+    Feeding,
+    Perching,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StateWeights
+{
+    pub flocking: f32,
+    pub wander: f32,
+    pub fear: f32,
+    pub attraction: f32,
+    pub guidance: f32,
+    pub speed: f32,
+}
+
+impl BehaviourState
+{
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            BehaviourState::Flocking => "flocking",
+            BehaviourState::Fleeing => "fleeing",
+            BehaviourState::Feeding => "feeding",
+            BehaviourState::Perching => "perching",
+        }
+    }
+
+    pub fn transition(self, bird: &Bird, predators: &[Predator], attractors: &[Attractor], food: &[FoodSource]) -> BehaviourState
+    {
+        if bird.state == BirdState::Perched {
+            return BehaviourState::Perching;
+        }
+
+        let fear_radius = if self == BehaviourState::Fleeing { CALM_RADIUS } else { FEAR_RADIUS };
+        if predators.iter().any(|predator| (predator.position - bird.position).norm() < fear_radius) {
+            return BehaviourState::Fleeing;
+        }
+
+        if bird.tired() {
+            return BehaviourState::Perching;
+        }
+
+        let hungry = if self == BehaviourState::Feeding { bird.energy < 1.0 } else { bird.energy < HUNGRY_ENERGY };
+        let near_attractor = attractors.iter().any(|attractor| attractor.strength > 0.0 && (attractor.position - bird.position).norm() < attractor.radius);
+        let near_food = near_attractor || food.iter().any(|source| source.in_reach(bird));
+        if hungry && near_food {
+            return BehaviourState::Feeding;
+        }
+
+        BehaviourState::Flocking
+    }
+
+    pub fn weights(self) -> StateWeights
+    {
+        match self {
+            BehaviourState::Flocking => StateWeights { flocking: 1.0, wander: 1.0, fear: 1.0, attraction: 1.0, guidance: 1.0, speed: 1.0 },
+            BehaviourState::Fleeing => StateWeights { flocking: 0.5, wander: 0.0, fear: 1.5, attraction: 0.0, guidance: 0.0, speed: 1.1 },
+            BehaviourState::Feeding => StateWeights { flocking: 0.3, wander: 0.5, fear: 1.0, attraction: 2.0, guidance: 0.0, speed: 0.5 },
+            BehaviourState::Perching => StateWeights { flocking: 0.5, wander: 0.0, fear: 1.0, attraction: 0.5, guidance: 0.5, speed: 1.0 },
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use clap::{Parser, ValueEnum};
+use bird_flock::flock::{Flock, FlockConfig, NeighbourSearch};
+use std::time::Instant;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format
+{
+    Csv,
+    Json,
+}
+
+#[derive(Parser)]
+#[command(about = "Headless benchmark of Flock::update")]
+struct Args
+{
+    #[arg(long, value_delimiter = ',', default_values_t = [100, 1000, 10000])]
+    sizes: Vec<usize>,
+
+    #[arg(long, default_value_t = 100)]
+    steps: usize,
+
+    #[arg(long)]
+    threads: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+
+    #[arg(long, default_value = "brute-force")]
+    neighbour_search: NeighbourSearch,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+struct BenchResult
+{
+    birds: usize,
+    steps: usize,
+    steps_per_sec: f64,
+    mean_ms: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64
+{
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[index.min(sorted.len() - 1)]
+}
+
+fn run(birds: usize, steps: usize, neighbour_search: NeighbourSearch, seed: u64) -> BenchResult
+{
+    let config = FlockConfig { num_birds: birds, neighbour_search, seed: Some(seed), ..FlockConfig::default() };
+    let mut flock = Flock::with_seed(config, seed);
+    let mut samples = Vec::with_capacity(steps);
+
+    let start = Instant::now();
+    for _ in 0..steps {
+        let step_start = Instant::now();
+        flock.update();
+        samples.push(step_start.elapsed().as_secs_f64() * 1000.0);
+    }
+    let total = start.elapsed().as_secs_f64();
+
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mean_ms = samples.iter().sum::<f64>() / samples.len().max(1) as f64;
+
+    BenchResult
+    {
+        birds,
+        steps,
+        steps_per_sec: if total > 0.0 { steps as f64 / total } else { 0.0 },
+        mean_ms,
+        p50_ms: percentile(&samples, 50.0),
+        p90_ms: percentile(&samples, 90.0),
+        p99_ms: percentile(&samples, 99.0),
+        max_ms: samples.last().copied().unwrap_or(0.0),
+    }
+}
+
+fn main()
+{
+    if let Err(err) = bench(Args::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(2);
+    }
+}
+
+fn bench(args: Args) -> Result<(), String>
+{
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().map_err(|err| format!("could not configure thread pool: {}", err))?;
+    }
+    let threads = rayon::current_num_threads();
+
+    let results: Vec<BenchResult> = args.sizes.iter().map(|&birds| run(birds, args.steps, args.neighbour_search, args.seed)).collect();
+
+    match args.format {
+        Format::Csv => {
+            println!("birds,threads,steps,steps_per_sec,mean_ms,p50_ms,p90_ms,p99_ms,max_ms");
+            for r in &results {
+                println!(
+                    "{},{},{},{:.3},{:.4},{:.4},{:.4},{:.4},{:.4}",
+                    r.birds, threads, r.steps, r.steps_per_sec, r.mean_ms, r.p50_ms, r.p90_ms, r.p99_ms, r.max_ms
+                );
+            }
+        },
+        Format::Json => {
+            let rows: Vec<String> = results.iter().map(|r| {
+                format!(
+                    "{{\"birds\":{},\"threads\":{},\"steps\":{},\"steps_per_sec\":{:.3},\"mean_ms\":{:.4},\"p50_ms\":{:.4},\"p90_ms\":{:.4},\"p99_ms\":{:.4},\"max_ms\":{:.4}}}",
+                    r.birds, threads, r.steps, r.steps_per_sec, r.mean_ms, r.p50_ms, r.p90_ms, r.p99_ms, r.max_ms
+                )
+            }).collect();
+            println!("[{}]", rows.join(","));
+        },
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn percentile_picks_the_nearest_rank()
+    {
+        let sorted: Vec<f64> = (1..=11).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 50.0), 6.0);
+        assert_eq!(percentile(&sorted, 90.0), 10.0);
+        assert_eq!(percentile(&sorted, 100.0), 11.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn run_reports_ordered_percentiles()
+    {
+        let result = run(20, 10, NeighbourSearch::Grid, 3);
+        assert_eq!((result.birds, result.steps), (20, 10));
+        assert!(result.p50_ms <= result.p90_ms && result.p90_ms <= result.p99_ms && result.p99_ms <= result.max_ms);
+        assert!(result.mean_ms <= result.max_ms);
+    }
+}
@@ -0,0 +1,180 @@
+use clap::Parser;
+use nalgebra::{Perspective3, Vector3};
+use std::f32::consts::FRAC_PI_3;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::window::{Window, WindowId};
+
+use bird_flock::draw::{BirdInstance, FlockRenderer, FrameUniforms, OrbitCamera};
+use bird_flock::timestep::FixedTimestep;
+use bird_flock::wgpu_render::WgpuRenderer;
+use bird_flock::{Flock, FlockConfig};
+
+const MAX_STEPS: u32 = 5;
+const LIGHT_DIRECTION: [f32; 3] = [0.4, 1.0, 0.3];
+const BIRD_COLOUR: [f32; 3] = [1.0, 0.0, 0.0];
+
+#[derive(Parser)]
+#[command(about = "Bird flock simulation drawn with the wgpu renderer")]
+struct Args
+{
+    #[arg(long, default_value_t = 1000)]
+    num_birds: usize,
+
+    #[arg(long, default_value_t = 60.0)]
+    sim_hz: f64,
+
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+struct Running
+{
+    window: Arc<Window>,
+    renderer: WgpuRenderer,
+}
+
+struct App
+{
+    running: Option<Running>,
+    flock: Flock,
+    timestep: FixedTimestep,
+    last_frame: Instant,
+    instances: Vec<BirdInstance>,
+    camera: OrbitCamera,
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+}
+
+impl App
+{
+    fn new(args: &Args) -> App
+    {
+        let config = FlockConfig { num_birds: args.num_birds, ..FlockConfig::default() };
+        let flock = match args.seed {
+            Some(seed) => Flock::with_seed(config, seed),
+            None => Flock::new(config),
+        };
+        App
+        {
+            running: None,
+            flock,
+            timestep: FixedTimestep::new(args.sim_hz, MAX_STEPS),
+            last_frame: Instant::now(),
+            instances: Vec::new(),
+            camera: OrbitCamera::default(),
+            dragging: false,
+            cursor: None,
+        }
+    }
+
+    fn start(&mut self, event_loop: &ActiveEventLoop) -> Result<Running, String>
+    {
+        let attributes = Window::default_attributes().with_title("Bird Flock Simulation (wgpu)");
+        let window = Arc::new(event_loop.create_window(attributes).map_err(|err| err.to_string())?);
+        let renderer = pollster::block_on(WgpuRenderer::new(window.clone()))?;
+        window.request_redraw();
+        Ok(Running { window, renderer })
+    }
+
+    fn redraw(&mut self) -> Result<(), String>
+    {
+        let running = match &mut self.running {
+            Some(running) => running,
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+        for _ in 0..self.timestep.advance(now - self.last_frame) {
+            self.flock.step(self.timestep.step().as_secs_f32());
+        }
+        self.last_frame = now;
+        let alpha = self.timestep.alpha();
+
+        let max_speed = self.flock.config.max_speed;
+        self.instances.clear();
+        self.instances.extend(self.flock.birds.iter().map(|bird| {
+            BirdInstance::from_bird(bird, alpha, max_speed, BIRD_COLOUR, 1.0)
+        }));
+
+        let size = running.window.inner_size();
+        let aspect = size.width.max(1) as f32 / size.height.max(1) as f32;
+        let projection = Perspective3::new(aspect, FRAC_PI_3, 0.1, 100.0).to_homogeneous();
+        let uniforms = FrameUniforms
+        {
+            view: *self.camera.view().as_ref(),
+            projection: *projection.as_ref(),
+            light_dir: Vector3::from(LIGHT_DIRECTION).normalize().into(),
+            light_colour: [1.0, 0.96, 0.9],
+            ambient: 0.3,
+            flap_time: alpha * self.timestep.step().as_secs_f32(),
+        };
+
+        let mut frame = running.renderer.begin_frame()?;
+        running.renderer.draw_birds(&mut frame, &self.instances, &uniforms)?;
+        running.window.pre_present_notify();
+        frame.present();
+        running.window.request_redraw();
+        Ok(())
+    }
+}
+
+impl ApplicationHandler for App
+{
+    fn resumed(&mut self, event_loop: &ActiveEventLoop)
+    {
+        if self.running.is_some() {
+            return;
+        }
+        match self.start(event_loop) {
+            Ok(running) => self.running = Some(running),
+            Err(err) => {
+                eprintln!("{}", err);
+                event_loop.exit();
+            },
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent)
+    {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => {
+                if let Some(running) = &mut self.running {
+                    running.renderer.resize(size.width, size.height);
+                }
+            },
+            WindowEvent::RedrawRequested => {
+                if let Err(err) = self.redraw() {
+                    eprintln!("{}", err);
+                    event_loop.exit();
+                }
+            },
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => self.dragging = state == ElementState::Pressed,
+            WindowEvent::CursorMoved { position, .. } => {
+                if let (true, Some((x, y))) = (self.dragging, self.cursor) {
+                    self.camera.drag((position.x - x) as f32, (position.y - y) as f32);
+                }
+                self.cursor = Some((position.x, position.y));
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+                };
+                self.camera.zoom(steps);
+            },
+            _ => (),
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let event_loop = EventLoop::new().expect("could not create the event loop");
+    let mut app = App::new(&args);
+    event_loop.run_app(&mut app).expect("event loop failed");
+}
@@ -0,0 +1,354 @@
+use nalgebra::Vector3;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::behaviour::BehaviourState;
+use crate::flock::{normalize_or_zero, positive, Attractor, BoundaryShape, FlockConfig, Obstacle, ATTRACTOR_RADIUS, PERCH_HEIGHT, PERCH_SPEED, REFERENCE_DT};
+use crate::predator::{Predator, FEAR_RADIUS};
+use crate::steering;
+use crate::terrain::Terrain;
+
+pub const FLAP_FREQUENCY: f32 = 3.0;
+pub const FLAP_SPEED_FREQUENCY: f32 = 5.0;
+pub const TIRED_ENERGY: f32 = 0.25;
+pub const PERCH_REACH: f32 = 0.15;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BirdState
+{
+    #[default]
+    Flying,
+    Perched,
+}
+
+fn full_energy() -> f32
+{
+    1.0
+}
+
+fn average_trait() -> f32
+{
+    1.0
+}
+
+fn vary<R: Rng + ?Sized>(rng: &mut R, variance: f32) -> f32
+{
+    if variance > 0.0 {
+        1.0 + (rng.gen::<f32>() * 2.0 - 1.0) * variance
+    } else {
+        1.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bird
+{
+    pub position: Vector3<f32>,
+    #[serde(default = "Vector3::zeros")]
+    pub velocity: Vector3<f32>,
+    #[serde(default = "Vector3::zeros")]
+    pub acceleration: Vector3<f32>,
+    #[serde(default)]
+    pub neighbour_count: u32,
+    #[serde(default = "Vector3::zeros")]
+    pub previous_position: Vector3<f32>,
+    #[serde(default)]
+    pub species: u8,
+    #[serde(default)]
+    pub state: BirdState,
+    /// Reference steps (1/60 s each) left on the perch, counted down by the
+    /// step's scale so a perch lasts as long at any timestep.
+    #[serde(default)]
+    pub perch_timer: f32,
+    #[serde(default = "Vector3::zeros")]
+    pub steering: Vector3<f32>,
+    #[serde(default)]
+    pub leader: bool,
+    #[serde(default)]
+    pub wing_phase: f32,
+    #[serde(default = "full_energy")]
+    pub energy: f32,
+    #[serde(default = "Vector3::zeros")]
+    pub wander: Vector3<f32>,
+    #[serde(default = "average_trait")]
+    pub mass: f32,
+    #[serde(default = "average_trait")]
+    pub speed_factor: f32,
+    #[serde(default = "average_trait")]
+    pub sociability: f32,
+    #[serde(default)]
+    pub behaviour: BehaviourState,
+}
+
+impl Bird
+{
+    pub fn new(config: &FlockConfig) -> Bird
+    {
+        Bird::with_rng(config, &mut rand::thread_rng())
+    }
+
+    pub fn with_rng<R: Rng + ?Sized>(config: &FlockConfig, rng: &mut R) -> Bird
+    {
+        let size = match config.boundary_shape {
+            BoundaryShape::Cube | BoundaryShape::Wrap | BoundaryShape::Unbounded => config.boundary_size,
+            BoundaryShape::Sphere { radius } | BoundaryShape::Soft { radius, .. } => radius * 2.0 / 3f32.sqrt(),
+        };
+        let pos_x = rng.gen::<f32>() * size - size/2.0;
+        let pos_y = rng.gen::<f32>() * size - size/2.0;
+        let pos_z = rng.gen::<f32>() * size - size/2.0;
+        
+        let vel_x = rng.gen::<f32>() * 0.02 - 0.01;
+        let vel_y = rng.gen::<f32>() * 0.02 - 0.01;
+        let vel_z = rng.gen::<f32>() * 0.02 - 0.01;
+
+        let mut position = Vector3::new(pos_x, pos_y, pos_z);
+        if let BoundaryShape::Soft { home, .. } = config.boundary_shape {
+            position += home;
+        }
+        if let Some(spawn) = &config.spawn {
+            position = spawn.sample(rng);
+        }
+        let mut velocity = Vector3::new(vel_x, vel_y, vel_z);
+        if let Some(plane) = config.plane {
+            position = plane.flatten(position);
+            velocity = plane.flatten(velocity);
+        }
+        
+        let mut bird = Bird
+        {
+            position,
+            velocity,
+            acceleration: Vector3::zeros(),
+            neighbour_count: 0,
+            previous_position: position,
+            species: 0,
+            state: BirdState::Flying,
+            perch_timer: 0.0,
+            steering: Vector3::zeros(),
+            leader: false,
+            wing_phase: rng.gen::<f32>() * TAU,
+            energy: 0.5 + rng.gen::<f32>() * 0.5,
+            wander: Vector3::zeros(),
+            mass: 1.0,
+            speed_factor: 1.0,
+            sociability: 1.0,
+            behaviour: BehaviourState::Flocking,
+        };
+        bird.mass = vary(rng, config.mass_variance);
+        bird.speed_factor = vary(rng, config.speed_variance);
+        bird.sociability = vary(rng, config.sociability_variance);
+        bird
+    }
+
+    pub fn update(&mut self, config: &FlockConfig, roll: f32, scale: f32)
+    {
+        let half_size = config.boundary_size / 2.0;
+
+        if self.state == BirdState::Perched {
+            self.acceleration = Vector3::zeros();
+            self.steering = Vector3::zeros();
+            self.velocity = Vector3::zeros();
+            self.energy = (self.energy + config.energy_recovery * scale).min(1.0);
+
+            if self.perch_timer > 0.0 {
+                self.perch_timer -= scale;
+            } else {
+                self.take_off(config);
+            }
+            return;
+        }
+
+        if self.landing(config) {
+            self.perch(config, roll);
+            self.settle_on_floor(config);
+            return;
+        }
+
+        let max_speed = config.max_speed * self.speed_factor * self.behaviour.weights().speed;
+        self.velocity = (self.velocity + self.acceleration / self.mass * scale).cap_magnitude(max_speed);
+        self.position += self.velocity * scale;
+        self.steering = self.acceleration;
+        self.acceleration = Vector3::zeros();
+        self.wing_phase = (self.wing_phase + self.flap_frequency(config.max_speed) * TAU * REFERENCE_DT * scale) % TAU;
+        let effort = self.flap_frequency(config.max_speed) / (FLAP_FREQUENCY + FLAP_SPEED_FREQUENCY);
+        if self.behaviour == BehaviourState::Feeding {
+            self.energy = (self.energy + config.energy_recovery * scale).min(1.0);
+        } else {
+            self.energy = (self.energy - config.energy_drain * effort * scale).max(0.0);
+        }
+
+        if config.boundary_shape == BoundaryShape::Cube {
+            for i in 0..3
+            {
+                if self.position[i].abs() > half_size {
+                    self.velocity[i] = -self.velocity[i] * 0.8;
+                    if self.position[i] > 0.0 {
+                        self.position[i] = half_size;
+                    } else {
+                        self.position[i] = -half_size;
+                    }
+                }
+            }
+        } else if config.boundary_shape == BoundaryShape::Wrap {
+            for i in 0..3
+            {
+                if self.position[i].abs() > half_size {
+                    let shift = self.position[i].signum() * config.boundary_size;
+                    self.position[i] -= shift;
+                    self.previous_position[i] -= shift;
+                }
+            }
+        }
+
+        let on_floor = match config.boundary_shape {
+            BoundaryShape::Cube => self.position.y <= -half_size,
+            BoundaryShape::Wrap | BoundaryShape::Unbounded | BoundaryShape::Soft { .. } => false,
+            BoundaryShape::Sphere { radius } => {
+                let dist = self.position.norm();
+                if dist > radius {
+                    let normal = self.position / dist;
+                    let outward = self.velocity.dot(&normal);
+                    if outward > 0.0 {
+                        self.velocity -= normal * (outward * 1.8);
+                    }
+                    self.position = normal * radius;
+                }
+                dist >= radius && self.position.y < -0.7 * radius
+            },
+        };
+
+        if config.perch_probability <= 0.0 && !self.tired() {
+            return;
+        }
+
+        if self.landing(config) || (on_floor && roll < config.perch_probability) {
+            self.perch(config, roll);
+            self.settle_on_floor(config);
+        }
+    }
+
+    /// A slow or tired bird within `PERCH_HEIGHT` of the floor lands. This is
+    /// checked before integrating as well, so the floor's boundary push cannot
+    /// throw a slow bird back up before it has a chance to settle.
+    fn landing(&self, config: &FlockConfig) -> bool
+    {
+        if config.perch_probability <= 0.0 && !self.tired() {
+            return false;
+        }
+
+        let near_floor = match config.boundary_shape {
+            BoundaryShape::Cube => self.position.y <= -config.boundary_size / 2.0 + PERCH_HEIGHT,
+            BoundaryShape::Wrap | BoundaryShape::Unbounded | BoundaryShape::Soft { .. } => false,
+            BoundaryShape::Sphere { radius } => self.position.norm() >= radius - PERCH_HEIGHT && self.position.y < -0.7 * radius,
+        };
+        near_floor && (self.tired() || self.speed() < PERCH_SPEED)
+    }
+
+    fn settle_on_floor(&mut self, config: &FlockConfig)
+    {
+        match config.boundary_shape {
+            BoundaryShape::Sphere { radius } => self.position = normalize_or_zero(self.position) * radius,
+            _ => self.position.y = -config.boundary_size / 2.0,
+        }
+    }
+
+    pub fn perch(&mut self, config: &FlockConfig, roll: f32)
+    {
+        let rest = if roll < config.perch_probability {
+            roll / config.perch_probability
+        } else {
+            (roll - config.perch_probability) / (1.0 - config.perch_probability)
+        };
+        let span = config.perch_max_steps - config.perch_min_steps;
+        let mut timer = config.perch_min_steps as f32 + rest * span as f32;
+        // A tired bird stays until it has fully recovered; one that perched by
+        // chance leaves when its rest is up, whatever its energy.
+        if self.tired() && positive(config.energy_recovery) {
+            timer = timer.max((1.0 - self.energy) / config.energy_recovery);
+        }
+        self.state = BirdState::Perched;
+        self.perch_timer = timer;
+        self.velocity = Vector3::zeros();
+        self.wing_phase = 0.0;
+    }
+
+    pub fn take_off(&mut self, config: &FlockConfig)
+    {
+        self.state = BirdState::Flying;
+        self.perch_timer = 0.0;
+        self.velocity = Vector3::new(0.0, config.max_speed, 0.0);
+    }
+
+    pub fn tired(&self) -> bool
+    {
+        self.state == BirdState::Flying && self.energy < TIRED_ENERGY
+    }
+
+    pub fn perch_site(&self, config: &FlockConfig, obstacles: &[Obstacle], terrain: Option<&Terrain>) -> Option<Vector3<f32>>
+    {
+        let floor = match config.boundary_shape {
+            BoundaryShape::Cube => Some(Vector3::new(self.position.x, -config.boundary_size / 2.0, self.position.z)),
+            BoundaryShape::Sphere { radius } => Some(Vector3::new(0.0, -radius, 0.0)),
+            BoundaryShape::Wrap | BoundaryShape::Unbounded | BoundaryShape::Soft { .. } => None,
+        };
+        let ground = terrain.map(|terrain| Vector3::new(self.position.x, terrain.height_at(self.position.x, self.position.z), self.position.z));
+
+        floor.into_iter()
+            .chain(ground)
+            .chain(obstacles.iter().map(|obstacle| obstacle.top()))
+            .min_by(|a, b| (*a - self.position).norm_squared().partial_cmp(&(*b - self.position).norm_squared()).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    pub fn disturbed(&self, attractors: &[Attractor], herding: Option<Vector3<f32>>, predators: &[Predator]) -> bool
+    {
+        let near_attractor = attractors.iter().any(|attractor| (attractor.position - self.position).norm() < attractor.radius);
+        let near_herding = herding.is_some_and(|point| (point - self.position).norm() < ATTRACTOR_RADIUS);
+        let near_predator = predators.iter().any(|predator| (predator.position - self.position).norm() < FEAR_RADIUS);
+        near_attractor || near_herding || near_predator
+    }
+
+    pub fn apply_force(&mut self, force: Vector3<f32>)
+    {
+        self.acceleration += force;
+    }
+
+    pub fn steer(&self, desired: Vector3<f32>, max_speed: f32) -> Vector3<f32>
+    {
+        steering::steer(desired, self.velocity, max_speed)
+    }
+
+    pub fn speed(&self) -> f32
+    {
+        self.velocity.norm()
+    }
+
+    pub fn flap_frequency(&self, max_speed: f32) -> f32
+    {
+        if self.state == BirdState::Perched {
+            return 0.0;
+        }
+        FLAP_FREQUENCY + FLAP_SPEED_FREQUENCY * (self.speed() / max_speed.max(1e-6)).min(1.0)
+    }
+
+    pub fn interpolated_position(&self, alpha: f32) -> Vector3<f32>
+    {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+
+    pub fn can_see(&self, point: Vector3<f32>, view_cos: f32) -> bool
+    {
+        let heading = normalize_or_zero(self.velocity);
+        let to_point = normalize_or_zero(point - self.position);
+        if view_cos <= -1.0 || heading == Vector3::zeros() || to_point == Vector3::zeros() {
+            return true;
+        }
+
+        heading.dot(&to_point) >= view_cos
+    }
+
+    pub fn distance_to(&self, other: &Bird) -> f32 {
+        (self.position - other.position).norm()
+    }
+}
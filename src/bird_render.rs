@@ -0,0 +1,200 @@
+use glium::backend::{Context, Facade};
+use glium::vertex::VertexBufferSlice;
+use glium::{Frame, Program, Surface, VertexBuffer};
+use std::rc::Rc;
+
+use bird_flock::draw::{self, FlockRenderer, FrameUniforms};
+
+use crate::mesh::{self, LitVertex};
+use crate::render;
+use crate::shaders::ShaderSet;
+use crate::sky::Fog;
+
+const LOD_MIN_BIRDS: usize = 10_000;
+const LOD_NEAR: f32 = 10.0;
+const LOD_FAR: f32 = 25.0;
+const POINT_SIZE: f32 = 2.0;
+const INSTANCE_BUFFERS: usize = 3;
+
+#[derive(Copy, Clone)]
+struct BirdInstance
+{
+    offset: [f32; 3],
+    orientation: [[f32; 3]; 3],
+    colour: [f32; 3],
+    wing: [f32; 2],
+}
+
+implement_vertex!(BirdInstance, offset, orientation, colour, wing);
+
+struct InstanceBucket
+{
+    instances: Vec<BirdInstance>,
+    buffers: [Option<VertexBuffer<BirdInstance>>; INSTANCE_BUFFERS],
+    current: usize,
+}
+
+impl InstanceBucket
+{
+    fn new() -> InstanceBucket
+    {
+        InstanceBucket { instances: Vec::new(), buffers: [None, None, None], current: 0 }
+    }
+
+    fn upload(&mut self, context: &Rc<Context>) -> Result<Option<VertexBufferSlice<'_, BirdInstance>>, String>
+    {
+        let count = self.instances.len();
+        if count == 0 {
+            return Ok(None);
+        }
+
+        self.current = (self.current + 1) % INSTANCE_BUFFERS;
+        let buffer = &mut self.buffers[self.current];
+        let needs_buffer = match buffer {
+            Some(buffer) => buffer.len() < count,
+            None => true,
+        };
+        if needs_buffer {
+            let capacity = count.next_power_of_two();
+            let created = match VertexBuffer::empty_persistent(context, capacity) {
+                Ok(persistent) => persistent,
+                Err(_) => VertexBuffer::empty_dynamic(context, capacity).map_err(|err| err.to_string())?,
+            };
+            *buffer = Some(created);
+        }
+        let slice = buffer.as_ref().and_then(|buffer| buffer.slice(0..count));
+        if let Some(slice) = &slice {
+            slice.write(&self.instances);
+        }
+        Ok(slice)
+    }
+}
+
+pub struct BirdRenderer
+{
+    context: Rc<Context>,
+    shape: VertexBuffer<LitVertex>,
+    silhouette: VertexBuffer<LitVertex>,
+    near: InstanceBucket,
+    mid: InstanceBucket,
+    far: InstanceBucket,
+    program: Program,
+    point_program: Program,
+    params: glium::DrawParameters<'static>,
+    pub fog: Fog,
+    pub viewport: Option<glium::Rect>,
+}
+
+impl BirdRenderer
+{
+    pub fn new<F: ?Sized + Facade>(facade: &F, params: glium::DrawParameters<'static>, shaders: &ShaderSet) -> Result<BirdRenderer, String>
+    {
+        let program = render::compile_program(facade, "bird", &shaders.bird.vertex, &shaders.bird.fragment)?;
+        let point_program = render::compile_program(facade, "bird point", &shaders.bird_points.vertex, &shaders.bird_points.fragment)?;
+        let shape = VertexBuffer::new(facade, &mesh::bird_mesh()).map_err(|err| err.to_string())?;
+        let silhouette = VertexBuffer::new(facade, &mesh::bird_silhouette()).map_err(|err| err.to_string())?;
+
+        Ok(BirdRenderer
+        {
+            context: facade.get_context().clone(),
+            shape,
+            silhouette,
+            near: InstanceBucket::new(),
+            mid: InstanceBucket::new(),
+            far: InstanceBucket::new(),
+            program,
+            point_program,
+            params,
+            fog: Fog::default(),
+            viewport: None,
+        })
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn reload<F: ?Sized + Facade>(&mut self, facade: &F, shaders: &ShaderSet) -> Result<(), String>
+    {
+        let program = render::compile_program(facade, "bird", &shaders.bird.vertex, &shaders.bird.fragment)?;
+        self.point_program = render::compile_program(facade, "bird point", &shaders.bird_points.vertex, &shaders.bird_points.fragment)?;
+        self.program = program;
+        Ok(())
+    }
+}
+
+fn view_depth(view: &[[f32; 4]; 4], offset: [f32; 3]) -> f32
+{
+    -(view[0][2] * offset[0] + view[1][2] * offset[1] + view[2][2] * offset[2] + view[3][2])
+}
+
+impl FlockRenderer for BirdRenderer
+{
+    type Frame = Frame;
+
+    fn draw_birds(&mut self, target: &mut Frame, birds: &[draw::BirdInstance], uniforms: &FrameUniforms) -> Result<(), String>
+    {
+        if birds.is_empty() {
+            return Ok(());
+        }
+
+        self.near.instances.clear();
+        self.mid.instances.clear();
+        self.far.instances.clear();
+        let lod = birds.len() >= LOD_MIN_BIRDS;
+        for bird in birds {
+            let instance = BirdInstance { offset: bird.offset, orientation: bird.orientation, colour: bird.colour, wing: bird.wing };
+            let depth = if lod { view_depth(&uniforms.view, bird.offset) } else { 0.0 };
+            if depth < LOD_NEAR {
+                self.near.instances.push(instance);
+            } else if depth < LOD_FAR {
+                self.mid.instances.push(instance);
+            } else {
+                self.far.instances.push(instance);
+            }
+        }
+
+        let bird_uniforms = uniform! {
+            view: uniforms.view,
+            projection: uniforms.projection,
+            light_dir: uniforms.light_dir,
+            light_color: uniforms.light_colour,
+            ambient: uniforms.ambient,
+            flap_time: uniforms.flap_time,
+            fog_color: self.fog.colour,
+            fog_density: self.fog.density,
+        };
+        let triangles = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+        let base_params = glium::DrawParameters { viewport: self.viewport, .. self.params.clone() };
+
+        if let Some(slice) = self.near.upload(&self.context)? {
+            let instances = slice.per_instance().map_err(|_| "instanced drawing is not supported".to_string())?;
+            target.draw((&self.shape, instances), triangles, &self.program, &bird_uniforms, &base_params).map_err(|err| err.to_string())?;
+        }
+
+        if let Some(slice) = self.mid.upload(&self.context)? {
+            let instances = slice.per_instance().map_err(|_| "instanced drawing is not supported".to_string())?;
+            let params = glium::DrawParameters {
+                backface_culling: glium::draw_parameters::BackfaceCullingMode::CullingDisabled,
+                .. base_params.clone()
+            };
+            target.draw((&self.silhouette, instances), triangles, &self.program, &bird_uniforms, &params).map_err(|err| err.to_string())?;
+        }
+
+        if let Some(slice) = self.far.upload(&self.context)? {
+            let point_uniforms = uniform! {
+                view: uniforms.view,
+                projection: uniforms.projection,
+                light_color: uniforms.light_colour,
+                ambient: uniforms.ambient,
+                fog_color: self.fog.colour,
+                fog_density: self.fog.density,
+            };
+            let params = glium::DrawParameters {
+                point_size: Some(POINT_SIZE),
+                .. base_params.clone()
+            };
+            let points = glium::index::NoIndices(glium::index::PrimitiveType::Points);
+            target.draw(slice, points, &self.point_program, &point_uniforms, &params).map_err(|err| err.to_string())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use bird_flock::flock::Plane;
+
+const ORBIT_SENSITIVITY: f32 = 0.01;
+const ZOOM_STEP: f32 = 0.1;
+const PAN_SENSITIVITY: f32 = 0.002;
+const MOVE_SPEED: f32 = 0.05;
+const LOOK_SENSITIVITY: f32 = 0.003;
+const FLY_SPEED: f32 = 0.04;
+const FLY_BOOST: f32 = 4.0;
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 50.0;
+const MAX_PITCH: f32 = 1.5;
+const FOLLOW_DISTANCE: f32 = 0.6;
+const FOLLOW_HEIGHT: f32 = 0.2;
+const FOLLOW_STIFFNESS: f32 = 20.0;
+const MAX_FOLLOW_DT: f32 = 0.1;
+
+pub struct Follow
+{
+    pub index: usize,
+    eye: Point3<f32>,
+    velocity: Vector3<f32>,
+    focus: Point3<f32>,
+    heading: Vector3<f32>,
+}
+
+impl Follow
+{
+    pub fn new(index: usize, eye: Point3<f32>, focus: Point3<f32>) -> Follow
+    {
+        Follow { index, eye, velocity: Vector3::zeros(), focus, heading: (focus - eye).normalize() }
+    }
+
+    pub fn update(&mut self, position: Vector3<f32>, velocity: Vector3<f32>, dt: f32)
+    {
+        if let Some(heading) = velocity.try_normalize(1e-6) {
+            self.heading = heading;
+        }
+
+        let dt = dt.min(MAX_FOLLOW_DT);
+        let desired = Point3::from(position - self.heading * FOLLOW_DISTANCE + Vector3::y() * FOLLOW_HEIGHT);
+        let damping = 2.0 * FOLLOW_STIFFNESS.sqrt();
+        let acceleration = (desired - self.eye) * FOLLOW_STIFFNESS - self.velocity * damping;
+
+        self.velocity += acceleration * dt;
+        self.eye += self.velocity * dt;
+        self.focus = Point3::from(position);
+    }
+}
+
+pub struct Camera
+{
+    pub target: Point3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub free: bool,
+    pub follow: Option<Follow>,
+}
+
+impl Camera
+{
+    pub fn look(&self) -> Vector3<f32>
+    {
+        -Vector3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    pub fn eye(&self) -> Point3<f32>
+    {
+        if let Some(follow) = &self.follow {
+            follow.eye
+        } else if self.free {
+            self.target
+        } else {
+            self.target - self.look() * self.distance
+        }
+    }
+
+    pub fn focus(&self) -> Point3<f32>
+    {
+        if let Some(follow) = &self.follow {
+            follow.focus
+        } else if self.free {
+            self.target + self.look() * self.distance
+        } else {
+            self.target
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32>
+    {
+        Matrix4::look_at_rh(&self.eye(), &self.focus(), &Vector3::y())
+    }
+
+    pub fn follow(&mut self, index: usize)
+    {
+        self.follow = Some(Follow::new(index, self.eye(), self.focus()));
+    }
+
+    pub fn face_plane(&mut self, plane: Plane)
+    {
+        self.yaw = 0.0;
+        self.pitch = match plane {
+            Plane::Xz => MAX_PITCH,
+            Plane::Xy => 0.0,
+        };
+    }
+
+    pub fn toggle_free(&mut self)
+    {
+        self.target = if self.free { self.focus() } else { self.eye() };
+        self.free = !self.free;
+    }
+
+    pub fn ray(&self, cursor: (f64, f64), size: (u32, u32), projection: &Matrix4<f32>) -> Option<(Vector3<f32>, Vector3<f32>)>
+    {
+        if size.0 == 0 || size.1 == 0 {
+            return None;
+        }
+
+        let ndc_x = (2.0 * cursor.0 / size.0 as f64 - 1.0) as f32;
+        let ndc_y = (1.0 - 2.0 * cursor.1 / size.1 as f64) as f32;
+        if ndc_x.abs() > 1.0 || ndc_y.abs() > 1.0 {
+            return None;
+        }
+
+        let inverse = (projection * self.view_matrix()).try_inverse()?;
+        let near = inverse * Vector4::new(ndc_x, ndc_y, -1.0, 1.0);
+        let far = inverse * Vector4::new(ndc_x, ndc_y, 1.0, 1.0);
+        let near = near.xyz() / near.w;
+        let far = far.xyz() / far.w;
+        Some((near, far - near))
+    }
+
+    pub fn unproject(&self, cursor: (f64, f64), size: (u32, u32), projection: &Matrix4<f32>) -> Option<Vector3<f32>>
+    {
+        let (near, ray) = self.ray(cursor, size, projection)?;
+        let normal = self.focus() - self.eye();
+        let denom = ray.dot(&normal);
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+
+        let t = (self.focus().coords - near).dot(&normal) / denom;
+        Some(near + ray * t)
+    }
+
+    pub fn orbit(&mut self, dx: f32, dy: f32)
+    {
+        self.yaw -= dx * ORBIT_SENSITIVITY;
+        self.pitch = (self.pitch + dy * ORBIT_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn turn(&mut self, dx: f32, dy: f32)
+    {
+        self.yaw -= dx * LOOK_SENSITIVITY;
+        self.pitch = (self.pitch + dy * LOOK_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn zoom(&mut self, steps: f32)
+    {
+        self.distance = (self.distance * (1.0 - steps * ZOOM_STEP)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32)
+    {
+        let forward = self.look();
+        let right = forward.cross(&Vector3::y()).normalize();
+        let up = right.cross(&forward);
+        let step = PAN_SENSITIVITY * self.distance;
+        self.target += (up * dy - right * dx) * step;
+    }
+
+    pub fn translate(&mut self, forward: f32, right: f32)
+    {
+        let forward_dir = Vector3::new(-self.yaw.sin(), 0.0, -self.yaw.cos());
+        let right_dir = Vector3::new(self.yaw.cos(), 0.0, -self.yaw.sin());
+        let step = MOVE_SPEED * self.distance / 5.0;
+        self.target += forward_dir * forward * step + right_dir * right * step;
+    }
+
+    pub fn fly(&mut self, forward: f32, right: f32, fast: bool)
+    {
+        let forward_dir = self.look();
+        let right_dir = forward_dir.cross(&Vector3::y()).normalize();
+        let step = if fast { FLY_SPEED * FLY_BOOST } else { FLY_SPEED };
+        self.target += forward_dir * forward * step + right_dir * right * step;
+    }
+
+    pub fn travel(&mut self, forward: f32, right: f32, fast: bool)
+    {
+        if self.free {
+            self.fly(forward, right, fast);
+        } else {
+            self.translate(forward, right);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MoveKeys
+{
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl MoveKeys
+{
+    pub fn apply(&self, camera: &mut Camera, fast: bool)
+    {
+        let forward = self.forward as i32 - self.back as i32;
+        let right = self.right as i32 - self.left as i32;
+        if forward == 0 && right == 0 {
+            return;
+        }
+
+        camera.travel(forward as f32, right as f32, fast);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glium::texture::RawImage2d;
+
+pub const CAPTURE_DIR: &str = "captures";
+pub const SCREENSHOT_DIR: &str = "screenshots";
+pub const CAPTURE_QUEUE: usize = 8;
+pub const CAPTURE_FPS: f64 = 60.0;
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mkv", "mov", "webm"];
+
+struct CapturedFrame
+{
+    index: u32,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+pub fn save_png(raw: RawImage2d<'_, u8>, path: &Path) -> Result<(), String>
+{
+    save_pixels(raw.width, raw.height, raw.data.into_owned(), path)
+}
+
+fn save_pixels(width: u32, height: u32, data: Vec<u8>, path: &Path) -> Result<(), String>
+{
+    let image = image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(width, height, data)
+        .ok_or_else(|| "framebuffer size does not match its pixel data".to_string())?;
+    let image = image::DynamicImage::ImageRgba8(image).flipv();
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+    }
+    image.save(path).map_err(|err| err.to_string())
+}
+
+pub fn screenshot_path() -> PathBuf
+{
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    Path::new(SCREENSHOT_DIR).join(format!("screenshot_{}.png", millis))
+}
+
+pub fn frame_path(dir: &Path, index: u32) -> PathBuf
+{
+    dir.join(format!("frame_{:05}.png", index))
+}
+
+pub fn is_video(path: &Path) -> bool
+{
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| VIDEO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+fn write_frames(receiver: Receiver<CapturedFrame>, dir: &Path) -> Result<(), String>
+{
+    for frame in receiver {
+        let path = frame_path(dir, frame.index);
+        save_pixels(frame.width, frame.height, frame.data, &path)
+            .map_err(|err| format!("could not save {}: {}", path.display(), err))?;
+    }
+    Ok(())
+}
+
+fn start_ffmpeg(path: &Path, width: u32, height: u32, fps: f64) -> Result<Child, String>
+{
+    Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &fps.to_string(), "-i", "-"])
+        .args(["-vf", "vflip,scale=trunc(iw/2)*2:trunc(ih/2)*2", "-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("could not start ffmpeg: {}", err))
+}
+
+fn encode_video(receiver: Receiver<CapturedFrame>, path: &Path, fps: f64) -> Result<(), String>
+{
+    let mut encoder: Option<(Child, u32, u32)> = None;
+
+    for frame in receiver {
+        if encoder.is_none() {
+            encoder = Some((start_ffmpeg(path, frame.width, frame.height, fps)?, frame.width, frame.height));
+        }
+        if let Some((child, width, height)) = &mut encoder {
+            if (frame.width, frame.height) != (*width, *height) {
+                return Err(format!("the window was resized from {}x{} while recording video", width, height));
+            }
+            child.stdin.as_mut()
+                .ok_or_else(|| "ffmpeg has no input pipe".to_string())?
+                .write_all(&frame.data)
+                .map_err(|err| format!("could not write to ffmpeg: {}", err))?;
+        }
+    }
+
+    if let Some((mut child, _, _)) = encoder {
+        drop(child.stdin.take());
+        let status = child.wait().map_err(|err| err.to_string())?;
+        if !status.success() {
+            return Err(format!("ffmpeg exited with {}", status));
+        }
+    }
+    Ok(())
+}
+
+pub struct FrameRecorder
+{
+    path: PathBuf,
+    sender: Option<SyncSender<CapturedFrame>>,
+    writer: Option<JoinHandle<Result<(), String>>>,
+    lossless: bool,
+    every: u32,
+    frame: u32,
+    saved: u32,
+}
+
+impl FrameRecorder
+{
+    pub fn create<P: AsRef<Path>>(path: P, every: u32, fps: f64) -> Result<FrameRecorder, String>
+    {
+        let path = path.as_ref().to_path_buf();
+        let (sender, receiver) = mpsc::sync_channel::<CapturedFrame>(CAPTURE_QUEUE);
+
+        let lossless = is_video(&path);
+        let writer = if lossless {
+            if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).map_err(|err| format!("could not create {}: {}", parent.display(), err))?;
+            }
+            let video = path.clone();
+            thread::spawn(move || encode_video(receiver, &video, fps / every.max(1) as f64))
+        } else {
+            fs::create_dir_all(&path).map_err(|err| format!("could not create {}: {}", path.display(), err))?;
+            let dir = path.clone();
+            thread::spawn(move || write_frames(receiver, &dir))
+        };
+
+        Ok(FrameRecorder { path, sender: Some(sender), writer: Some(writer), lossless, every: every.max(1), frame: 0, saved: 0 })
+    }
+
+    pub fn path(&self) -> &Path
+    {
+        &self.path
+    }
+
+    pub fn capture<'a, F: FnOnce() -> Option<RawImage2d<'a, u8>>>(&mut self, read: F)
+    {
+        let wanted = self.sender.is_some() && self.frame.is_multiple_of(self.every);
+        if let Some(raw) = if wanted { read() } else { None } {
+            let frame = CapturedFrame { index: self.saved, width: raw.width, height: raw.height, data: raw.data.into_owned() };
+            let sent = match &self.sender {
+                Some(sender) if self.lossless => sender.send(frame).map_err(|err| TrySendError::Disconnected(err.0)),
+                Some(sender) => sender.try_send(frame),
+                None => Err(TrySendError::Disconnected(frame)),
+            };
+            match sent {
+                Ok(()) => self.saved += 1,
+                Err(TrySendError::Full(_)) => eprintln!("warning: dropped frame {}, the PNG writer is falling behind", self.frame),
+                Err(TrySendError::Disconnected(_)) => self.sender = None,
+            }
+        }
+        self.frame += 1;
+    }
+
+    pub fn finish(mut self) -> Result<u32, String>
+    {
+        self.close()?;
+        Ok(self.saved)
+    }
+
+    fn close(&mut self) -> Result<(), String>
+    {
+        self.sender = None;
+        match self.writer.take() {
+            Some(writer) => writer.join().unwrap_or_else(|_| Err("frame encoder thread panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FrameRecorder
+{
+    fn drop(&mut self)
+    {
+        if let Err(err) = self.close() {
+            eprintln!("warning: frame capture did not finish cleanly: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf
+    {
+        std::env::temp_dir().join(format!("flock-capture-{}-{}", name, std::process::id()))
+    }
+
+    fn stripes(width: u32, height: u32) -> Vec<u8>
+    {
+        (0..height).flat_map(|row| (0..width).flat_map(move |column| [row as u8 * 10, column as u8, 0, 255])).collect()
+    }
+
+    #[test]
+    fn saved_png_is_flipped_upright()
+    {
+        let dir = test_dir("flip");
+        let path = dir.join("tiny.png");
+        save_png(RawImage2d::from_raw_rgba(stripes(2, 3), (2, 3)), &path).unwrap();
+
+        let image = image::open(&path).unwrap().to_rgba8();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(image.dimensions(), (2, 3));
+        assert_eq!(image.get_pixel(0, 0).0, [20, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [20, 1, 0, 255]);
+        assert_eq!(image.get_pixel(1, 2).0, [0, 1, 0, 255]);
+    }
+
+    #[test]
+    fn mismatched_framebuffer_is_an_error()
+    {
+        let dir = test_dir("mismatch");
+        let result = save_pixels(4, 4, vec![0; 12], &dir.join("bad.png"));
+
+        assert!(result.is_err());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn recorder_keeps_every_nth_frame()
+    {
+        let dir = test_dir("sequence");
+        let mut recorder = FrameRecorder::create(&dir, 2, CAPTURE_FPS).unwrap();
+        for _ in 0..5 {
+            recorder.capture(|| Some(RawImage2d::from_raw_rgba(stripes(2, 2), (2, 2))));
+        }
+
+        assert_eq!(recorder.finish(), Ok(3));
+        let saved = (0..3).all(|index| frame_path(&dir, index).exists()) && !frame_path(&dir, 3).exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(saved);
+    }
+
+    #[test]
+    fn video_paths_are_recognised_by_extension()
+    {
+        assert!(is_video(Path::new("clip.MP4")));
+        assert!(is_video(Path::new("out/clip.webm")));
+        assert!(!is_video(Path::new("frames")));
+        assert_eq!(frame_path(Path::new("frames"), 7), Path::new("frames/frame_00007.png"));
+    }
+}
@@ -0,0 +1,489 @@
+use clap::Parser;
+#[cfg(feature = "serve")]
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bird_flock::export::ExportFormat;
+use bird_flock::flock::{BoundaryShape, FlockConfig, InteractionModel, NeighbourSearch, Plane};
+use bird_flock::gltf::GLTF_RATE;
+use bird_flock::terrain::TERRAIN_HEIGHT;
+use bird_flock::trail::TRAIL_LENGTH;
+use bird_flock::wind::WindMode;
+use nalgebra::Vector3;
+
+use crate::capture;
+use crate::config;
+#[cfg(feature = "serve")]
+use crate::server::StreamFormat;
+#[cfg(feature = "hot-reload")]
+use crate::watch;
+
+pub const SNAPSHOT_PATH: &str = "flock_state.json";
+pub const HEADLESS_STEPS: &str = "1000";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Switch
+{
+    #[default]
+    On,
+    Off,
+}
+
+impl FromStr for Switch
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Switch, String>
+    {
+        match s {
+            "on" => Ok(Switch::On),
+            "off" => Ok(Switch::Off),
+            _ => Err(format!("unknown setting '{}', expected on or off", s)),
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(about = "Bird flock simulation")]
+pub struct Args
+{
+    #[arg(long, value_name = "FILE", default_value = config::CONFIG_PATH)]
+    pub config: PathBuf,
+
+    #[arg(long)]
+    pub scene: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", default_value = SNAPSHOT_PATH)]
+    pub snapshot: PathBuf,
+
+    #[arg(long, value_name = "FILE")]
+    pub save_on_exit: Option<PathBuf>,
+
+    #[arg(long, default_value_t = TRAIL_LENGTH)]
+    pub trail_length: usize,
+
+    #[arg(long, default_value_t = 60.0)]
+    pub sim_hz: f64,
+
+    #[arg(long, value_name = "STEPS", num_args = 0..=1, default_missing_value = HEADLESS_STEPS)]
+    pub headless: Option<u32>,
+
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    #[arg(long, value_name = "INDEX")]
+    pub monitor: Option<usize>,
+
+    #[arg(long)]
+    pub vsync: Option<Switch>,
+
+    #[arg(long)]
+    pub pipeline: Option<Switch>,
+
+    #[arg(long)]
+    pub stereo: bool,
+
+    #[arg(long)]
+    pub eye_separation: Option<f32>,
+
+    #[arg(long, value_name = "HZ")]
+    pub max_fps: Option<f64>,
+
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 1, requires = "export")]
+    pub export_every: u32,
+
+    #[arg(long, requires = "export")]
+    pub export_format: Option<ExportFormat>,
+
+    #[arg(long, value_name = "FILE")]
+    pub export_gltf: Option<PathBuf>,
+
+    #[arg(long, value_name = "HZ", default_value_t = GLTF_RATE, requires = "export_gltf")]
+    pub gltf_rate: f32,
+
+    #[arg(long, value_name = "FILE")]
+    pub metrics: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 1, requires = "metrics")]
+    pub metrics_every: u32,
+
+    #[arg(long, value_name = "FILE")]
+    pub save_replay: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 1, requires = "save_replay")]
+    pub replay_every: u32,
+
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["scene", "headless", "gpu", "save_replay"])]
+    pub replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "audio_input")]
+    pub audio_file: Option<PathBuf>,
+
+    #[arg(long)]
+    pub audio_input: bool,
+
+    #[cfg(feature = "serve")]
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["headless", "gpu"])]
+    pub serve: Option<SocketAddr>,
+
+    #[cfg(feature = "serve")]
+    #[arg(long, default_value = "binary", requires = "serve")]
+    pub serve_format: StreamFormat,
+
+    #[cfg(feature = "profile")]
+    #[arg(long, value_name = "FILE")]
+    pub profile: Option<PathBuf>,
+
+    #[cfg(feature = "hot-reload")]
+    #[arg(long, conflicts_with_all = ["headless", "replay"])]
+    pub watch: bool,
+
+    #[cfg(feature = "hot-reload")]
+    #[arg(long, value_name = "DIR", default_value = watch::SHADER_DIR, requires = "watch")]
+    pub shader_dir: PathBuf,
+
+    #[arg(long)]
+    pub gpu: bool,
+
+    #[arg(long, requires = "gpu")]
+    pub gpu_validate: bool,
+
+    #[arg(long, visible_alias = "record", value_name = "DIR")]
+    pub capture: Option<PathBuf>,
+
+    #[arg(long, value_name = "INDEX")]
+    pub follow: Option<usize>,
+
+    #[arg(long)]
+    pub ground: bool,
+
+    #[arg(long, value_name = "FILE")]
+    pub heightmap: Option<PathBuf>,
+
+    #[arg(long, default_value_t = TERRAIN_HEIGHT, allow_negative_numbers = true)]
+    pub terrain_height: f32,
+
+    #[arg(long, num_args = 3, value_delimiter = ',', allow_negative_numbers = true)]
+    pub light_dir: Option<Vec<f32>>,
+
+    #[arg(long, value_name = "PATH")]
+    pub skybox: Option<PathBuf>,
+
+    #[arg(long)]
+    pub fog_density: Option<f32>,
+
+    #[arg(long, num_args = 3, value_delimiter = ',')]
+    pub fog_colour: Option<Vec<f32>>,
+
+    #[arg(long, default_value_t = 1, requires = "capture")]
+    pub capture_every: u32,
+
+    #[arg(long, default_value_t = capture::CAPTURE_FPS)]
+    pub capture_fps: f64,
+
+    #[arg(long, visible_alias = "birds")]
+    num_birds: Option<usize>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    max_speed: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    neighbour_radius: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    separation_weight: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    alignment_weight: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    cohesion_weight: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    boundary_size: Option<f32>,
+
+    #[arg(long)]
+    boundary: Option<BoundaryShape>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    sphere_radius: Option<f32>,
+
+    #[arg(long, num_args = 3, value_delimiter = ',', allow_negative_numbers = true)]
+    home: Option<Vec<f32>>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    view_angle: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    attraction_weight: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    wander_weight: Option<f32>,
+
+    #[arg(long)]
+    mass_variance: Option<f32>,
+
+    #[arg(long)]
+    speed_variance: Option<f32>,
+
+    #[arg(long)]
+    sociability_variance: Option<f32>,
+
+    #[arg(long)]
+    behaviour_states: Option<Switch>,
+
+    #[arg(long, num_args = 3, value_delimiter = ',', allow_negative_numbers = true)]
+    gravity: Option<Vec<f32>>,
+
+    #[arg(long, value_delimiter = ',')]
+    species: Option<Vec<usize>>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    inter_species_weight: Option<f32>,
+
+    #[arg(long)]
+    neighbour_search: Option<NeighbourSearch>,
+
+    #[arg(long)]
+    barnes_hut_theta: Option<f32>,
+
+    #[arg(long)]
+    interaction: Option<InteractionModel>,
+
+    #[arg(long)]
+    topological_k: Option<usize>,
+
+    #[arg(long, num_args = 3, value_delimiter = ',', allow_negative_numbers = true)]
+    wind: Option<Vec<f32>>,
+
+    #[arg(long)]
+    wind_mode: Option<WindMode>,
+
+    #[arg(long)]
+    wind_seed: Option<u64>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    turbulence: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    turbulence_scale: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    perch_probability: Option<f32>,
+
+    #[arg(long)]
+    perch_min_steps: Option<u32>,
+
+    #[arg(long)]
+    perch_max_steps: Option<u32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    energy_drain: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    energy_recovery: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    day_length: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    roost_weight: Option<f32>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    bird_radius: Option<f32>,
+
+    #[arg(long = "2d", value_name = "PLANE", num_args = 0..=1, default_missing_value = "xz")]
+    plane: Option<Plane>,
+
+    #[arg(long)]
+    predators: Option<usize>,
+
+    #[arg(long)]
+    food: Option<usize>,
+
+    #[arg(long)]
+    food_amount: Option<f32>,
+
+    #[arg(long)]
+    food_spawn_rate: Option<f32>,
+
+    #[arg(long)]
+    food_weight: Option<f32>,
+
+    #[arg(long)]
+    leaders: Option<usize>,
+
+    #[arg(long, allow_negative_numbers = true)]
+    leader_weight: Option<f32>,
+
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+impl Args
+{
+    pub fn species(&self, config: &FlockConfig) -> Vec<FlockConfig>
+    {
+        match &self.species {
+            Some(counts) => counts.iter().map(|&num_birds| FlockConfig { num_birds, ..config.clone() }).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn apply(&self, config: &mut FlockConfig)
+    {
+        if let Some(num_birds) = self.num_birds {
+            config.num_birds = num_birds;
+        }
+        if let Some(max_speed) = self.max_speed {
+            config.max_speed = max_speed;
+        }
+        if let Some(neighbour_radius) = self.neighbour_radius {
+            config.neighbour_radius = neighbour_radius;
+        }
+        if let Some(separation_weight) = self.separation_weight {
+            config.separation_weight = separation_weight;
+        }
+        if let Some(alignment_weight) = self.alignment_weight {
+            config.alignment_weight = alignment_weight;
+        }
+        if let Some(cohesion_weight) = self.cohesion_weight {
+            config.cohesion_weight = cohesion_weight;
+        }
+        if let Some(boundary_size) = self.boundary_size {
+            config.boundary_size = boundary_size;
+        }
+        if let Some(boundary) = self.boundary {
+            config.boundary_shape = match boundary {
+                BoundaryShape::Sphere { .. } => BoundaryShape::Sphere { radius: config.boundary_size / 2.0 },
+                BoundaryShape::Soft { home, .. } => BoundaryShape::Soft { home, radius: config.boundary_size / 2.0 },
+                other => other,
+            };
+        }
+        if let Some(radius) = self.sphere_radius {
+            config.boundary_shape = match config.boundary_shape {
+                BoundaryShape::Soft { home, .. } => BoundaryShape::Soft { home, radius },
+                _ => BoundaryShape::Sphere { radius },
+            };
+        }
+        if let Some(home) = &self.home {
+            let home = Vector3::new(home[0], home[1], home[2]);
+            config.boundary_shape = match config.boundary_shape {
+                BoundaryShape::Soft { radius, .. } => BoundaryShape::Soft { home, radius },
+                _ => BoundaryShape::Soft { home, radius: config.boundary_size / 2.0 },
+            };
+        }
+        if let Some(view_angle) = self.view_angle {
+            config.view_angle = view_angle;
+        }
+        if let Some(attraction_weight) = self.attraction_weight {
+            config.attraction_weight = attraction_weight;
+        }
+        if let Some(wander_weight) = self.wander_weight {
+            config.wander_weight = wander_weight;
+        }
+        if let Some(mass_variance) = self.mass_variance {
+            config.mass_variance = mass_variance;
+        }
+        if let Some(speed_variance) = self.speed_variance {
+            config.speed_variance = speed_variance;
+        }
+        if let Some(sociability_variance) = self.sociability_variance {
+            config.sociability_variance = sociability_variance;
+        }
+        if let Some(behaviour_states) = self.behaviour_states {
+            config.behaviour_states = behaviour_states == Switch::On;
+        }
+        if let Some(gravity) = &self.gravity {
+            config.gravity = Vector3::new(gravity[0], gravity[1], gravity[2]);
+        }
+        if let Some(inter_species_weight) = self.inter_species_weight {
+            config.inter_species_weight = inter_species_weight;
+        }
+        if let Some(neighbour_search) = self.neighbour_search {
+            config.neighbour_search = neighbour_search;
+        }
+        if let Some(theta) = self.barnes_hut_theta {
+            config.barnes_hut_theta = theta;
+        }
+        if let Some(interaction) = self.interaction {
+            config.interaction = interaction;
+        }
+        if let Some(k) = self.topological_k {
+            config.interaction = InteractionModel::Topological { k };
+        }
+        if let Some(wind) = &self.wind {
+            config.wind = Vector3::new(wind[0], wind[1], wind[2]);
+        }
+        if let Some(wind_mode) = self.wind_mode {
+            config.wind_mode = wind_mode;
+        }
+        if let Some(wind_seed) = self.wind_seed {
+            config.wind_seed = wind_seed;
+        }
+        if let Some(turbulence) = self.turbulence {
+            config.turbulence = turbulence;
+        }
+        if let Some(turbulence_scale) = self.turbulence_scale {
+            config.turbulence_scale = turbulence_scale;
+        }
+        if let Some(perch_probability) = self.perch_probability {
+            config.perch_probability = perch_probability;
+        }
+        if let Some(perch_min_steps) = self.perch_min_steps {
+            config.perch_min_steps = perch_min_steps;
+        }
+        if let Some(perch_max_steps) = self.perch_max_steps {
+            config.perch_max_steps = perch_max_steps;
+        }
+        if let Some(energy_drain) = self.energy_drain {
+            config.energy_drain = energy_drain;
+        }
+        if let Some(energy_recovery) = self.energy_recovery {
+            config.energy_recovery = energy_recovery;
+        }
+        if let Some(day_length) = self.day_length {
+            config.day_length = day_length;
+        }
+        if let Some(roost_weight) = self.roost_weight {
+            config.roost_weight = roost_weight;
+        }
+        if let Some(bird_radius) = self.bird_radius {
+            config.bird_radius = bird_radius;
+        }
+        if let Some(plane) = self.plane {
+            config.plane = Some(plane);
+            if self.neighbour_search.is_none() {
+                config.neighbour_search = NeighbourSearch::Grid;
+            }
+        }
+        if let Some(predators) = self.predators {
+            config.num_predators = predators;
+        }
+        if let Some(food) = self.food {
+            config.num_food_sources = food;
+        }
+        if let Some(food_amount) = self.food_amount {
+            config.food_amount = food_amount;
+        }
+        if let Some(food_spawn_rate) = self.food_spawn_rate {
+            config.food_spawn_rate = food_spawn_rate;
+        }
+        if let Some(food_weight) = self.food_weight {
+            config.food_weight = food_weight;
+        }
+        if let Some(leaders) = self.leaders {
+            config.num_leaders = leaders;
+        }
+        if let Some(leader_weight) = self.leader_weight {
+            config.leader_weight = leader_weight;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use bird_flock::behaviour::BehaviourState;
+use bird_flock::flock::{Bird, FlockConfig};
+
+const DENSITY_SATURATION: f32 = 10.0;
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+pub const CLUSTER_INTERVAL: u32 = 10;
+
+#[derive(Clone, Copy)]
+pub struct Palette
+{
+    pub low: [f32; 3],
+    pub high: [f32; 3],
+}
+
+pub const GRADIENT_PALETTE: Palette = Palette
+{
+    low: [0.0, 0.0, 1.0],
+    high: [1.0, 0.0, 0.0],
+};
+
+pub const SOLID_COLOUR: [f32; 3] = [1.0, 0.0, 0.0];
+pub const PREDATOR_COLOUR: [f32; 3] = [1.0, 0.85, 0.1];
+pub const LEADER_COLOUR: [f32; 3] = [1.0, 1.0, 1.0];
+pub const SELECTED_COLOUR: [f32; 3] = [0.2, 1.0, 1.0];
+
+pub const BEHAVIOUR_COLOURS: [[f32; 3]; 4] = [
+    [0.3, 0.6, 1.0],
+    [1.0, 0.2, 0.2],
+    [0.3, 1.0, 0.3],
+    [0.7, 0.7, 0.7],
+];
+
+pub const SPECIES_COLOURS: [[f32; 3]; 6] = [
+    [1.0, 0.2, 0.2],
+    [0.2, 0.6, 1.0],
+    [0.3, 1.0, 0.3],
+    [1.0, 0.8, 0.2],
+    [0.8, 0.3, 1.0],
+    [0.2, 1.0, 0.9],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColourMode
+{
+    Solid,
+    Speed,
+    Density,
+    Species,
+    Cluster,
+    Behaviour,
+}
+
+impl ColourMode
+{
+    pub fn next(self) -> ColourMode
+    {
+        match self {
+            ColourMode::Solid => ColourMode::Speed,
+            ColourMode::Speed => ColourMode::Density,
+            ColourMode::Density => ColourMode::Species,
+            ColourMode::Species => ColourMode::Cluster,
+            ColourMode::Cluster => ColourMode::Behaviour,
+            ColourMode::Behaviour => ColourMode::Solid,
+        }
+    }
+
+    pub fn colour(self, bird: &Bird, speed: f32, cluster: usize, config: &FlockConfig) -> [f32; 3]
+    {
+        match self {
+            ColourMode::Solid => SOLID_COLOUR,
+            ColourMode::Speed => gradient(speed / config.max_speed, &GRADIENT_PALETTE),
+            ColourMode::Density => gradient(bird.neighbour_count as f32 / DENSITY_SATURATION, &GRADIENT_PALETTE),
+            ColourMode::Species => config.colour.unwrap_or(SPECIES_COLOURS[bird.species as usize % SPECIES_COLOURS.len()]),
+            ColourMode::Cluster => cluster_colour(cluster),
+            ColourMode::Behaviour => behaviour_colour(bird.behaviour),
+        }
+    }
+}
+
+pub fn behaviour_colour(state: BehaviourState) -> [f32; 3]
+{
+    match state {
+        BehaviourState::Flocking => BEHAVIOUR_COLOURS[0],
+        BehaviourState::Fleeing => BEHAVIOUR_COLOURS[1],
+        BehaviourState::Feeding => BEHAVIOUR_COLOURS[2],
+        BehaviourState::Perching => BEHAVIOUR_COLOURS[3],
+    }
+}
+
+pub fn cluster_colour(cluster: usize) -> [f32; 3]
+{
+    let hue = (cluster as f32 * GOLDEN_RATIO_CONJUGATE).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    match hue as u32 {
+        0 => [1.0, x, 0.2],
+        1 => [x, 1.0, 0.2],
+        2 => [0.2, 1.0, x],
+        3 => [0.2, x, 1.0],
+        4 => [x, 0.2, 1.0],
+        _ => [1.0, 0.2, x],
+    }
+}
+
+pub fn gradient(value: f32, palette: &Palette) -> [f32; 3]
+{
+    let t = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+    [
+        palette.low[0] + (palette.high[0] - palette.low[0]) * t,
+        palette.low[1] + (palette.high[1] - palette.low[1]) * t,
+        palette.low[2] + (palette.high[2] - palette.low[2]) * t,
+    ]
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn gradient_runs_from_low_to_high()
+    {
+        assert_eq!(gradient(0.0, &GRADIENT_PALETTE), [0.0, 0.0, 1.0]);
+        assert_eq!(gradient(0.5, &GRADIENT_PALETTE), [0.5, 0.0, 0.5]);
+        assert_eq!(gradient(1.0, &GRADIENT_PALETTE), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn gradient_clamps_out_of_range_values()
+    {
+        assert_eq!(gradient(-3.0, &GRADIENT_PALETTE), GRADIENT_PALETTE.low);
+        assert_eq!(gradient(7.5, &GRADIENT_PALETTE), GRADIENT_PALETTE.high);
+        assert_eq!(gradient(f32::INFINITY, &GRADIENT_PALETTE), GRADIENT_PALETTE.high);
+        assert_eq!(gradient(f32::NAN, &GRADIENT_PALETTE), GRADIENT_PALETTE.low);
+    }
+
+    #[test]
+    fn cluster_colours_stay_in_range()
+    {
+        for cluster in 0..50 {
+            assert!(cluster_colour(cluster).iter().all(|c| (0.0..=1.0).contains(c)));
+        }
+        assert_ne!(cluster_colour(0), cluster_colour(1));
+    }
+}
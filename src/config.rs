@@ -0,0 +1,191 @@
+use nalgebra::{Point3, Vector3};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bird_flock::flock::{positive, FlockConfig, Obstacle, MAX_SPECIES};
+use bird_flock::modulation::Mapping;
+use bird_flock::preset::{self, Preset};
+
+use crate::camera::Camera;
+use crate::sky::{Fog, FOG_COLOUR, FOG_DENSITY};
+use crate::stereo::{self, EYE_SEPARATION};
+
+pub const CONFIG_PATH: &str = "flock.toml";
+pub const WINDOW_WIDTH: u32 = 1024;
+pub const WINDOW_HEIGHT: u32 = 768;
+pub const WINDOW_SAMPLES: u8 = 4;
+pub const CAMERA_DISTANCE: f32 = 5.0;
+pub const CAMERA_FOV: f32 = 60.0;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig
+{
+    pub target: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub fov: f32,
+    pub stereo: bool,
+    pub eye_separation: f32,
+}
+
+impl Default for CameraConfig
+{
+    fn default() -> CameraConfig
+    {
+        CameraConfig
+        {
+            target: [0.0, 0.0, 0.0],
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: CAMERA_DISTANCE,
+            fov: CAMERA_FOV,
+            stereo: false,
+            eye_separation: EYE_SEPARATION,
+        }
+    }
+}
+
+impl CameraConfig
+{
+    pub fn camera(&self) -> Camera
+    {
+        Camera
+        {
+            target: Point3::from(self.target),
+            yaw: self.yaw.to_radians(),
+            pitch: self.pitch.to_radians(),
+            distance: self.distance,
+            free: false,
+            follow: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig
+{
+    pub width: u32,
+    pub height: u32,
+    pub samples: u8,
+    pub vsync: bool,
+    pub max_fps: f64,
+    pub fullscreen: bool,
+    pub monitor: Option<usize>,
+}
+
+impl Default for WindowConfig
+{
+    fn default() -> WindowConfig
+    {
+        WindowConfig { width: WINDOW_WIDTH, height: WINDOW_HEIGHT, samples: WINDOW_SAMPLES, vsync: true, max_fps: 0.0, fullscreen: false, monitor: None }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SkyConfig
+{
+    pub skybox: Option<PathBuf>,
+    pub fog_colour: [f32; 3],
+    pub fog_density: f32,
+}
+
+impl Default for SkyConfig
+{
+    fn default() -> SkyConfig
+    {
+        SkyConfig { skybox: None, fog_colour: FOG_COLOUR, fog_density: FOG_DENSITY }
+    }
+}
+
+impl SkyConfig
+{
+    pub fn fog(&self) -> Fog
+    {
+        Fog { colour: self.fog_colour, density: self.fog_density }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SimConfig
+{
+    pub flock: FlockConfig,
+    pub camera: CameraConfig,
+    pub window: WindowConfig,
+    pub sky: SkyConfig,
+    pub obstacles: Option<Vec<Obstacle>>,
+    pub waypoints: Vec<Vector3<f32>>,
+    pub roosts: Vec<Vector3<f32>>,
+    pub audio: Vec<Mapping>,
+    pub presets: Vec<Preset>,
+    pub flocks: Vec<FlockConfig>,
+}
+
+impl SimConfig
+{
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SimConfig, String>
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(SimConfig::default());
+        }
+
+        let text = fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        let mut table: toml::Table = toml::from_str(&text).map_err(|err| format!("could not parse {}: {}", path.display(), err))?;
+        inherit_flock(&mut table);
+        toml::Value::Table(table).try_into().map_err(|err| format!("could not parse {}: {}", path.display(), err))
+    }
+
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if self.window.width == 0 || self.window.height == 0 {
+            return Err(format!("window size must be positive, got {}x{}", self.window.width, self.window.height));
+        }
+        if self.window.samples > 16 || (self.window.samples > 0 && !self.window.samples.is_power_of_two()) {
+            return Err(format!("window samples must be 0 or a power of two up to 16, got {}", self.window.samples));
+        }
+        if !(self.window.max_fps >= 0.0 && self.window.max_fps.is_finite()) {
+            return Err(format!("window max_fps must be zero or positive, got {}", self.window.max_fps));
+        }
+        if !positive(self.camera.distance) {
+            return Err(format!("camera distance must be positive, got {}", self.camera.distance));
+        }
+        if !(self.camera.fov > 0.0 && self.camera.fov < 180.0) {
+            return Err(format!("camera fov must be between 0 and 180 degrees, got {}", self.camera.fov));
+        }
+        if !self.camera.target.iter().all(|v| v.is_finite()) || !self.camera.yaw.is_finite() || !self.camera.pitch.is_finite() {
+            return Err("camera target, yaw and pitch must be finite".to_string());
+        }
+        stereo::validate_separation(self.camera.eye_separation)?;
+        self.sky.fog().validate()?;
+        for mapping in &self.audio {
+            mapping.validate()?;
+        }
+        if self.flocks.len() > MAX_SPECIES {
+            return Err(format!("at most {} flocks are supported, got {}", MAX_SPECIES, self.flocks.len()));
+        }
+        preset::validate_presets(&self.presets)
+    }
+}
+
+fn inherit_flock(table: &mut toml::Table)
+{
+    let base = match table.get("flock").and_then(|flock| flock.as_table()) {
+        Some(base) => base.clone(),
+        None => return,
+    };
+    if let Some(flocks) = table.get_mut("flocks").and_then(|flocks| flocks.as_array_mut()) {
+        for entry in flocks.iter_mut().filter_map(|entry| entry.as_table_mut()) {
+            for (key, value) in base.iter() {
+                if !entry.contains_key(key) {
+                    entry.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use nalgebra::Vector3;
+
+use crate::flock::{normalize_or_zero, REFERENCE_DT};
+
+pub const DAY_START: f32 = 0.3;
+pub const DAWN: f32 = 0.25;
+pub const DUSK: f32 = 0.75;
+pub const TWILIGHT: f32 = 0.04;
+pub const DISPERSAL_RADIUS: f32 = 1.5;
+
+const NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.05];
+const TWILIGHT_SKY: [f32; 3] = [0.85, 0.42, 0.25];
+const DAY_SKY: [f32; 3] = [0.45, 0.65, 0.9];
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32
+{
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3]
+{
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+pub fn time_of_day(time: f32, day_length: f32) -> Option<f32>
+{
+    if day_length <= 0.0 {
+        return None;
+    }
+    Some((DAY_START + time * REFERENCE_DT / day_length).rem_euclid(1.0))
+}
+
+pub fn daylight(time_of_day: f32) -> f32
+{
+    smoothstep(DAWN - TWILIGHT, DAWN + TWILIGHT, time_of_day) - smoothstep(DUSK - TWILIGHT, DUSK + TWILIGHT, time_of_day)
+}
+
+pub fn sky_colour(time_of_day: f32) -> [f32; 3]
+{
+    let light = daylight(time_of_day);
+    let glow = 1.0 - (2.0 * light - 1.0).powi(2);
+    mix(mix(NIGHT_SKY, DAY_SKY, light), TWILIGHT_SKY, glow * 0.7)
+}
+
+pub fn clock(time_of_day: f32) -> (u32, u32)
+{
+    let minutes = (time_of_day * 24.0 * 60.0) as u32 % (24 * 60);
+    (minutes / 60, minutes % 60)
+}
+
+pub fn roost_force(position: Vector3<f32>, roosts: &[Vector3<f32>], time_of_day: f32) -> Vector3<f32>
+{
+    let nearest = roosts.iter()
+        .map(|&roost| roost - position)
+        .min_by(|a, b| a.norm_squared().partial_cmp(&b.norm_squared()).unwrap_or(std::cmp::Ordering::Equal));
+    let offset = match nearest {
+        Some(offset) => offset,
+        None => return Vector3::zeros(),
+    };
+
+    let light = daylight(time_of_day);
+    let morning = time_of_day > DAWN - TWILIGHT && time_of_day < DAWN + 3.0 * TWILIGHT;
+    if morning && offset.norm() < DISPERSAL_RADIUS {
+        return -normalize_or_zero(offset) * light;
+    }
+    normalize_or_zero(offset) * (1.0 - light)
+}
@@ -0,0 +1,174 @@
+use glium::backend::Facade;
+use glium::{Frame, Program, Surface, VertexBuffer};
+use nalgebra::Vector3;
+use std::collections::HashSet;
+
+use bird_flock::flock::Flock;
+
+use crate::render;
+
+const MAX_DEBUG_BIRDS: usize = 2000;
+const VELOCITY_SCALE: f32 = 10.0;
+const FORCE_SCALE: f32 = 200.0;
+const VELOCITY_COLOUR: [f32; 3] = [1.0, 1.0, 1.0];
+const SEPARATION_COLOUR: [f32; 3] = [1.0, 0.25, 0.25];
+const ALIGNMENT_COLOUR: [f32; 3] = [0.25, 1.0, 0.25];
+const COHESION_COLOUR: [f32; 3] = [0.3, 0.5, 1.0];
+const GRID_COLOUR: [f32; 3] = [0.3, 0.3, 0.2];
+
+#[derive(Copy, Clone)]
+struct DebugVertex
+{
+    position: [f32; 3],
+    colour: [f32; 3],
+}
+
+implement_vertex!(DebugVertex, position, colour);
+
+pub struct DebugRenderer
+{
+    pub visible: bool,
+    vertices: Vec<DebugVertex>,
+    vertex_buffer: Option<VertexBuffer<DebugVertex>>,
+    program: Program,
+}
+
+impl DebugRenderer
+{
+    pub fn new<F: ?Sized + Facade>(facade: &F) -> Result<DebugRenderer, String>
+    {
+        let vertex_shader_src = r#"
+            #version 140
+
+            in vec3 position;
+            in vec3 colour;
+
+            out vec3 v_colour;
+
+            uniform mat4 view;
+            uniform mat4 projection;
+
+            void main() {
+                v_colour = colour;
+                gl_Position = projection * view * vec4(position, 1.0);
+            }
+        "#;
+
+        let fragment_shader_src = r#"
+            #version 140
+
+            in vec3 v_colour;
+            out vec4 color;
+
+            void main() {
+                color = vec4(v_colour, 1.0);
+            }
+        "#;
+
+        let program = render::compile_program(facade, "debug", vertex_shader_src, fragment_shader_src)?;
+
+        Ok(DebugRenderer
+        {
+            visible: false,
+            vertices: Vec::new(),
+            vertex_buffer: None,
+            program,
+        })
+    }
+
+    pub fn toggle(&mut self)
+    {
+        self.visible = !self.visible;
+    }
+
+    fn line(&mut self, from: Vector3<f32>, to: Vector3<f32>, colour: [f32; 3])
+    {
+        self.vertices.push(DebugVertex { position: from.into(), colour });
+        self.vertices.push(DebugVertex { position: to.into(), colour });
+    }
+
+    fn cell(&mut self, corner: Vector3<f32>, size: f32)
+    {
+        for axis in 0..3
+        {
+            let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+            for &(da, db) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].iter()
+            {
+                let mut from = corner;
+                from[a] += da * size;
+                from[b] += db * size;
+                let mut to = from;
+                to[axis] += size;
+                self.line(from, to, GRID_COLOUR);
+            }
+        }
+    }
+
+    pub fn build(&mut self, flock: &Flock, alpha: f32, show_grid: bool)
+    {
+        self.vertices.clear();
+
+        for (index, bird) in flock.birds.iter().enumerate().take(MAX_DEBUG_BIRDS) {
+            let position = bird.interpolated_position(alpha);
+            self.line(position, position + bird.velocity * VELOCITY_SCALE, VELOCITY_COLOUR);
+
+            if let Some(forces) = flock.steering_forces(index) {
+                self.line(position, position + forces.separation * FORCE_SCALE, SEPARATION_COLOUR);
+                self.line(position, position + forces.alignment * FORCE_SCALE, ALIGNMENT_COLOUR);
+                self.line(position, position + forces.cohesion * FORCE_SCALE, COHESION_COLOUR);
+            }
+        }
+
+        if show_grid {
+            let size = flock.grid_cell_size();
+            let cells: HashSet<(i32, i32, i32)> = flock.birds.iter().map(|bird| {
+                let cell = bird.position.map(|v| (v / size).floor() as i32);
+                (cell.x, cell.y, cell.z)
+            }).collect();
+
+            for (x, y, z) in cells {
+                self.cell(Vector3::new(x as f32, y as f32, z as f32) * size, size);
+            }
+        }
+    }
+
+    pub fn draw<F: ?Sized + Facade>(&mut self, facade: &F, target: &mut Frame, view: [[f32; 4]; 4], projection: [[f32; 4]; 4], viewport: Option<glium::Rect>) -> Result<(), String>
+    {
+        let count = self.vertices.len();
+        if !self.visible || count == 0 {
+            return Ok(());
+        }
+
+        let needs_buffer = match &self.vertex_buffer {
+            Some(buffer) => buffer.len() < count,
+            None => true,
+        };
+        if needs_buffer {
+            self.vertex_buffer = Some(VertexBuffer::empty_dynamic(facade, count.next_power_of_two()).map_err(|err| err.to_string())?);
+        }
+        let slice = match self.vertex_buffer.as_ref().and_then(|buffer| buffer.slice(0..count)) {
+            Some(slice) => slice,
+            None => return Ok(()),
+        };
+        slice.write(&self.vertices);
+
+        let uniforms = uniform! {
+            view: view,
+            projection: projection,
+        };
+
+        let params = glium::DrawParameters {
+            depth: render::depth_test(true),
+            viewport,
+            .. Default::default()
+        };
+
+        target.draw(
+            slice,
+            glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+            &self.program,
+            &uniforms,
+            &params,
+        ).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,139 @@
+use nalgebra::{Matrix3, Matrix4, Point3, Rotation3, Unit, Vector3};
+
+use std::f32::consts::TAU;
+
+use crate::flock::{Bird, MAX_FORCE};
+
+const MAX_BANK: f32 = 0.6;
+const ORBIT_SENSITIVITY: f32 = 0.01;
+const MAX_PITCH: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MeshVertex
+{
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct BirdInstance
+{
+    pub offset: [f32; 3],
+    pub orientation: [[f32; 3]; 3],
+    pub colour: [f32; 3],
+    pub wing: [f32; 2],
+}
+
+impl BirdInstance
+{
+    pub fn new(position: Vector3<f32>, velocity: Vector3<f32>, steering: Vector3<f32>, colour: [f32; 3], scale: f32) -> BirdInstance
+    {
+        let orientation = bird_orientation(velocity, steering) * scale;
+        BirdInstance { offset: position.into(), orientation: *orientation.as_ref(), colour, wing: [0.0, 0.0] }
+    }
+
+    pub fn from_bird(bird: &Bird, alpha: f32, max_speed: f32, colour: [f32; 3], scale: f32) -> BirdInstance
+    {
+        let mut instance = BirdInstance::new(bird.interpolated_position(alpha), bird.velocity, bird.steering, colour, scale);
+        instance.wing = [bird.wing_phase, bird.flap_frequency(max_speed) * TAU];
+        instance
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameUniforms
+{
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub light_dir: [f32; 3],
+    pub light_colour: [f32; 3],
+    pub ambient: f32,
+    pub flap_time: f32,
+}
+
+pub trait FlockRenderer
+{
+    type Frame;
+
+    fn draw_birds(&mut self, frame: &mut Self::Frame, birds: &[BirdInstance], uniforms: &FrameUniforms) -> Result<(), String>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitCamera
+{
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl Default for OrbitCamera
+{
+    fn default() -> OrbitCamera
+    {
+        OrbitCamera { yaw: 0.0, pitch: 0.0, distance: 5.0 }
+    }
+}
+
+impl OrbitCamera
+{
+    pub fn drag(&mut self, dx: f32, dy: f32)
+    {
+        self.yaw -= dx * ORBIT_SENSITIVITY;
+        self.pitch = (self.pitch + dy * ORBIT_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn zoom(&mut self, steps: f32)
+    {
+        self.distance = (self.distance * (1.0 - steps * 0.1)).clamp(0.5, 50.0);
+    }
+
+    pub fn view(&self) -> Matrix4<f32>
+    {
+        let look = Vector3::new(self.pitch.cos() * self.yaw.sin(), self.pitch.sin(), self.pitch.cos() * self.yaw.cos());
+        Matrix4::look_at_rh(&Point3::from(look * self.distance), &Point3::origin(), &Vector3::y())
+    }
+}
+
+pub fn bird_mesh() -> Vec<MeshVertex>
+{
+    let nose = Vector3::new(0.0, 0.0577, 0.0);
+    let left = Vector3::new(-0.05, -0.0288, 0.0);
+    let right = Vector3::new(0.05, -0.0288, 0.0);
+    let keel = Vector3::new(0.0, -0.0288, -0.03);
+    let centre = (nose + left + right + keel) / 4.0;
+
+    let faces = [
+        [nose, left, right],
+        [nose, right, keel],
+        [nose, keel, left],
+        [left, keel, right],
+    ];
+
+    let mut vertices = Vec::with_capacity(12);
+    for face in faces.iter() {
+        let mut face = *face;
+        let mut normal = (face[1] - face[0]).cross(&(face[2] - face[0])).normalize();
+        if normal.dot(&(face[0] - centre)) < 0.0 {
+            normal = -normal;
+            face.swap(1, 2);
+        }
+        for corner in face.iter() {
+            vertices.push(MeshVertex { position: (*corner).into(), normal: normal.into() });
+        }
+    }
+    vertices
+}
+
+pub fn bird_orientation(velocity: Vector3<f32>, steering: Vector3<f32>) -> Matrix3<f32>
+{
+    let forward = velocity.try_normalize(1e-6).unwrap_or_else(Vector3::x);
+    let reference = if forward.y.abs() < 0.99 { Vector3::y() } else { Vector3::x() };
+    let right = forward.cross(&reference).normalize();
+    let up = right.cross(&forward);
+
+    let lateral = (steering.dot(&right) / MAX_FORCE).clamp(-1.0, 1.0);
+    let bank = Rotation3::from_axis_angle(&Unit::new_normalize(forward), lateral * MAX_BANK);
+    bank.matrix() * Matrix3::from_columns(&[right, forward, up])
+}
@@ -0,0 +1,79 @@
+use nalgebra::Vector3;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlockEvent
+{
+    BirdCollision { first: usize, second: usize },
+    BoundaryHit { bird: usize, position: Vector3<f32> },
+    PredatorCatch { predator: usize, bird: usize },
+    FlockSplit { groups: usize },
+    FlockMerge { groups: usize },
+}
+
+pub trait FlockObserver: Send + Sync
+{
+    fn on_event(&self, event: &FlockEvent);
+}
+
+struct ChannelObserver
+{
+    sender: Mutex<Sender<FlockEvent>>,
+}
+
+impl FlockObserver for ChannelObserver
+{
+    fn on_event(&self, event: &FlockEvent)
+    {
+        if let Ok(sender) = self.sender.lock() {
+            let _ = sender.send(*event);
+        }
+    }
+}
+
+pub fn channel() -> (Arc<dyn FlockObserver>, Receiver<FlockEvent>)
+{
+    let (sender, receiver) = mpsc::channel();
+    (Arc::new(ChannelObserver { sender: Mutex::new(sender) }), receiver)
+}
+
+#[derive(Clone, Default)]
+pub struct Observers
+{
+    observers: Vec<Arc<dyn FlockObserver>>,
+}
+
+impl Observers
+{
+    pub fn add(&mut self, observer: Arc<dyn FlockObserver>)
+    {
+        self.observers.push(observer);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.observers.clear();
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.observers.is_empty()
+    }
+
+    pub fn notify(&self, event: &FlockEvent)
+    {
+        for observer in self.observers.iter() {
+            observer.on_event(event);
+        }
+    }
+}
+
+impl fmt::Debug for Observers
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} observers", self.observers.len())
+    }
+}
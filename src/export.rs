@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use crate::flock::Bird;
+
+pub const EXPORT_QUEUE: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat
+{
+    #[default]
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat
+{
+    pub fn from_path<P: AsRef<Path>>(path: P) -> ExportFormat
+    {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") | Some("ndjson") => ExportFormat::JsonLines,
+            _ => ExportFormat::default(),
+        }
+    }
+}
+
+impl FromStr for ExportFormat
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExportFormat, String>
+    {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" | "json-lines" => Ok(ExportFormat::JsonLines),
+            _ => Err(format!("unknown export format '{}', expected csv or jsonl", s)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportRow
+{
+    frame: u64,
+    bird: usize,
+    x: f32,
+    y: f32,
+    z: f32,
+    vx: f32,
+    vy: f32,
+    vz: f32,
+    speed: f32,
+}
+
+struct ExportFrame
+{
+    frame: u64,
+    birds: Vec<Bird>,
+}
+
+pub struct Exporter
+{
+    sender: Option<SyncSender<ExportFrame>>,
+    writer: Option<JoinHandle<io::Result<()>>>,
+    every: u64,
+    frame: u64,
+}
+
+impl Exporter
+{
+    pub fn create<P: AsRef<Path>>(path: P, format: ExportFormat, every: u32) -> io::Result<Exporter>
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        if format == ExportFormat::Csv {
+            writeln!(out, "frame,bird,x,y,z,vx,vy,vz,speed")?;
+        }
+
+        let (sender, receiver) = mpsc::sync_channel::<ExportFrame>(EXPORT_QUEUE);
+        let writer = thread::spawn(move || {
+            for export in receiver {
+                for (index, bird) in export.birds.iter().enumerate() {
+                    let (position, velocity) = (bird.position, bird.velocity);
+                    let row = ExportRow
+                    {
+                        frame: export.frame,
+                        bird: index,
+                        x: position.x,
+                        y: position.y,
+                        z: position.z,
+                        vx: velocity.x,
+                        vy: velocity.y,
+                        vz: velocity.z,
+                        speed: bird.speed(),
+                    };
+                    write_row(&mut out, format, &row)?;
+                }
+            }
+            out.flush()
+        });
+
+        Ok(Exporter { sender: Some(sender), writer: Some(writer), every: every.max(1) as u64, frame: 0 })
+    }
+
+    pub fn record(&mut self, birds: &[Bird])
+    {
+        if self.frame.is_multiple_of(self.every) {
+            if let Some(sender) = &self.sender {
+                if sender.send(ExportFrame { frame: self.frame, birds: birds.to_vec() }).is_err() {
+                    self.sender = None;
+                }
+            }
+        }
+        self.frame += 1;
+    }
+
+    pub fn finish(mut self) -> io::Result<()>
+    {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()>
+    {
+        self.sender = None;
+        match self.writer.take() {
+            Some(writer) => writer.join().unwrap_or_else(|_| Err(io::Error::other("export writer thread panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Exporter
+{
+    fn drop(&mut self)
+    {
+        if let Err(err) = self.close() {
+            eprintln!("warning: export did not finish cleanly: {}", err);
+        }
+    }
+}
+
+fn write_row<W: Write>(out: &mut W, format: ExportFormat, row: &ExportRow) -> io::Result<()>
+{
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{},{},{},{},{},{},{},{},{}", row.frame, row.bird, row.x, row.y, row.z, row.vx, row.vy, row.vz, row.speed)
+        },
+        ExportFormat::JsonLines => {
+            serde_json::to_writer(&mut *out, row)?;
+            writeln!(out)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::flock::{Flock, FlockConfig};
+
+    fn export_run(format: ExportFormat, extension: &str) -> (Vec<Vec<Bird>>, String)
+    {
+        let path = std::env::temp_dir().join(format!("flock-export-{}.{}", std::process::id(), extension));
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 12, seed: Some(6), ..FlockConfig::default() }, 6);
+        let mut exporter = Exporter::create(&path, format, 2).unwrap();
+        let mut exported = Vec::new();
+
+        for frame in 0..5 {
+            flock.update();
+            exporter.record(&flock.birds);
+            if frame % 2 == 0 {
+                exported.push(flock.birds.clone());
+            }
+        }
+        exporter.finish().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        (exported, text)
+    }
+
+    #[test]
+    fn csv_rows_match_the_exported_frames()
+    {
+        let (exported, text) = export_run(ExportFormat::Csv, "csv");
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("frame,bird,x,y,z,vx,vy,vz,speed"));
+
+        let rows: Vec<Vec<f32>> = lines.map(|line| line.split(',').map(|field| field.parse().unwrap()).collect()).collect();
+        assert_eq!(rows.len(), 3 * 12);
+        for (row, (frame, bird)) in rows.iter().zip((0..3).flat_map(|frame| (0..12).map(move |bird| (frame, bird)))) {
+            let expected = &exported[frame][bird];
+            assert_eq!((row[0], row[1]), ((frame * 2) as f32, bird as f32));
+            assert_eq!(&row[2..8], &[expected.position.x, expected.position.y, expected.position.z, expected.velocity.x, expected.velocity.y, expected.velocity.z]);
+            assert_eq!(row[8], expected.speed());
+        }
+    }
+
+    #[test]
+    fn json_lines_rows_match_the_exported_frames()
+    {
+        let (exported, text) = export_run(ExportFormat::JsonLines, "jsonl");
+        let rows: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(rows.len(), 3 * 12);
+        let last = &rows[rows.len() - 1];
+        let expected = &exported[2][11];
+        assert_eq!((last["frame"].as_u64(), last["bird"].as_u64()), (Some(4), Some(11)));
+        assert_eq!(last["x"].as_f64().unwrap() as f32, expected.position.x);
+        assert_eq!(last["vz"].as_f64().unwrap() as f32, expected.velocity.z);
+    }
+
+    #[test]
+    fn format_follows_the_extension()
+    {
+        assert_eq!(ExportFormat::from_path("out.jsonl"), ExportFormat::JsonLines);
+        assert_eq!(ExportFormat::from_path("out.csv"), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path("out"), ExportFormat::Csv);
+        assert!("parquet".parse::<ExportFormat>().is_err());
+    }
+}
@@ -0,0 +1,2790 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+pub use crate::bird::{Bird, BirdState};
+use crate::behaviour::BehaviourState;
+use crate::bird::PERCH_REACH;
+use crate::daynight;
+use crate::events::{self, FlockEvent, FlockObserver, Observers};
+use crate::food::FoodSource;
+use crate::grid::SpatialGrid;
+use crate::metrics::{self, FlockMetrics};
+use crate::octree::{Aggregate, Octree, OCTREE_CAPACITY};
+use crate::params::SimParam;
+use crate::predator::{self, Predator, FEAR_WEIGHT};
+use crate::steering::{self, Neighbourhood, SteeringBehaviour, SteeringContext, SteeringForces, WeightedBehaviour};
+use crate::terrain::Terrain;
+use crate::wind::{ConstantWind, TurbulentWind, Wind, WindField, WindMode, TURBULENCE, TURBULENCE_SCALE};
+
+pub const REFERENCE_DT: f32 = 1.0 / 60.0;
+pub const NUM_BIRDS: usize = 10;
+pub const NUM_PREDATORS: usize = 0;
+pub const NUM_FOOD_SOURCES: usize = 0;
+pub const FOOD_AMOUNT: f32 = 20.0;
+pub const FOOD_SPAWN_RATE: f32 = 0.01;
+pub const FOOD_WEIGHT: f32 = 0.003;
+pub const MAX_SPEED: f32 = 0.02;
+pub const MAX_FORCE: f32 = 0.001;
+pub const NEIGHBOUR_RADIUS: f32 = 1.0;
+pub const SEPARATION_WEIGHT: f32 = 1.5;
+pub const ALIGNMENT_WEIGHT: f32 = 0.001;
+pub const COHESION_WEIGHT: f32 = 0.001;
+pub const GRAVITY: f32 = 0.0005;
+pub const BOUNDARY_SIZE: f32 = 5.0;
+pub const BOUNDARY_FORCE: f32 = 0.1;
+pub const BOUNDARY_MARGIN: f32 = 1.0;
+pub const HOME_FORCE: f32 = MAX_FORCE;
+pub const SOFT_HOME_FORCE: f32 = MAX_FORCE * 0.25;
+pub const VIEW_ANGLE: f32 = 240.0;
+pub const ATTRACTION_WEIGHT: f32 = 0.005;
+pub const MASS_VARIANCE: f32 = 0.2;
+pub const SPEED_VARIANCE: f32 = 0.05;
+pub const SOCIABILITY_VARIANCE: f32 = 0.25;
+pub const WANDER_WEIGHT: f32 = 0.5;
+pub const WANDER_DISTANCE: f32 = 2.0;
+pub const WANDER_RADIUS: f32 = 1.0;
+pub const WANDER_JITTER: f32 = 0.3;
+pub const INTER_SPECIES_WEIGHT: f32 = 1.0;
+pub const OBSTACLE_LOOK_AHEAD: f32 = 1.0;
+pub const OBSTACLE_MARGIN: f32 = 0.2;
+pub const OBSTACLE_AVOID_WEIGHT: f32 = 0.01;
+pub const OBSTACLE_PROBES: u32 = 8;
+pub const ATTRACTOR_STRENGTH: f32 = 0.005;
+pub const ATTRACTOR_RADIUS: f32 = 2.0;
+pub const PULSE_STEPS: f32 = 90.0;
+pub const MAX_WIND: f32 = BOUNDARY_FORCE * 0.1;
+pub const WIND_SEED: u64 = 7;
+pub const PERCH_PROBABILITY: f32 = 0.02;
+pub const PERCH_MIN_STEPS: u32 = 60;
+pub const PERCH_MAX_STEPS: u32 = 240;
+pub const PERCH_HEIGHT: f32 = 0.05;
+pub const PERCH_SPEED: f32 = MAX_SPEED * 0.25;
+pub const PERCH_SEEK_WEIGHT: f32 = 1.0;
+pub const ENERGY_DRAIN: f32 = 0.0005;
+pub const ENERGY_RECOVERY: f32 = 0.004;
+pub const DAY_LENGTH: f32 = 0.0;
+pub const ROOST_WEIGHT: f32 = 0.003;
+pub const BIRD_RADIUS: f32 = 0.03;
+pub const COLLISION_RESTITUTION: f32 = 0.5;
+pub const SPAWN_SPREAD: f32 = 0.5;
+pub const WAYPOINT_WEIGHT: f32 = 0.002;
+pub const WAYPOINT_RADIUS: f32 = 0.75;
+pub const NUM_LEADERS: usize = 0;
+pub const LEADER_WEIGHT: f32 = 1.0;
+pub const LEADER_PATH_WEIGHT: f32 = 2.0;
+pub const MAX_SPECIES: usize = u8::MAX as usize + 1;
+pub const TOPOLOGICAL_K: usize = 7;
+pub const BARNES_HUT_THETA: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighbourSearch
+{
+    #[default]
+    BruteForce,
+    Octree,
+    Grid,
+    BarnesHut,
+}
+
+impl FromStr for NeighbourSearch
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NeighbourSearch, String>
+    {
+        match s {
+            "brute-force" | "brute_force" => Ok(NeighbourSearch::BruteForce),
+            "octree" => Ok(NeighbourSearch::Octree),
+            "grid" => Ok(NeighbourSearch::Grid),
+            "barnes-hut" | "barnes_hut" => Ok(NeighbourSearch::BarnesHut),
+            _ => Err(format!("unknown neighbour search '{}', expected brute-force, octree, grid or barnes-hut", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionModel
+{
+    #[default]
+    Metric,
+    Topological { k: usize },
+}
+
+impl FromStr for InteractionModel
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<InteractionModel, String>
+    {
+        match s {
+            "metric" => Ok(InteractionModel::Metric),
+            "topological" => Ok(InteractionModel::Topological { k: TOPOLOGICAL_K }),
+            _ => Err(format!("unknown interaction model '{}', expected metric or topological", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Plane
+{
+    #[default]
+    Xz,
+    Xy,
+}
+
+impl FromStr for Plane
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Plane, String>
+    {
+        match s {
+            "xz" => Ok(Plane::Xz),
+            "xy" => Ok(Plane::Xy),
+            _ => Err(format!("unknown plane '{}', expected xz or xy", s)),
+        }
+    }
+}
+
+impl Plane
+{
+    pub fn normal_axis(self) -> usize
+    {
+        match self {
+            Plane::Xz => 1,
+            Plane::Xy => 2,
+        }
+    }
+
+    pub fn flatten(self, v: Vector3<f32>) -> Vector3<f32>
+    {
+        let mut flat = v;
+        flat[self.normal_axis()] = 0.0;
+        flat
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundaryShape
+{
+    #[default]
+    Cube,
+    Wrap,
+    Sphere { radius: f32 },
+    Unbounded,
+    Soft
+    {
+        #[serde(default = "Vector3::zeros")]
+        home: Vector3<f32>,
+        radius: f32,
+    },
+}
+
+impl BoundaryShape
+{
+    pub fn next(self, boundary_size: f32) -> BoundaryShape
+    {
+        match self {
+            BoundaryShape::Cube => BoundaryShape::Wrap,
+            BoundaryShape::Wrap => BoundaryShape::Sphere { radius: boundary_size / 2.0 },
+            BoundaryShape::Sphere { .. } => BoundaryShape::Unbounded,
+            BoundaryShape::Unbounded => BoundaryShape::Soft { home: Vector3::zeros(), radius: boundary_size / 2.0 },
+            BoundaryShape::Soft { .. } => BoundaryShape::Cube,
+        }
+    }
+
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            BoundaryShape::Cube => "cube",
+            BoundaryShape::Wrap => "wrap",
+            BoundaryShape::Sphere { .. } => "sphere",
+            BoundaryShape::Unbounded => "unbounded",
+            BoundaryShape::Soft { .. } => "soft",
+        }
+    }
+}
+
+impl FromStr for BoundaryShape
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BoundaryShape, String>
+    {
+        match s {
+            "cube" => Ok(BoundaryShape::Cube),
+            "wrap" => Ok(BoundaryShape::Wrap),
+            "sphere" => Ok(BoundaryShape::Sphere { radius: BOUNDARY_SIZE / 2.0 }),
+            "unbounded" => Ok(BoundaryShape::Unbounded),
+            "soft" => Ok(BoundaryShape::Soft { home: Vector3::zeros(), radius: BOUNDARY_SIZE / 2.0 }),
+            _ => Err(format!("unknown boundary '{}', expected cube, wrap, sphere, unbounded or soft", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlockConfig
+{
+    pub num_birds: usize,
+    pub max_speed: f32,
+    pub neighbour_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub boundary_size: f32,
+    pub boundary_shape: BoundaryShape,
+    pub view_angle: f32,
+    pub attraction_weight: f32,
+    pub wander_weight: f32,
+    pub mass_variance: f32,
+    pub speed_variance: f32,
+    pub sociability_variance: f32,
+    pub behaviour_states: bool,
+    pub gravity: Vector3<f32>,
+    pub waypoint_weight: f32,
+    pub waypoint_radius: f32,
+    pub waypoint_loop: bool,
+    pub num_leaders: usize,
+    pub leader_weight: f32,
+    pub inter_species_weight: f32,
+    pub neighbour_search: NeighbourSearch,
+    pub barnes_hut_theta: f32,
+    pub interaction: InteractionModel,
+    pub wind: Vector3<f32>,
+    pub wind_mode: WindMode,
+    pub wind_seed: u64,
+    pub turbulence: f32,
+    pub turbulence_scale: f32,
+    pub perch_probability: f32,
+    pub perch_min_steps: u32,
+    pub perch_max_steps: u32,
+    pub energy_drain: f32,
+    pub energy_recovery: f32,
+    pub day_length: f32,
+    pub roost_weight: f32,
+    pub bird_radius: f32,
+    pub plane: Option<Plane>,
+    pub num_predators: usize,
+    pub num_food_sources: usize,
+    pub food_amount: f32,
+    pub food_spawn_rate: f32,
+    pub food_weight: f32,
+    pub colour: Option<[f32; 3]>,
+    pub spawn: Option<SpawnRegion>,
+    pub seed: Option<u64>,
+}
+
+impl Default for FlockConfig
+{
+    fn default() -> FlockConfig
+    {
+        FlockConfig
+        {
+            num_birds: NUM_BIRDS,
+            max_speed: MAX_SPEED,
+            neighbour_radius: NEIGHBOUR_RADIUS,
+            separation_weight: SEPARATION_WEIGHT,
+            alignment_weight: ALIGNMENT_WEIGHT,
+            cohesion_weight: COHESION_WEIGHT,
+            boundary_size: BOUNDARY_SIZE,
+            boundary_shape: BoundaryShape::default(),
+            view_angle: VIEW_ANGLE,
+            attraction_weight: ATTRACTION_WEIGHT,
+            wander_weight: WANDER_WEIGHT,
+            mass_variance: MASS_VARIANCE,
+            speed_variance: SPEED_VARIANCE,
+            sociability_variance: SOCIABILITY_VARIANCE,
+            behaviour_states: true,
+            gravity: Vector3::new(0.0, -GRAVITY, 0.0),
+            waypoint_weight: WAYPOINT_WEIGHT,
+            waypoint_radius: WAYPOINT_RADIUS,
+            waypoint_loop: true,
+            num_leaders: NUM_LEADERS,
+            leader_weight: LEADER_WEIGHT,
+            inter_species_weight: INTER_SPECIES_WEIGHT,
+            neighbour_search: NeighbourSearch::default(),
+            barnes_hut_theta: BARNES_HUT_THETA,
+            interaction: InteractionModel::default(),
+            wind: Vector3::zeros(),
+            wind_mode: WindMode::default(),
+            wind_seed: WIND_SEED,
+            turbulence: TURBULENCE,
+            turbulence_scale: TURBULENCE_SCALE,
+            perch_probability: PERCH_PROBABILITY,
+            perch_min_steps: PERCH_MIN_STEPS,
+            perch_max_steps: PERCH_MAX_STEPS,
+            energy_drain: ENERGY_DRAIN,
+            energy_recovery: ENERGY_RECOVERY,
+            day_length: DAY_LENGTH,
+            roost_weight: ROOST_WEIGHT,
+            bird_radius: BIRD_RADIUS,
+            plane: None,
+            num_predators: NUM_PREDATORS,
+            num_food_sources: NUM_FOOD_SOURCES,
+            food_amount: FOOD_AMOUNT,
+            food_spawn_rate: FOOD_SPAWN_RATE,
+            food_weight: FOOD_WEIGHT,
+            colour: None,
+            spawn: None,
+            seed: None,
+        }
+    }
+}
+
+impl FlockConfig
+{
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if self.num_birds == 0 {
+            return Err("num-birds must be at least 1".to_string());
+        }
+        if !positive(self.max_speed) {
+            return Err(format!("max-speed must be positive, got {}", self.max_speed));
+        }
+        if !positive(self.neighbour_radius) {
+            return Err(format!("neighbour-radius must be positive, got {}", self.neighbour_radius));
+        }
+        if !positive(self.boundary_size) {
+            return Err(format!("boundary-size must be positive, got {}", self.boundary_size));
+        }
+        match self.boundary_shape {
+            BoundaryShape::Sphere { radius } | BoundaryShape::Soft { radius, .. } if !positive(radius) => {
+                return Err(format!("sphere-radius must be positive, got {}", radius));
+            },
+            BoundaryShape::Soft { home, .. } if !home.iter().all(|h| h.is_finite()) => {
+                return Err("home must be finite".to_string());
+            },
+            _ => (),
+        }
+        if !(self.view_angle > 0.0 && self.view_angle <= 360.0) {
+            return Err(format!("view-angle must be in (0, 360] degrees, got {}", self.view_angle));
+        }
+        if !non_negative(self.attraction_weight) {
+            return Err(format!("attraction-weight must not be negative, got {}", self.attraction_weight));
+        }
+        if !non_negative(self.waypoint_weight) {
+            return Err(format!("waypoint-weight must not be negative, got {}", self.waypoint_weight));
+        }
+        if !positive(self.waypoint_radius) {
+            return Err(format!("waypoint-radius must be positive, got {}", self.waypoint_radius));
+        }
+        if !non_negative(self.leader_weight) {
+            return Err(format!("leader-weight must not be negative, got {}", self.leader_weight));
+        }
+        if self.num_leaders > self.num_birds {
+            return Err(format!("leaders ({}) must not exceed num-birds ({})", self.num_leaders, self.num_birds));
+        }
+        if !self.gravity.iter().all(|g| g.is_finite()) {
+            return Err(format!("gravity must be finite, got {},{},{}", self.gravity.x, self.gravity.y, self.gravity.z));
+        }
+        if !non_negative(self.inter_species_weight) {
+            return Err(format!("inter-species-weight must not be negative, got {}", self.inter_species_weight));
+        }
+        if !(self.perch_probability >= 0.0 && self.perch_probability <= 1.0) {
+            return Err(format!("perch-probability must be in [0, 1], got {}", self.perch_probability));
+        }
+        if self.perch_min_steps > self.perch_max_steps {
+            return Err(format!("perch-min-steps ({}) must not exceed perch-max-steps ({})", self.perch_min_steps, self.perch_max_steps));
+        }
+        if !non_negative(self.energy_drain) {
+            return Err(format!("energy-drain must not be negative, got {}", self.energy_drain));
+        }
+        if !non_negative(self.energy_recovery) {
+            return Err(format!("energy-recovery must not be negative, got {}", self.energy_recovery));
+        }
+        if !non_negative(self.day_length) {
+            return Err(format!("day-length must not be negative, got {}", self.day_length));
+        }
+        if !non_negative(self.roost_weight) {
+            return Err(format!("roost-weight must not be negative, got {}", self.roost_weight));
+        }
+        if !non_negative(self.bird_radius) {
+            return Err(format!("bird-radius must not be negative, got {}", self.bird_radius));
+        }
+        if !positive(self.food_amount) {
+            return Err(format!("food-amount must be positive, got {}", self.food_amount));
+        }
+        if !(self.food_spawn_rate >= 0.0 && self.food_spawn_rate <= 1.0) {
+            return Err(format!("food-spawn-rate must be in [0, 1], got {}", self.food_spawn_rate));
+        }
+        if !non_negative(self.food_weight) {
+            return Err(format!("food-weight must not be negative, got {}", self.food_weight));
+        }
+        if let Some(colour) = self.colour {
+            if !colour.iter().all(|c| (0.0..=1.0).contains(c)) {
+                return Err(format!("colour components must be in [0, 1], got {:?}", colour));
+            }
+        }
+        if !positive(self.barnes_hut_theta) {
+            return Err(format!("barnes-hut-theta must be positive, got {}", self.barnes_hut_theta));
+        }
+        if self.interaction == (InteractionModel::Topological { k: 0 }) {
+            return Err("topological-k must be at least 1".to_string());
+        }
+        if !non_negative(self.turbulence) {
+            return Err(format!("turbulence must not be negative, got {}", self.turbulence));
+        }
+        if !positive(self.turbulence_scale) {
+            return Err(format!("turbulence-scale must be positive, got {}", self.turbulence_scale));
+        }
+        if self.wind.norm().is_nan() || self.wind.norm() > MAX_WIND {
+            return Err(format!("wind strength must be at most {}, got {}", MAX_WIND, self.wind.norm()));
+        }
+
+        let weights = [
+            ("separation-weight", self.separation_weight),
+            ("alignment-weight", self.alignment_weight),
+            ("cohesion-weight", self.cohesion_weight),
+            ("wander-weight", self.wander_weight),
+        ];
+        for (name, weight) in weights.iter() {
+            if !non_negative(*weight) {
+                return Err(format!("{} must not be negative, got {}", name, weight));
+            }
+        }
+        let variances = [
+            ("mass-variance", self.mass_variance),
+            ("speed-variance", self.speed_variance),
+            ("sociability-variance", self.sociability_variance),
+        ];
+        for (name, variance) in variances.iter() {
+            if !(*variance >= 0.0 && *variance < 1.0) {
+                return Err(format!("{} must be in [0, 1), got {}", name, variance));
+            }
+        }
+        if let Some(spawn) = &self.spawn {
+            if !positive(spawn.radius) {
+                return Err(format!("spawn radius must be positive, got {}", spawn.radius));
+            }
+            if !spawn.center.iter().all(|c| c.is_finite()) {
+                return Err("spawn center must be finite".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn flat_axis(&self) -> Option<usize>
+    {
+        self.plane.map(Plane::normal_axis)
+    }
+
+    pub fn rng(&self) -> StdRng
+    {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    pub fn view_cos(&self) -> f32
+    {
+        (self.view_angle.to_radians() / 2.0).cos()
+    }
+
+    pub fn boundary_force(&self, position: Vector3<f32>) -> Vector3<f32>
+    {
+        let mut force = Vector3::zeros();
+
+        match self.boundary_shape {
+            BoundaryShape::Cube => {
+                for i in 0..3 {
+                    let depth = position[i].abs() - (self.boundary_size/2.0 - BOUNDARY_MARGIN);
+                    if depth > 0.0 {
+                        force[i] = -position[i].signum() * BOUNDARY_FORCE * (depth / BOUNDARY_MARGIN).min(1.0);
+                    }
+                }
+            },
+            BoundaryShape::Sphere { radius } => {
+                let depth = position.norm() - (radius - BOUNDARY_MARGIN);
+                if depth > 0.0 {
+                    force = normalize_or_zero(position) * -BOUNDARY_FORCE * (depth / BOUNDARY_MARGIN).min(1.0);
+                }
+            },
+            BoundaryShape::Wrap => (),
+            BoundaryShape::Unbounded => {
+                let half_size = self.boundary_size / 2.0;
+                let distance = position.norm();
+                if distance > half_size {
+                    force = normalize_or_zero(position) * -HOME_FORCE * ((distance - half_size) / half_size).min(1.0);
+                }
+            },
+            BoundaryShape::Soft { home, radius } => {
+                let offset = home - position;
+                let depth = offset.norm() - radius;
+                if depth > 0.0 {
+                    force = normalize_or_zero(offset) * (SOFT_HOME_FORCE * depth / BOUNDARY_MARGIN).min(HOME_FORCE);
+                }
+            },
+        }
+
+        force
+    }
+
+    pub fn clamp_point(&self, position: Vector3<f32>) -> Option<Vector3<f32>>
+    {
+        match self.boundary_shape {
+            BoundaryShape::Wrap => {
+                let half_size = self.boundary_size / 2.0;
+                if position.iter().all(|p| p.abs() <= half_size) {
+                    return None;
+                }
+                Some(position.map(|p| (p + half_size).rem_euclid(self.boundary_size) - half_size))
+            },
+            BoundaryShape::Unbounded | BoundaryShape::Soft { .. } => None,
+            BoundaryShape::Cube => {
+                let half_size = self.boundary_size / 2.0;
+                if position.iter().all(|p| p.abs() <= half_size) {
+                    return None;
+                }
+                Some(position.map(|p| p.max(-half_size).min(half_size)))
+            },
+            BoundaryShape::Sphere { radius } => {
+                let dist = position.norm();
+                if dist <= radius {
+                    return None;
+                }
+                Some(position * (radius / dist))
+            },
+        }
+    }
+
+    pub fn crossed_boundary(&self, bird: &Bird) -> bool
+    {
+        match self.boundary_shape {
+            BoundaryShape::Wrap => (bird.position - bird.previous_position).iter().any(|d| d.abs() > self.boundary_size / 2.0),
+            BoundaryShape::Soft { .. } => false,
+            _ => self.boundary_force(bird.position) != Vector3::zeros() && self.boundary_force(bird.previous_position) == Vector3::zeros(),
+        }
+    }
+
+    pub fn wind_field(&self) -> Wind
+    {
+        match self.wind_mode {
+            WindMode::Constant => Wind::Constant(ConstantWind { force: self.wind }),
+            WindMode::Turbulent => Wind::Turbulent(TurbulentWind::new(self.wind, self.turbulence, self.turbulence_scale, self.wind_seed)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpawnRegion
+{
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl SpawnRegion
+{
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vector3<f32>
+    {
+        loop {
+            let offset = Vector3::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()).map(|v| v * 2.0 - 1.0);
+            if offset.norm_squared() <= 1.0 {
+                return self.center + offset * self.radius;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum ObstacleShape
+{
+    Sphere { radius: f32 },
+    Box { half_extents: Vector3<f32> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle
+{
+    pub center: Vector3<f32>,
+    #[serde(flatten)]
+    pub shape: ObstacleShape,
+}
+
+impl Obstacle
+{
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if !self.center.iter().all(|v| v.is_finite()) {
+            return Err("obstacle center must be finite".to_string());
+        }
+        match self.shape {
+            ObstacleShape::Sphere { radius } if !positive(radius) => Err(format!("obstacle radius must be positive, got {}", radius)),
+            ObstacleShape::Box { half_extents } if !half_extents.iter().all(|&v| v > 0.0 && v.is_finite()) => {
+                Err(format!("obstacle half-extents must be positive, got {:?}", half_extents))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn top(&self) -> Vector3<f32>
+    {
+        match self.shape {
+            ObstacleShape::Sphere { radius } => self.center + Vector3::new(0.0, radius, 0.0),
+            ObstacleShape::Box { half_extents } => self.center + Vector3::new(0.0, half_extents.y, 0.0),
+        }
+    }
+
+    pub fn surface(&self, point: Vector3<f32>) -> (f32, Vector3<f32>)
+    {
+        let offset = point - self.center;
+        match self.shape {
+            ObstacleShape::Sphere { radius } => {
+                let mut normal = normalize_or_zero(offset);
+                if normal == Vector3::zeros() {
+                    normal = Vector3::new(0.0, 1.0, 0.0);
+                }
+                (offset.norm() - radius, normal)
+            },
+            ObstacleShape::Box { half_extents } => {
+                let outside = offset.map(|v| v.abs()) - half_extents;
+                if outside.iter().any(|&v| v > 0.0) {
+                    let excess = Vector3::new(
+                        offset.x.signum() * outside.x.max(0.0),
+                        offset.y.signum() * outside.y.max(0.0),
+                        offset.z.signum() * outside.z.max(0.0),
+                    );
+                    return (excess.norm(), normalize_or_zero(excess));
+                }
+
+                let mut axis = 0;
+                for i in 1..3
+                {
+                    if outside[i] > outside[axis] {
+                        axis = i;
+                    }
+                }
+                let mut normal = Vector3::zeros();
+                normal[axis] = if offset[axis] < 0.0 { -1.0 } else { 1.0 };
+                (outside[axis], normal)
+            },
+        }
+    }
+
+    pub fn avoidance_force(&self, bird: &Bird) -> Vector3<f32>
+    {
+        let dir = normalize_or_zero(bird.velocity);
+        if dir == Vector3::zeros() {
+            return Vector3::zeros();
+        }
+
+        let radius = match self.shape {
+            ObstacleShape::Sphere { radius } => radius,
+            ObstacleShape::Box { .. } => return self.box_avoidance(bird.position, dir),
+        };
+
+        let to_center = self.center - bird.position;
+        let along = to_center.dot(&dir);
+        if along <= 0.0 || along > OBSTACLE_LOOK_AHEAD + radius {
+            return Vector3::zeros();
+        }
+
+        let offset = dir * along - to_center;
+        if offset.norm() >= radius + OBSTACLE_MARGIN {
+            return Vector3::zeros();
+        }
+
+        let mut tangent = normalize_or_zero(offset);
+        if tangent == Vector3::zeros() {
+            tangent = perpendicular(dir);
+        }
+
+        let urgency = 1.0 - ((along - radius).max(0.0) / OBSTACLE_LOOK_AHEAD).min(1.0);
+        tangent * (OBSTACLE_AVOID_WEIGHT * urgency)
+    }
+
+    fn box_avoidance(&self, position: Vector3<f32>, dir: Vector3<f32>) -> Vector3<f32>
+    {
+        for step in 0..OBSTACLE_PROBES
+        {
+            let along = OBSTACLE_LOOK_AHEAD * (step + 1) as f32 / OBSTACLE_PROBES as f32;
+            let (distance, normal) = self.surface(position + dir * along);
+            if distance < OBSTACLE_MARGIN {
+                let mut tangent = normalize_or_zero(normal - dir * normal.dot(&dir));
+                if tangent == Vector3::zeros() {
+                    tangent = perpendicular(dir);
+                }
+
+                let urgency = 1.0 - step as f32 / OBSTACLE_PROBES as f32;
+                return tangent * (OBSTACLE_AVOID_WEIGHT * urgency);
+            }
+        }
+
+        Vector3::zeros()
+    }
+
+    pub fn push_out(&self, bird: &mut Bird)
+    {
+        let (distance, normal) = self.surface(bird.position);
+        if distance >= 0.0 {
+            return;
+        }
+
+        bird.position -= normal * distance;
+
+        let inward = bird.velocity.dot(&normal);
+        if inward < 0.0 {
+            bird.velocity -= normal * inward;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attractor
+{
+    pub position: Vector3<f32>,
+    pub strength: f32,
+    pub radius: f32,
+    #[serde(default)]
+    pub remaining: Option<f32>,
+}
+
+impl Attractor
+{
+    pub fn force(&self, bird: &Bird) -> Vector3<f32>
+    {
+        let offset = self.position - bird.position;
+        let dist = offset.norm();
+        if dist >= self.radius {
+            return Vector3::zeros();
+        }
+
+        let falloff = 1.0 - dist / self.radius;
+        let fade = self.remaining.map_or(1.0, |steps| (steps / PULSE_STEPS).min(1.0));
+        normalize_or_zero(offset) * (self.strength * falloff * fade)
+    }
+}
+
+trait NeighbourIndex: Sync
+{
+    fn query_radius_into(&self, point: Vector3<f32>, radius: f32, found: &mut Vec<usize>);
+}
+
+impl NeighbourIndex for Octree
+{
+    fn query_radius_into(&self, point: Vector3<f32>, radius: f32, found: &mut Vec<usize>)
+    {
+        Octree::query_radius_into(self, point, radius, found);
+    }
+}
+
+impl NeighbourIndex for SpatialGrid
+{
+    fn query_radius_into(&self, point: Vector3<f32>, radius: f32, found: &mut Vec<usize>)
+    {
+        SpatialGrid::query_radius_into(self, point, radius, found);
+    }
+}
+
+/// Read-only view of the flock for one step. Each bird runs the stages in
+/// order: neighbourhood, flocking, wander, environment, guidance, integration
+/// and settle. The stages are plain methods over `Bird`, not ECS systems.
+struct StepContext<'a>
+{
+    front: &'a [Bird],
+    positions: &'a [[f32; 4]],
+    config: &'a FlockConfig,
+    species: &'a [FlockConfig],
+    wind: &'a dyn WindField,
+    tree: Option<&'a dyn NeighbourIndex>,
+    far_field: Option<&'a Octree>,
+    time: f32,
+    obstacles: &'a [Obstacle],
+    terrain: Option<&'a Terrain>,
+    attractors: &'a [Attractor],
+    attractor: Option<Vector3<f32>>,
+    waypoint: Option<Vector3<f32>>,
+    roosts: &'a [Vector3<f32>],
+    time_of_day: Option<f32>,
+    predators: &'a [Predator],
+    food: &'a [FoodSource],
+    behaviours: &'a [WeightedBehaviour],
+    leaders: &'a [Vector3<f32>],
+}
+
+impl<'a> StepContext<'a>
+{
+    fn neighbourhood(&self, index: usize, bird: &Bird, own: &FlockConfig, found: &mut Vec<usize>, nearest: &mut Vec<(f32, usize)>, groups: &mut Vec<Aggregate>) -> Neighbourhood
+    {
+        let front = self.front;
+        let positions = self.positions;
+        let view_cos = own.view_cos();
+        let max_search = self.config.boundary_size * 2.0;
+
+        let mut neighbourhood = Neighbourhood::default();
+        let radius = match own.interaction {
+            InteractionModel::Metric => own.neighbour_radius,
+            InteractionModel::Topological { .. } => f32::INFINITY,
+        };
+
+        let mut visit = |other_index: usize, other: &Bird| {
+            let dist = bird.distance_to(other);
+
+            if other_index != index && dist < radius && bird.can_see(other.position, view_cos)
+             {
+                neighbourhood.add(bird, other, dist);
+            }
+        };
+
+        match (own.interaction, self.tree, self.far_field) {
+            (InteractionModel::Metric, _, Some(octree)) => {
+                octree.query_radius_into(bird.position, own.neighbour_radius, found);
+                for &other in found.iter() {
+                    visit(other, &front[other]);
+                }
+                octree.query_far_field(bird.position, own.neighbour_radius, self.config.barnes_hut_theta, bird.species as usize, groups);
+                for group in groups.iter() {
+                    if bird.can_see(group.centroid(), view_cos) {
+                        neighbourhood.add_far_group(bird, group, own.neighbour_radius);
+                    }
+                }
+            },
+            (InteractionModel::Metric, Some(tree), None) => {
+                tree.query_radius_into(bird.position, own.neighbour_radius, found);
+                for &other in found.iter() {
+                    visit(other, &front[other]);
+                }
+            },
+            (InteractionModel::Metric, None, None) => {
+                let [x, y, z, _] = positions[index];
+                let radius_squared = own.neighbour_radius * own.neighbour_radius;
+                for (other_index, other) in positions.iter().enumerate()
+                {
+                    let (dx, dy, dz) = (other[0] - x, other[1] - y, other[2] - z);
+                    if dx * dx + dy * dy + dz * dz < radius_squared {
+                        visit(other_index, &front[other_index]);
+                    }
+                }
+            },
+            (InteractionModel::Topological { k }, tree, _) => {
+                nearest.clear();
+                match tree {
+                    Some(tree) => {
+                        let mut search = own.neighbour_radius;
+                        loop {
+                            tree.query_radius_into(bird.position, search, found);
+                            let visible = found.iter().filter(|&&other| other != index && bird.can_see(front[other].position, view_cos)).count();
+                            if visible >= k || found.len() >= front.len() || search >= max_search {
+                                break;
+                            }
+                            search *= 2.0;
+                        }
+                        nearest.extend(found.iter().map(|&other| ((front[other].position - bird.position).norm_squared(), other)));
+                    },
+                    None => {
+                        let [x, y, z, _] = positions[index];
+                        nearest.extend(positions.iter().enumerate().map(|(other, p)| {
+                            let (dx, dy, dz) = (p[0] - x, p[1] - y, p[2] - z);
+                            (dx * dx + dy * dy + dz * dz, other)
+                        }));
+                    },
+                }
+
+                nearest.retain(|&(_, other)| other != index && bird.can_see(front[other].position, view_cos));
+                if nearest.len() > k {
+                    nearest.select_nth_unstable_by(k, |a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+                    nearest.truncate(k);
+                }
+                for &(_, other) in nearest.iter() {
+                    visit(other, &front[other]);
+                }
+            },
+        }
+
+        neighbourhood
+    }
+
+    fn flocking(&self, bird: &mut Bird, neighbourhood: &Neighbourhood, own: &FlockConfig)
+    {
+        let mut total = Vector3::zeros();
+        self.behaviour_forces(bird, neighbourhood, own, |_, force| total += force);
+        bird.apply_force(total);
+
+        let weight = bird.behaviour.weights().flocking;
+        bird.apply_force(steering::repulsion(bird, neighbourhood, own.max_speed) * self.config.inter_species_weight * weight);
+    }
+
+    /// Hands each registered behaviour's name and weighted force to `each`.
+    fn behaviour_forces<F: FnMut(&str, Vector3<f32>)>(&self, bird: &Bird, neighbourhood: &Neighbourhood, own: &FlockConfig, mut each: F)
+    {
+        let context = SteeringContext { config: own, time: self.time, waypoint: self.waypoint };
+        let weight = bird.behaviour.weights().flocking;
+        for entry in self.behaviours.iter() {
+            each(entry.behaviour.name(), entry.behaviour.force(bird, neighbourhood, &context) * entry.weight * weight);
+        }
+    }
+
+    fn wander(&self, index: usize, bird: &mut Bird, own: &FlockConfig)
+    {
+        let weight = own.wander_weight * bird.behaviour.weights().wander;
+        if !positive(weight) {
+            return;
+        }
+
+        bird.wander = normalize_or_zero(bird.wander + wander_jitter(index, self.time) * WANDER_JITTER);
+        let target = normalize_or_zero(bird.velocity) * WANDER_DISTANCE + bird.wander * WANDER_RADIUS;
+        bird.apply_force(bird.steer(target, own.max_speed) * weight / (1.0 + bird.neighbour_count as f32));
+    }
+
+    fn environment(&self, bird: &mut Bird, own: &FlockConfig)
+    {
+        let weights = bird.behaviour.weights();
+        bird.apply_force(self.wind.sample(bird.position, self.time));
+
+        for obstacle in self.obstacles.iter() {
+            bird.apply_force(obstacle.avoidance_force(bird));
+        }
+
+        if let Some(terrain) = self.terrain {
+            bird.apply_force(terrain.avoidance_force(bird));
+        }
+
+        for attractor in self.attractors.iter() {
+            bird.apply_force(attractor.force(bird) * weights.attraction);
+        }
+
+        for source in self.food.iter() {
+            bird.apply_force(source.force(bird, own.food_weight) * weights.attraction);
+        }
+
+        bird.apply_force(bird.steer(predator::fear(bird, self.predators), own.max_speed) * FEAR_WEIGHT * weights.fear);
+
+        if let Some(point) = self.attractor {
+            let offset = point - bird.position;
+            let dist = offset.norm();
+            let pull = self.config.attraction_weight / (1.0 + dist * dist);
+            bird.apply_force(normalize_or_zero(offset) * pull);
+        }
+    }
+
+    fn guidance(&self, bird: &mut Bird, own: &FlockConfig)
+    {
+        let weight = bird.behaviour.weights().guidance;
+        if bird.leader {
+            if let Some(target) = self.waypoint {
+                bird.apply_force(bird.steer(target - bird.position, own.max_speed) * LEADER_PATH_WEIGHT * weight);
+            }
+        } else if !self.leaders.is_empty() {
+            let nearest = self.leaders.iter()
+                .map(|&leader| leader - bird.position)
+                .min_by(|a, b| a.norm_squared().partial_cmp(&b.norm_squared()).unwrap_or(std::cmp::Ordering::Equal));
+            if let Some(offset) = nearest {
+                bird.apply_force(bird.steer(offset, own.max_speed) * self.config.leader_weight * weight);
+            }
+        } else if let Some(target) = self.waypoint {
+            bird.apply_force(normalize_or_zero(target - bird.position) * self.config.waypoint_weight * weight);
+        }
+
+        if let Some(time_of_day) = self.time_of_day {
+            bird.apply_force(daynight::roost_force(bird.position, self.roosts, time_of_day) * self.config.roost_weight);
+        }
+
+        if bird.tired() && self.config.plane.is_none() {
+            if let Some(site) = bird.perch_site(own, self.obstacles, self.terrain) {
+                bird.apply_force(bird.steer(site - bird.position, own.max_speed) * PERCH_SEEK_WEIGHT);
+            }
+        }
+    }
+
+    fn settle(&self, bird: &mut Bird, own: &FlockConfig, roll: f32)
+    {
+        for obstacle in self.obstacles.iter() {
+            obstacle.push_out(bird);
+        }
+        if let Some(terrain) = self.terrain {
+            terrain.push_out(bird);
+        }
+
+        if let Some(plane) = self.config.plane {
+            bird.position = plane.flatten(bird.position);
+            bird.velocity = plane.flatten(bird.velocity);
+        } else if bird.tired() {
+            if let Some(site) = bird.perch_site(own, self.obstacles, self.terrain) {
+                if (site - bird.position).norm() < PERCH_REACH {
+                    bird.perch(own, roll);
+                    bird.position = site;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct StepBuffers
+{
+    grid: SpatialGrid,
+    octree: Octree,
+    collisions: SpatialGrid,
+    leaders: Vec<Vector3<f32>>,
+}
+
+impl Clone for StepBuffers
+{
+    fn clone(&self) -> StepBuffers
+    {
+        StepBuffers::default()
+    }
+}
+
+impl fmt::Debug for StepBuffers
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "step buffers")
+    }
+}
+
+fn config_for<'a>(config: &'a FlockConfig, species: &'a [FlockConfig], id: u8) -> &'a FlockConfig
+{
+    species.get(id as usize).unwrap_or(config)
+}
+
+const FOOD_SALT: u64 = 0xD1B5_4A32_D192_ED03;
+
+fn unit_roll(index: usize, time: f32) -> f32
+{
+    hash_unit((index as u64) ^ ((time as u64) << 32))
+}
+
+fn hash_unit(mut z: u64) -> f32
+{
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn pack_positions(birds: &[Bird], positions: &mut Vec<[f32; 4]>)
+{
+    positions.clear();
+    positions.extend(birds.iter().map(|bird| [bird.position.x, bird.position.y, bird.position.z, 0.0]));
+}
+
+fn wander_jitter(index: usize, time: f32) -> Vector3<f32>
+{
+    let seed = (index as u64) ^ ((time as u64) << 32);
+    let roll = |salt: u64| hash_unit(seed ^ salt) * 2.0 - 1.0;
+    Vector3::new(roll(0x51_7CC1_B727_220A), roll(0x2545_F491_4F6C_DD1D), roll(0x6A09_E667_F3BC_C909))
+}
+
+/// `false` for NaN as well as for zero and negative values, so validation
+/// written as `!positive(x)` rejects NaN too.
+pub fn positive<T: PartialOrd + Default>(x: T) -> bool
+{
+    x > T::default()
+}
+
+/// `false` for NaN as well as for negative values.
+pub fn non_negative<T: PartialOrd + Default>(x: T) -> bool
+{
+    x >= T::default()
+}
+
+pub fn normalize_or_zero(v: Vector3<f32>) -> Vector3<f32>
+{
+    v.try_normalize(1e-6).unwrap_or_else(Vector3::zeros)
+}
+
+fn perpendicular(dir: Vector3<f32>) -> Vector3<f32>
+{
+    let axis = if dir.y.abs() < 0.9 { Vector3::new(0.0, 1.0, 0.0) } else { Vector3::new(1.0, 0.0, 0.0) };
+    normalize_or_zero(dir.cross(&axis))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Flock
+{
+    #[serde(default)]
+    pub config: FlockConfig,
+    #[serde(default)]
+    pub species: Vec<FlockConfig>,
+    #[serde(default)]
+    pub birds: Vec<Bird>,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub terrain: Option<Terrain>,
+    #[serde(default)]
+    pub attractors: Vec<Attractor>,
+    #[serde(default)]
+    pub attractor: Option<Vector3<f32>>,
+    #[serde(default)]
+    pub time: f32,
+    #[serde(default)]
+    pub steps: u64,
+    #[serde(default)]
+    pub waypoints: Vec<Vector3<f32>>,
+    #[serde(default)]
+    pub waypoint_index: usize,
+    #[serde(default)]
+    pub predators: Vec<Predator>,
+    #[serde(default)]
+    pub roosts: Vec<Vector3<f32>>,
+    #[serde(default)]
+    pub food: Vec<FoodSource>,
+    #[serde(skip, default = "steering::default_behaviours")]
+    behaviours: Vec<WeightedBehaviour>,
+    #[serde(skip)]
+    back: Vec<Bird>,
+    /// Packed `[x, y, z, 0]` copy of each bird's position, refreshed at the start
+    /// of every step for the brute-force neighbour scan. Velocities and
+    /// accelerations stay in `birds`, which is the flock's public storage.
+    #[serde(skip)]
+    positions: Vec<[f32; 4]>,
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    groups: Option<usize>,
+    #[serde(skip)]
+    buffers: StepBuffers,
+}
+
+impl Flock
+{
+    pub fn new(config: FlockConfig) -> Flock
+    {
+        let mut rng = config.rng();
+        Flock::with_rng(config, &mut rng)
+    }
+
+    pub fn with_seed(config: FlockConfig, seed: u64) -> Flock
+    {
+        Flock::with_rng(config, &mut StdRng::seed_from_u64(seed))
+    }
+
+    pub fn with_rng<R: Rng + ?Sized>(config: FlockConfig, rng: &mut R) -> Flock
+    {
+        Flock::with_species_rng(config, Vec::new(), rng)
+    }
+
+    pub fn with_species(config: FlockConfig, species: Vec<FlockConfig>) -> Flock
+    {
+        let mut rng = config.rng();
+        Flock::with_species_rng(config, species, &mut rng)
+    }
+
+    pub fn with_species_rng<R: Rng + ?Sized>(config: FlockConfig, species: Vec<FlockConfig>, rng: &mut R) -> Flock
+    {
+        let mut flock = Flock { config, species, birds: Vec::new(), obstacles: Vec::new(), terrain: None, attractors: Vec::new(), attractor: None, time: 0.0, steps: 0, waypoints: Vec::new(), waypoint_index: 0, predators: Vec::new(), roosts: Vec::new(), food: Vec::new(), behaviours: steering::default_behaviours(), back: Vec::new(), positions: Vec::new(), observers: Observers::default(), groups: None, buffers: StepBuffers::default() };
+        flock.spawn_birds(rng);
+        flock.spawn_predators(rng);
+        flock.spawn_food(rng);
+        flock
+    }
+
+    fn spawn_birds<R: Rng + ?Sized>(&mut self, rng: &mut R)
+    {
+        let mut birds = Vec::new();
+
+        if self.species.is_empty() {
+            for _ in 0..self.config.num_birds
+            {
+                let bird = Bird::with_rng(&self.config, rng);
+                birds.push(bird);
+            }
+        } else {
+            for (species, own) in self.species.iter().enumerate()
+            {
+                for _ in 0..own.num_birds
+                {
+                    let mut bird = Bird::with_rng(own, rng);
+                    bird.species = species as u8;
+                    birds.push(bird);
+                }
+            }
+            self.config.num_birds = birds.len();
+        }
+
+        self.birds = birds;
+        self.assign_leaders();
+    }
+
+    pub fn assign_leaders(&mut self)
+    {
+        let num_leaders = self.config.num_leaders;
+        for (index, bird) in self.birds.iter_mut().enumerate() {
+            bird.leader = index < num_leaders;
+        }
+    }
+
+    pub fn spawn_predators<R: Rng + ?Sized>(&mut self, rng: &mut R)
+    {
+        self.predators.truncate(self.config.num_predators);
+        while self.predators.len() < self.config.num_predators {
+            let predator = Predator::with_rng(&self.config, rng);
+            self.predators.push(predator);
+        }
+    }
+
+    pub fn spawn_food<R: Rng + ?Sized>(&mut self, rng: &mut R)
+    {
+        self.food.truncate(self.config.num_food_sources);
+        while self.food.len() < self.config.num_food_sources {
+            let source = FoodSource::with_rng(&self.config, rng);
+            self.food.push(source);
+        }
+    }
+
+    pub fn species_config(&self, species: u8) -> &FlockConfig
+    {
+        config_for(&self.config, &self.species, species)
+    }
+
+    pub fn grid_cell_size(&self) -> f32
+    {
+        self.species.iter().map(|own| own.neighbour_radius).fold(self.config.neighbour_radius, f32::max)
+    }
+
+    /// The separation, alignment and cohesion behaviours' share of a bird's
+    /// steering, found the way `step` finds it but without a spatial index.
+    pub fn steering_forces(&self, index: usize) -> Option<SteeringForces>
+    {
+        let bird = self.birds.get(index)?;
+        let own = self.species_config(bird.species);
+        let mut positions = Vec::with_capacity(self.birds.len());
+        pack_positions(&self.birds, &mut positions);
+        let wind = self.config.wind_field();
+        let context = self.context(&positions, &wind, false);
+        let neighbourhood = context.neighbourhood(index, bird, own, &mut Vec::new(), &mut Vec::new(), &mut Vec::new());
+
+        let mut forces = SteeringForces::default();
+        context.behaviour_forces(bird, &neighbourhood, own, |name, force| match name {
+            "separation" => forces.separation += force,
+            "alignment" => forces.alignment += force,
+            "cohesion" => forces.cohesion += force,
+            _ => (),
+        });
+        Some(forces)
+    }
+
+    /// The per-step view of the flock. `indexed` selects the neighbour index
+    /// built at the start of `step`; without it neighbours are brute-forced.
+    fn context<'a>(&'a self, positions: &'a [[f32; 4]], wind: &'a dyn WindField, indexed: bool) -> StepContext<'a>
+    {
+        let config = &self.config;
+        let buffers = &self.buffers;
+        let tree: Option<&dyn NeighbourIndex> = match config.neighbour_search {
+            _ if !indexed => None,
+            NeighbourSearch::BruteForce => None,
+            NeighbourSearch::Octree | NeighbourSearch::BarnesHut => Some(&buffers.octree),
+            NeighbourSearch::Grid => Some(&buffers.grid),
+        };
+        StepContext
+        {
+            front: &self.birds,
+            positions,
+            config,
+            species: &self.species,
+            wind,
+            tree,
+            far_field: Some(&buffers.octree).filter(|_| indexed && config.neighbour_search == NeighbourSearch::BarnesHut),
+            time: self.time,
+            obstacles: &self.obstacles,
+            terrain: self.terrain.as_ref(),
+            attractors: &self.attractors,
+            attractor: self.attractor,
+            waypoint: self.current_waypoint(),
+            roosts: &self.roosts,
+            time_of_day: self.time_of_day(),
+            predators: &self.predators,
+            food: &self.food,
+            behaviours: &self.behaviours,
+            leaders: &buffers.leaders,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String>
+    {
+        self.config.validate()?;
+
+        if self.species.len() > MAX_SPECIES {
+            return Err(format!("at most {} species are supported, got {}", MAX_SPECIES, self.species.len()));
+        }
+        for (index, own) in self.species.iter().enumerate() {
+            own.validate().map_err(|message| format!("species {}: {}", index, message))?;
+        }
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            obstacle.validate().map_err(|message| format!("obstacle {}: {}", index, message))?;
+        }
+        if let Some(terrain) = &self.terrain {
+            terrain.validate()?;
+        }
+        if let Some(index) = self.roosts.iter().position(|roost| !roost.iter().all(|v| v.is_finite())) {
+            return Err(format!("roost {} must be finite", index));
+        }
+
+        Ok(())
+    }
+
+    pub fn behaviours(&self) -> &[WeightedBehaviour]
+    {
+        &self.behaviours
+    }
+
+    pub fn add_behaviour<B: SteeringBehaviour + 'static>(&mut self, behaviour: B, weight: f32)
+    {
+        self.behaviours.push(WeightedBehaviour { behaviour: Arc::new(behaviour), weight });
+    }
+
+    pub fn remove_behaviour(&mut self, name: &str) -> bool
+    {
+        let count = self.behaviours.len();
+        self.behaviours.retain(|entry| entry.behaviour.name() != name);
+        self.behaviours.len() != count
+    }
+
+    pub fn set_behaviour_weight(&mut self, name: &str, weight: f32) -> Result<(), String>
+    {
+        match self.behaviours.iter_mut().find(|entry| entry.behaviour.name() == name) {
+            Some(entry) => {
+                entry.weight = weight;
+                Ok(())
+            },
+            None => Err(format!("no steering behaviour named '{}'", name)),
+        }
+    }
+
+    pub fn reset_behaviours(&mut self)
+    {
+        self.behaviours = steering::default_behaviours();
+    }
+
+    pub fn add_observer(&mut self, observer: Arc<dyn FlockObserver>)
+    {
+        self.observers.add(observer);
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<FlockEvent>
+    {
+        let (observer, receiver) = events::channel();
+        self.observers.add(observer);
+        receiver
+    }
+
+    pub fn clear_observers(&mut self)
+    {
+        self.observers.clear();
+        self.groups = None;
+    }
+
+    pub fn has_default_behaviours(&self) -> bool
+    {
+        let defaults = steering::default_behaviours();
+        self.behaviours.len() == defaults.len() && self.behaviours.iter().zip(defaults.iter()).all(|(entry, default)| {
+            entry.behaviour.name() == default.behaviour.name() && entry.weight == default.weight
+        })
+    }
+
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> io::Result<()>
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> io::Result<Flock>
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let mut flock: Flock = serde_json::from_reader(reader)?;
+
+        let mut rng = flock.config.rng();
+        if flock.birds.is_empty() {
+            flock.spawn_birds(&mut rng);
+        } else if !flock.birds.iter().any(|bird| bird.leader) {
+            flock.assign_leaders();
+        }
+        flock.spawn_predators(&mut rng);
+        flock.spawn_food(&mut rng);
+
+        for bird in flock.birds.iter_mut() {
+            bird.previous_position = bird.position;
+        }
+
+        Ok(flock)
+    }
+
+    pub fn copy_from(&mut self, other: &Flock)
+    {
+        self.config.clone_from(&other.config);
+        self.species.clone_from(&other.species);
+        self.birds.clone_from(&other.birds);
+        self.obstacles.clone_from(&other.obstacles);
+        self.terrain.clone_from(&other.terrain);
+        self.attractors.clone_from(&other.attractors);
+        self.attractor = other.attractor;
+        self.time = other.time;
+        self.steps = other.steps;
+        self.waypoints.clone_from(&other.waypoints);
+        self.waypoint_index = other.waypoint_index;
+        self.predators.clone_from(&other.predators);
+        self.roosts.clone_from(&other.roosts);
+        self.food.clone_from(&other.food);
+        self.behaviours.clone_from(&other.behaviours);
+        self.observers.clone_from(&other.observers);
+        self.groups = other.groups;
+    }
+
+    pub fn clamp_to_boundary(&mut self) -> usize
+    {
+        let mut clamped = 0;
+
+        for bird in self.birds.iter_mut() {
+            if let Some(position) = self.config.clamp_point(bird.position) {
+                bird.position = position;
+                bird.previous_position = position;
+                clamped += 1;
+            }
+        }
+
+        clamped
+    }
+
+    pub fn spawn(&mut self, bird: Bird) -> usize
+    {
+        self.birds.push(bird);
+        self.config.num_birds = self.birds.len();
+        self.birds.len() - 1
+    }
+
+    pub fn spawn_near_centroid<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize
+    {
+        let centroid = self.centroid().unwrap_or_else(Vector3::zeros);
+        let mut bird = Bird::with_rng(&self.config, rng);
+        let offset = Vector3::new(
+            rng.gen_range(-SPAWN_SPREAD..SPAWN_SPREAD),
+            rng.gen_range(-SPAWN_SPREAD..SPAWN_SPREAD),
+            rng.gen_range(-SPAWN_SPREAD..SPAWN_SPREAD),
+        );
+        let position = centroid + offset;
+        let position = self.config.plane.map_or(position, |plane| plane.flatten(position));
+
+        bird.position = self.config.clamp_point(position).unwrap_or(position);
+        bird.previous_position = bird.position;
+        self.spawn(bird)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Bird, String>
+    {
+        if index >= self.birds.len() {
+            return Err(format!("bird index {} out of range for a flock of {}", index, self.birds.len()));
+        }
+
+        let bird = self.birds.swap_remove(index);
+        self.config.num_birds = self.birds.len();
+        Ok(bird)
+    }
+
+    pub fn resize<R: Rng + ?Sized>(&mut self, count: usize, rng: &mut R)
+    {
+        while self.birds.len() < count {
+            self.spawn_near_centroid(rng);
+        }
+        while self.birds.len() > count {
+            let index = rng.gen_range(0..self.birds.len());
+            self.birds.swap_remove(index);
+        }
+        self.config.num_birds = self.birds.len();
+    }
+
+    pub fn centroid(&self) -> Option<Vector3<f32>>
+    {
+        if self.birds.is_empty() {
+            return None;
+        }
+
+        let sum = self.birds.iter().fold(Vector3::zeros(), |sum, bird| sum + bird.position);
+        Some(sum / self.birds.len() as f32)
+    }
+
+    pub fn leader_centroid(&self) -> Option<Vector3<f32>>
+    {
+        let (sum, count) = self.birds.iter()
+            .filter(|bird| bird.leader)
+            .fold((Vector3::zeros(), 0), |(sum, count), bird| (sum + bird.position, count + 1));
+
+        if count == 0 {
+            return None;
+        }
+        Some(sum / count as f32)
+    }
+
+    pub fn average_speed(&self) -> f32
+    {
+        if self.birds.is_empty() {
+            return 0.0;
+        }
+
+        self.birds.iter().map(|bird| bird.speed()).sum::<f32>() / self.birds.len() as f32
+    }
+
+    pub fn average_nearest_distance(&self) -> f32
+    {
+        if self.birds.len() < 2 {
+            return 0.0;
+        }
+
+        let birds = &self.birds;
+        let tree = Octree::build(birds.iter().map(|bird| bird.position).collect(), self.config.boundary_size / 2.0, OCTREE_CAPACITY);
+        let total: f32 = birds.par_iter().enumerate().map(|(index, bird)| {
+            tree.nearest(bird.position, index).map_or(0.0, |(_, dist)| dist)
+        }).sum();
+
+        total / birds.len() as f32
+    }
+
+    pub fn order_parameter(&self) -> f32
+    {
+        if self.birds.is_empty() {
+            return 0.0;
+        }
+
+        let sum = self.birds.iter().fold(Vector3::zeros(), |sum, bird| sum + normalize_or_zero(bird.velocity));
+        sum.norm() / self.birds.len() as f32
+    }
+
+    pub fn metrics(&self) -> FlockMetrics
+    {
+        FlockMetrics::measure(self)
+    }
+
+    pub fn clusters(&self) -> Vec<usize>
+    {
+        metrics::clusters(&self.birds, self.config.neighbour_radius)
+    }
+
+    pub fn current_waypoint(&self) -> Option<Vector3<f32>>
+    {
+        self.waypoints.get(self.waypoint_index).copied()
+    }
+
+    pub fn time_of_day(&self) -> Option<f32>
+    {
+        daynight::time_of_day(self.time, self.config.day_length)
+    }
+
+    pub fn clamp_waypoints(&mut self) -> usize
+    {
+        let mut clamped = 0;
+        for waypoint in self.waypoints.iter_mut() {
+            if let Some(position) = self.config.clamp_point(*waypoint) {
+                *waypoint = position;
+                clamped += 1;
+            }
+        }
+        clamped
+    }
+
+    pub fn add_waypoint(&mut self, point: Vector3<f32>)
+    {
+        self.waypoints.push(self.config.clamp_point(point).unwrap_or(point));
+    }
+
+    pub fn clear_waypoints(&mut self)
+    {
+        self.waypoints.clear();
+        self.waypoint_index = 0;
+    }
+
+    fn advance_waypoint(&mut self)
+    {
+        let (target, centroid) = match (self.current_waypoint(), self.leader_centroid().or_else(|| self.centroid())) {
+            (Some(target), Some(centroid)) => (target, centroid),
+            _ => return,
+        };
+
+        if (target - centroid).norm() < self.config.waypoint_radius {
+            self.waypoint_index += 1;
+            if self.waypoint_index >= self.waypoints.len() && self.config.waypoint_loop {
+                self.waypoint_index = 0;
+            }
+        }
+    }
+
+    pub fn add_obstacle(&mut self, center: Vector3<f32>, radius: f32)
+    {
+        self.obstacles.push(Obstacle { center, shape: ObstacleShape::Sphere { radius } });
+    }
+
+    pub fn add_box_obstacle(&mut self, center: Vector3<f32>, half_extents: Vector3<f32>)
+    {
+        self.obstacles.push(Obstacle { center, shape: ObstacleShape::Box { half_extents } });
+    }
+
+    pub fn add_attractor(&mut self, position: Vector3<f32>, strength: f32)
+    {
+        self.attractors.push(Attractor { position, strength, radius: ATTRACTOR_RADIUS, remaining: None });
+    }
+
+    pub fn add_pulse(&mut self, position: Vector3<f32>, strength: f32)
+    {
+        self.attractors.push(Attractor { position, strength, radius: ATTRACTOR_RADIUS, remaining: Some(PULSE_STEPS) });
+    }
+
+    pub fn clear_attractors(&mut self)
+    {
+        self.attractors.clear();
+    }
+
+    pub fn adjust_wind(&mut self, delta: Vector3<f32>)
+    {
+        self.config.wind = (self.config.wind + delta).cap_magnitude(MAX_WIND);
+    }
+
+    pub fn scale_param(&mut self, param: SimParam, factor: f32)
+    {
+        param.scale(&mut self.config, factor);
+        for own in self.species.iter_mut() {
+            param.scale(own, factor);
+        }
+    }
+
+    pub fn reset_param(&mut self, param: SimParam)
+    {
+        param.reset(&mut self.config);
+        for own in self.species.iter_mut() {
+            param.reset(own);
+        }
+    }
+
+    pub fn set_param(&mut self, param: SimParam, value: f32)
+    {
+        param.set(&mut self.config, value);
+        for own in self.species.iter_mut() {
+            param.set(own, value);
+        }
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vector3<f32>)
+    {
+        self.config.gravity = gravity;
+        for own in self.species.iter_mut() {
+            own.gravity = gravity;
+        }
+    }
+
+    pub fn turn_wind(&mut self, angle: f32)
+    {
+        let (x, y, z) = (self.config.wind.x, self.config.wind.y, self.config.wind.z);
+        let (sin, cos) = angle.sin_cos();
+        self.config.wind = Vector3::new(x * cos - z * sin, y, x * sin + z * cos);
+    }
+
+    pub fn tilt_wind(&mut self, delta: f32)
+    {
+        let strength = self.config.wind.norm();
+        let direction = normalize_or_zero(self.config.wind);
+        if direction == Vector3::zeros() {
+            return;
+        }
+
+        let tilted = normalize_or_zero(direction + Vector3::new(0.0, delta, 0.0));
+        self.config.wind = tilted * strength;
+    }
+
+    pub fn scale_wind(&mut self, factor: f32)
+    {
+        self.config.wind = (self.config.wind * factor).cap_magnitude(MAX_WIND);
+    }
+
+    pub fn update(&mut self)
+    {
+        self.step(REFERENCE_DT);
+    }
+
+    pub fn step(&mut self, dt: f32)
+    {
+        crate::profile_scope!("flock step");
+        let scale = dt / REFERENCE_DT;
+        if self.back.len() != self.birds.len() {
+            self.back.clone_from(&self.birds);
+        }
+
+        pack_positions(&self.birds, &mut self.positions);
+
+        let cell_size = self.grid_cell_size();
+        let config = &self.config;
+        let front = &self.birds;
+        let buffers = &mut self.buffers;
+        match config.neighbour_search {
+            NeighbourSearch::BruteForce => (),
+            NeighbourSearch::Octree => {
+                crate::profile_scope!("octree");
+                buffers.octree.rebuild(front.iter().map(|bird| bird.position), config.boundary_size / 2.0, OCTREE_CAPACITY);
+            },
+            NeighbourSearch::BarnesHut => {
+                crate::profile_scope!("barnes-hut octree");
+                buffers.octree.rebuild(front.iter().map(|bird| bird.position), config.boundary_size / 2.0, OCTREE_CAPACITY);
+                buffers.octree.aggregate(self.species.len(), |index| {
+                    let bird = &front[index];
+                    Some((bird.species as usize, bird.velocity)).filter(|_| bird.state == BirdState::Flying)
+                });
+            },
+            NeighbourSearch::Grid => {
+                crate::profile_scope!("grid");
+                buffers.grid.rebuild(front.iter().map(|bird| bird.position), cell_size, config.flat_axis());
+            },
+        }
+        buffers.leaders.clear();
+        buffers.leaders.extend(front.iter().filter(|bird| bird.leader && bird.state == BirdState::Flying).map(|bird| bird.position));
+
+        let wind = self.config.wind_field();
+        let positions = std::mem::take(&mut self.positions);
+        let mut back = std::mem::take(&mut self.back);
+        let context = self.context(&positions, &wind, true);
+        let (config, front) = (context.config, context.front);
+
+        back.par_iter_mut().enumerate().for_each_init(|| (Vec::new(), Vec::new(), Vec::new()), |(found, nearest, groups), (index, next)| {
+            let mut current = front[index];
+            let bird = &mut current;
+            bird.previous_position = bird.position;
+
+            let own = config_for(config, context.species, bird.species);
+            let roll = unit_roll(index, context.time);
+            bird.behaviour = if own.behaviour_states {
+                bird.behaviour.transition(bird, context.predators, context.attractors, context.food)
+            } else {
+                BehaviourState::Flocking
+            };
+
+            if bird.state == BirdState::Perched {
+                bird.neighbour_count = 0;
+                if bird.disturbed(context.attractors, context.attractor, context.predators) {
+                    bird.take_off(own);
+                } else {
+                    bird.update(own, roll, scale);
+                }
+                *next = current;
+                return;
+            }
+
+            let neighbourhood = context.neighbourhood(index, bird, own, found, nearest, groups);
+            bird.neighbour_count = neighbourhood.neighbours;
+
+            context.flocking(bird, &neighbourhood, own);
+            context.wander(index, bird, own);
+            context.environment(bird, own);
+            context.guidance(bird, own);
+            bird.update(own, roll, scale);
+            context.settle(bird, own, roll);
+
+            *next = current;
+        });
+
+        self.positions = positions;
+        self.back = back;
+        std::mem::swap(&mut self.birds, &mut self.back);
+        let collisions = self.resolve_collisions();
+        for predator in self.predators.iter_mut() {
+            predator.update(&self.birds, &self.config, scale);
+            if let Some(plane) = self.config.plane {
+                predator.position = plane.flatten(predator.position);
+                predator.velocity = plane.flatten(predator.velocity);
+            }
+        }
+        self.time += scale;
+        self.steps += 1;
+        for attractor in self.attractors.iter_mut() {
+            if let Some(steps) = &mut attractor.remaining {
+                *steps -= scale;
+            }
+        }
+        self.attractors.retain(|attractor| attractor.remaining.is_none_or(|steps| steps > 0.0));
+        self.consume_food(scale);
+        self.advance_waypoint();
+        if !self.observers.is_empty() {
+            self.report_events(collisions);
+        }
+    }
+
+    fn consume_food(&mut self, scale: f32)
+    {
+        if self.food.is_empty() && self.config.num_food_sources == 0 {
+            return;
+        }
+
+        let (config, species, birds) = (&self.config, &self.species, &self.birds);
+        for source in self.food.iter_mut() {
+            let (feeders, eaten) = birds.iter()
+                .filter(|bird| bird.behaviour == BehaviourState::Feeding && source.in_reach(bird))
+                .fold((0, 0.0), |(feeders, eaten), bird| (feeders + 1, eaten + config_for(config, species, bird.species).energy_recovery * scale));
+            source.feeders = feeders;
+            source.amount -= eaten;
+        }
+        self.food.retain(|source| source.amount > 0.0);
+
+        if self.food.len() < self.config.num_food_sources && hash_unit(self.steps ^ FOOD_SALT) < self.config.food_spawn_rate * scale {
+            let mut rng = StdRng::seed_from_u64(self.config.seed.unwrap_or(0) ^ self.steps.wrapping_mul(FOOD_SALT));
+            let source = FoodSource::with_rng(&self.config, &mut rng);
+            self.food.push(source);
+        }
+    }
+
+    fn report_events(&mut self, collisions: Vec<(usize, usize)>)
+    {
+        crate::profile_scope!("events");
+        let mut events: Vec<FlockEvent> = collisions.into_iter().map(|(first, second)| FlockEvent::BirdCollision { first, second }).collect();
+        for (index, bird) in self.birds.iter().enumerate() {
+            if self.config.crossed_boundary(bird) {
+                events.push(FlockEvent::BoundaryHit { bird: index, position: bird.position });
+            }
+        }
+        for (index, predator) in self.predators.iter().enumerate() {
+            if let Some(bird) = predator.caught(&self.birds) {
+                events.push(FlockEvent::PredatorCatch { predator: index, bird });
+            }
+        }
+
+        let groups = metrics::group_count(&self.birds, self.config.neighbour_radius);
+        match self.groups {
+            Some(previous) if groups > previous => events.push(FlockEvent::FlockSplit { groups }),
+            Some(previous) if groups < previous => events.push(FlockEvent::FlockMerge { groups }),
+            _ => (),
+        }
+        self.groups = Some(groups);
+
+        for event in events.iter() {
+            self.observers.notify(event);
+        }
+    }
+
+    fn resolve_collisions(&mut self) -> Vec<(usize, usize)>
+    {
+        crate::profile_scope!("collisions");
+        let radius = self.config.bird_radius;
+        if !positive(radius) {
+            return Vec::new();
+        }
+
+        let diameter = radius * 2.0;
+        self.buffers.collisions.rebuild(self.birds.iter().map(|bird| bird.position), diameter, self.config.flat_axis());
+        let grid = &self.buffers.collisions;
+        let front = &self.birds;
+        let collisions = if self.observers.is_empty() {
+            Vec::new()
+        } else {
+            (0..front.len()).into_par_iter().flat_map(|index| {
+                grid.query_radius(front[index].position, diameter).into_iter()
+                    .filter(|&other| other > index && (front[index].state == BirdState::Flying || front[other].state == BirdState::Flying))
+                    .map(|other| (index, other))
+                    .collect::<Vec<_>>()
+            }).collect()
+        };
+        self.back.par_iter_mut().enumerate().for_each_init(Vec::new, |found, (index, next)| {
+            let mut bird = front[index];
+            if bird.state == BirdState::Flying {
+                grid.query_radius_into(bird.position, diameter, found);
+                for &other_index in found.iter() {
+                    if other_index == index {
+                        continue;
+                    }
+                    let other = &front[other_index];
+                    let offset = front[index].position - other.position;
+                    let dist = offset.norm();
+                    let normal = if dist > 1e-6 {
+                        offset / dist
+                    } else if index < other_index {
+                        Vector3::x()
+                    } else {
+                        -Vector3::x()
+                    };
+                    let share = if other.state == BirdState::Flying { 0.5 } else { 1.0 };
+                    bird.position += normal * (diameter - dist) * share;
+
+                    let approach = (front[index].velocity - other.velocity).dot(&normal);
+                    if approach < 0.0 {
+                        bird.velocity -= normal * approach * (1.0 + COLLISION_RESTITUTION) * share;
+                    }
+                }
+            }
+            *next = bird;
+        });
+        std::mem::swap(&mut self.birds, &mut self.back);
+        collisions
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn quiet_config() -> FlockConfig
+    {
+        FlockConfig
+        {
+            num_birds: 0,
+            boundary_size: 100.0,
+            gravity: Vector3::zeros(),
+            wander_weight: 0.0,
+            mass_variance: 0.0,
+            speed_variance: 0.0,
+            sociability_variance: 0.0,
+            behaviour_states: false,
+            perch_probability: 0.0,
+            energy_drain: 0.0,
+            bird_radius: 0.0,
+            seed: Some(1),
+            ..FlockConfig::default()
+        }
+    }
+
+    fn bird_at(position: Vector3<f32>, velocity: Vector3<f32>) -> Bird
+    {
+        let mut bird = Bird::with_rng(&quiet_config(), &mut StdRng::seed_from_u64(0));
+        bird.position = position;
+        bird.previous_position = position;
+        bird.velocity = velocity;
+        bird.energy = 1.0;
+        bird
+    }
+
+    fn quiet_flock(birds: &[Bird]) -> Flock
+    {
+        let mut flock = Flock::with_seed(quiet_config(), 1);
+        for &bird in birds.iter() {
+            flock.spawn(bird);
+        }
+        flock
+    }
+
+    #[test]
+    fn bird_flying_at_a_sphere_turns_before_reaching_it()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::new(-3.0, 0.0, 0.0), Vector3::new(MAX_SPEED, 0.0, 0.0))]);
+        flock.add_obstacle(Vector3::zeros(), 0.5);
+
+        let mut deflected_at = None;
+        for _ in 0..400 {
+            flock.update();
+            let bird = &flock.birds[0];
+            if deflected_at.is_none() && bird.velocity.x < MAX_SPEED * 0.99 {
+                deflected_at = Some(bird.position);
+            }
+        }
+
+        let deflected_at = deflected_at.expect("the bird never turned");
+        assert!(deflected_at.norm() > 0.5 + OBSTACLE_MARGIN, "turned only at {:?}", deflected_at);
+        let bird = &flock.birds[0];
+        assert!(bird.position.norm() > 0.5, "ended inside the obstacle at {:?}", bird.position);
+        assert!(bird.position.y.abs() + bird.position.z.abs() > 0.1, "never left the obstacle's axis");
+    }
+
+    #[test]
+    fn bird_inside_an_obstacle_is_pushed_to_its_surface()
+    {
+        let obstacle = Obstacle { center: Vector3::zeros(), shape: ObstacleShape::Sphere { radius: 1.0 } };
+        let mut bird = bird_at(Vector3::new(0.2, 0.0, 0.0), Vector3::new(-MAX_SPEED, 0.0, 0.0));
+        obstacle.push_out(&mut bird);
+
+        assert!((bird.position - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-6);
+        assert!(bird.velocity.x >= 0.0);
+    }
+
+    #[test]
+    fn only_neighbours_inside_the_view_cone_steer_a_bird()
+    {
+        let heading = Vector3::new(MAX_SPEED, 0.0, 0.0);
+        let sideways = Vector3::new(0.0, MAX_SPEED, 0.0);
+        let bird = bird_at(Vector3::zeros(), heading);
+        let ahead = bird_at(Vector3::new(0.5, 0.0, 0.0), sideways);
+        let behind = bird_at(Vector3::new(-0.5, 0.0, 0.0), -sideways);
+
+        let both = quiet_flock(&[bird, ahead, behind]).steering_forces(0).unwrap();
+        let only_ahead = quiet_flock(&[bird, ahead]).steering_forces(0).unwrap();
+        let only_behind = quiet_flock(&[bird, behind]).steering_forces(0).unwrap();
+
+        assert_ne!(only_ahead, SteeringForces::default());
+        assert_eq!(both, only_ahead);
+        assert_eq!(only_behind, SteeringForces::default());
+
+        let mut with_behind = quiet_flock(&[bird, ahead, behind]);
+        let mut without = quiet_flock(&[bird, ahead]);
+        with_behind.update();
+        without.update();
+        assert!((with_behind.birds[0].velocity - without.birds[0].velocity).norm() < 1e-7);
+    }
+
+    #[test]
+    fn stationary_bird_sees_all_around()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        let behind = bird_at(Vector3::new(-0.5, 0.0, 0.0), Vector3::new(0.0, MAX_SPEED, 0.0));
+
+        assert_ne!(quiet_flock(&[bird, behind]).steering_forces(0).unwrap(), SteeringForces::default());
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_flight()
+    {
+        let run = |seed: u64| {
+            let mut flock = Flock::new(FlockConfig { num_birds: 60, seed: Some(seed), ..FlockConfig::default() });
+            for _ in 0..50 {
+                flock.update();
+            }
+            flock.birds.iter().map(|bird| bird.position).collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_result()
+    {
+        let simulate = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                let mut flock = Flock::with_seed(FlockConfig { num_birds: 200, seed: Some(11), ..FlockConfig::default() }, 11);
+                for _ in 0..30 {
+                    flock.update();
+                }
+                flock.birds
+            })
+        };
+
+        assert_eq!(simulate(1), simulate(4));
+    }
+
+    #[test]
+    fn constant_wind_accelerates_a_lone_bird_uniformly()
+    {
+        let wind = 0.0001;
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros())]);
+        flock.config.wind = Vector3::new(wind, 0.0, 0.0);
+
+        let steps = 100;
+        for _ in 0..steps {
+            flock.update();
+        }
+
+        let expected = wind * (steps * (steps + 1) / 2) as f32;
+        let bird = &flock.birds[0];
+        assert!((bird.position - Vector3::new(expected, 0.0, 0.0)).norm() < 1e-4, "ended at {:?}, expected x = {}", bird.position, expected);
+        assert!((bird.velocity.x - wind * steps as f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sphere_boundary_pushes_stray_birds_toward_the_centre()
+    {
+        let config = FlockConfig { boundary_shape: BoundaryShape::Sphere { radius: 5.0 }, ..quiet_config() };
+        let outward = Vector3::new(1.0, 2.0, -2.0) / 3.0;
+
+        let force = config.boundary_force(outward * 5.1);
+        assert!((force + outward * BOUNDARY_FORCE).norm() < 1e-7, "force {:?}", force);
+        assert_eq!(config.boundary_force(outward * 2.0), Vector3::zeros());
+
+        let mut flock = Flock::with_seed(config, 1);
+        flock.spawn(bird_at(outward * 5.1, outward * MAX_SPEED));
+        flock.update();
+        assert!(flock.birds[0].position.norm() <= 5.0 + 1e-5);
+        assert!(flock.birds[0].velocity.dot(&outward) <= 0.0);
+    }
+
+    #[test]
+    fn wrap_carries_birds_to_the_opposite_face_and_unbounded_never_clamps()
+    {
+        let config = FlockConfig { boundary_shape: BoundaryShape::Wrap, boundary_size: 4.0, ..quiet_config() };
+        let mut flock = Flock::with_seed(config, 1);
+        flock.spawn(bird_at(Vector3::new(2.0 - MAX_SPEED * 0.5, 0.5, 0.0), Vector3::new(MAX_SPEED, 0.0, 0.0)));
+        flock.update();
+        let bird = &flock.birds[0];
+        assert!((bird.position - Vector3::new(-2.0 + MAX_SPEED * 0.5, 0.5, 0.0)).norm() < 1e-5, "wrapped to {:?}", bird.position);
+        assert_eq!(bird.velocity, Vector3::new(MAX_SPEED, 0.0, 0.0));
+        assert_eq!(flock.config.clamp_point(Vector3::new(2.5, 0.0, 0.0)), Some(Vector3::new(-1.5, 0.0, 0.0)));
+
+        let unbounded = FlockConfig { boundary_shape: BoundaryShape::Unbounded, boundary_size: 4.0, ..quiet_config() };
+        assert_eq!(unbounded.clamp_point(Vector3::new(40.0, -30.0, 2.0)), None);
+        let mut flock = Flock::with_seed(unbounded, 1);
+        flock.spawn(bird_at(Vector3::new(40.0, 0.0, 0.0), Vector3::zeros()));
+        assert_eq!(flock.clamp_to_boundary(), 0);
+        assert_eq!(flock.birds[0].position, Vector3::new(40.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn soft_boundary_pulls_home_only_past_its_radius()
+    {
+        let home = Vector3::new(1.0, -1.0, 0.5);
+        let config = FlockConfig { boundary_shape: BoundaryShape::Soft { home, radius: 2.0 }, ..quiet_config() };
+        let outward = Vector3::new(2.0, -1.0, 2.0) / 3.0;
+
+        assert_eq!(config.boundary_force(home), Vector3::zeros());
+        assert_eq!(config.boundary_force(home + outward * 1.9), Vector3::zeros());
+
+        let near = config.boundary_force(home + outward * 2.5);
+        let far = config.boundary_force(home + outward * 3.5);
+        assert!((near.normalize() + outward).norm() < 1e-6, "force {:?}", near);
+        assert!((far.normalize() + outward).norm() < 1e-6, "force {:?}", far);
+        assert!(far.norm() > near.norm());
+        assert!((config.boundary_force(home + outward * 50.0).norm() - HOME_FORCE).abs() < 1e-7);
+    }
+
+    #[test]
+    fn zero_cohesion_stops_the_pull_toward_the_centroid()
+    {
+        let pair = [bird_at(Vector3::new(-0.3, 0.0, 0.0), Vector3::zeros()), bird_at(Vector3::new(0.3, 0.0, 0.0), Vector3::zeros())];
+        let gap_after = |cohesion: f32| {
+            let mut flock = quiet_flock(&pair);
+            flock.set_param(SimParam::SeparationWeight, 0.0);
+            flock.set_param(SimParam::CohesionWeight, cohesion);
+            for _ in 0..10 {
+                flock.update();
+            }
+            (flock.birds[0].position - flock.birds[1].position).norm()
+        };
+
+        assert!(gap_after(FlockConfig::default().cohesion_weight) < 0.6 - 1e-4);
+        assert!((gap_after(0.0) - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn flock_reaches_its_waypoints_in_order()
+    {
+        let config = FlockConfig { num_birds: 30, waypoint_loop: false, seed: Some(2), ..FlockConfig::default() };
+        let mut flock = Flock::with_seed(config, 2);
+        flock.add_waypoint(Vector3::new(-1.8, 0.0, 0.0));
+        flock.add_waypoint(Vector3::new(1.8, 0.0, 0.0));
+
+        let mut reached = Vec::new();
+        for step in 0..3000 {
+            let index = flock.waypoint_index;
+            flock.update();
+            if flock.waypoint_index != index {
+                reached.push(step);
+                let centroid = flock.leader_centroid().or_else(|| flock.centroid()).unwrap();
+                assert!((centroid - flock.waypoints[index]).norm() < flock.config.waypoint_radius + 0.1);
+            }
+            if flock.current_waypoint().is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(reached.len(), 2, "reached waypoints at steps {:?}", reached);
+        assert_eq!(flock.waypoint_index, 2);
+    }
+
+    #[test]
+    fn waypoints_outside_the_boundary_are_clamped()
+    {
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 0, ..FlockConfig::default() }, 1);
+        flock.add_waypoint(Vector3::new(10.0, 0.0, 0.0));
+
+        assert!(flock.waypoints[0].x <= flock.config.boundary_size / 2.0);
+    }
+
+    #[test]
+    fn weightless_lone_bird_keeps_its_velocity()
+    {
+        let velocity = Vector3::new(0.004, -0.002, 0.007);
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), velocity)]);
+        assert_eq!(flock.config.gravity, Vector3::zeros());
+
+        for step in 1..=50 {
+            flock.update();
+            assert_eq!(flock.birds[0].velocity, velocity);
+            assert!((flock.birds[0].position - velocity * step as f32).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn mass_and_speed_factor_shape_each_birds_response()
+    {
+        let config = quiet_config();
+        let push = Vector3::new(0.002, 0.0, 0.0);
+        let speed_after_push = |mass: f32, speed_factor: f32| {
+            let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+            bird.mass = mass;
+            bird.speed_factor = speed_factor;
+            bird.apply_force(push);
+            bird.update(&config, 0.5, 1.0);
+            bird.velocity.x
+        };
+        assert!((speed_after_push(1.0, 1.0) - 0.002).abs() < 1e-7);
+        assert!((speed_after_push(2.0, 1.0) - 0.001).abs() < 1e-7);
+
+        let mut bird = bird_at(Vector3::zeros(), Vector3::new(MAX_SPEED, 0.0, 0.0));
+        bird.speed_factor = 0.9;
+        bird.apply_force(push);
+        bird.update(&config, 0.5, 1.0);
+        assert!((bird.speed() - MAX_SPEED * 0.9).abs() < 1e-7);
+
+        let other = bird_at(Vector3::new(0.3, 0.2, 0.0), Vector3::new(0.0, MAX_SPEED, 0.0));
+        let mut loner = bird_at(Vector3::zeros(), Vector3::new(MAX_SPEED, 0.0, 0.0));
+        let social = quiet_flock(&[loner, other]).steering_forces(0).unwrap();
+        loner.sociability = 0.5;
+        let aloof = quiet_flock(&[loner, other]).steering_forces(0).unwrap();
+        assert!((aloof.alignment - social.alignment * 0.5).norm() < 1e-9);
+        assert!((aloof.cohesion - social.cohesion * 0.5).norm() < 1e-9);
+        assert_eq!(aloof.separation, social.separation);
+
+        let varied = FlockConfig { mass_variance: 0.2, speed_variance: 0.05, sociability_variance: 0.25, ..quiet_config() };
+        let mut rng = StdRng::seed_from_u64(3);
+        let birds: Vec<Bird> = (0..200).map(|_| Bird::with_rng(&varied, &mut rng)).collect();
+        assert!(birds.iter().all(|bird| (bird.mass - 1.0).abs() <= 0.2 && (bird.speed_factor - 1.0).abs() <= 0.05 && (bird.sociability - 1.0).abs() <= 0.25));
+        assert!(birds.iter().any(|bird| bird.mass != birds[0].mass));
+    }
+
+    #[test]
+    fn lone_bird_wanders_off_a_straight_line()
+    {
+        let start = Vector3::new(MAX_SPEED, 0.0, 0.0);
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), start)]);
+        flock.config.wander_weight = WANDER_WEIGHT;
+        for _ in 0..120 {
+            flock.update();
+        }
+
+        let bird = &flock.birds[0];
+        assert_ne!(bird.wander, Vector3::zeros());
+        assert!(bird.velocity.normalize().dot(&start.normalize()) < 0.999, "still heading {:?}", bird.velocity);
+        assert!(bird.position.y.abs() + bird.position.z.abs() > 1e-3);
+    }
+
+    #[test]
+    fn gravity_pulls_along_its_direction()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros())]);
+        flock.set_gravity(Vector3::new(0.0002, 0.0, 0.0));
+        flock.update();
+
+        assert!((flock.birds[0].velocity - Vector3::new(0.0002, 0.0, 0.0)).norm() < 1e-8);
+        assert_eq!(FlockConfig::default().gravity, Vector3::new(0.0, -GRAVITY, 0.0));
+    }
+
+    #[test]
+    fn slow_bird_near_the_floor_perches_then_takes_off()
+    {
+        let config = FlockConfig { boundary_size: 5.0, perch_probability: PERCH_PROBABILITY, perch_min_steps: 20, perch_max_steps: 20, gravity: Vector3::new(0.0, -GRAVITY, 0.0), ..quiet_config() };
+        let mut flock = Flock::with_seed(config, 1);
+        flock.spawn(bird_at(Vector3::new(0.3, -2.47, 0.0), Vector3::new(0.001, -0.002, 0.0)));
+
+        let mut landed = None;
+        for step in 0..5 {
+            flock.update();
+            if flock.birds[0].state == BirdState::Perched {
+                landed = Some(step);
+                break;
+            }
+        }
+        assert!(landed.is_some(), "still flying at {:?}", flock.birds[0].position);
+        let perch = flock.birds[0].position;
+        assert_eq!(perch.y, -2.5);
+
+        let mut took_off = None;
+        for step in 0..30 {
+            flock.update();
+            let bird = &flock.birds[0];
+            if bird.state == BirdState::Flying {
+                took_off = Some(step);
+                assert!(bird.velocity.y > 0.0);
+                break;
+            }
+            assert_eq!(bird.position, perch);
+            assert_eq!(bird.velocity, Vector3::zeros());
+        }
+        assert!(took_off.is_some_and(|step| step >= 15), "took off after {:?} steps", took_off);
+    }
+
+    #[test]
+    fn birds_head_for_the_roost_at_night_only()
+    {
+        let roost_after = |time: f32| {
+            let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros())]);
+            flock.config.day_length = 1.0;
+            flock.roosts.push(Vector3::new(2.0, 0.0, 0.0));
+            flock.time = time;
+            flock.update();
+            flock.birds[0].velocity
+        };
+
+        let midnight = 0.7 / REFERENCE_DT;
+        let noon = 0.2 / REFERENCE_DT;
+        assert!(roost_after(midnight).x > 0.0);
+        assert_eq!(roost_after(noon), Vector3::zeros());
+
+        let mut flock = quiet_flock(&[]);
+        flock.config.day_length = 1.0;
+        flock.time = noon;
+        assert!((flock.time_of_day().unwrap() - 0.5).abs() < 1e-5);
+        for _ in 0..60 {
+            flock.update();
+        }
+        assert!((flock.time_of_day().unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn flight_drains_energy_and_perching_restores_it()
+    {
+        let config = FlockConfig { energy_drain: 0.001, energy_recovery: 0.004, ..quiet_config() };
+        let drained = |speed: f32| {
+            let mut bird = bird_at(Vector3::zeros(), Vector3::new(speed, 0.0, 0.0));
+            bird.update(&config, 0.5, 1.0);
+            1.0 - bird.energy
+        };
+        assert!(drained(MAX_SPEED * 0.2) > 0.0);
+        assert!(drained(MAX_SPEED) > drained(MAX_SPEED * 0.2));
+        assert!(drained(MAX_SPEED) <= 0.001 + 1e-7);
+
+        let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        bird.energy = 0.5;
+        bird.state = BirdState::Perched;
+        bird.perch_timer = 10.0;
+        bird.update(&config, 0.5, 2.0);
+        assert!((bird.energy - 0.508).abs() < 1e-6);
+    }
+
+    #[test]
+    fn perch_lasts_as_long_at_any_timestep()
+    {
+        let config = FlockConfig { perch_probability: PERCH_PROBABILITY, perch_min_steps: 20, perch_max_steps: 20, ..quiet_config() };
+        let updates_to_take_off = |scale: f32| {
+            let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+            bird.perch(&config, 0.5);
+            (1..100).find(|_| {
+                bird.update(&config, 0.5, scale);
+                bird.state == BirdState::Flying
+            })
+        };
+
+        assert_eq!(updates_to_take_off(1.0), Some(21));
+        assert_eq!(updates_to_take_off(2.0), Some(11));
+        assert_eq!(updates_to_take_off(0.5), Some(41));
+    }
+
+    #[test]
+    fn only_tired_birds_wait_for_full_energy()
+    {
+        let config = FlockConfig { perch_probability: PERCH_PROBABILITY, perch_min_steps: 20, perch_max_steps: 20, energy_recovery: 0.01, ..quiet_config() };
+        let updates_to_take_off = |energy: f32| {
+            let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+            bird.energy = energy;
+            bird.perch(&config, 0.5);
+            (1..1000).find(|_| {
+                bird.update(&config, 0.5, 1.0);
+                bird.state == BirdState::Flying
+            }).map(|updates| (updates, bird.energy))
+        };
+
+        let (updates, energy) = updates_to_take_off(0.5).unwrap();
+        assert_eq!(updates, 21);
+        assert!(energy < 1.0);
+
+        let (updates, energy) = updates_to_take_off(0.1).unwrap();
+        assert!(updates > 80, "took off after {} updates", updates);
+        assert!(energy > 0.999);
+    }
+
+    #[test]
+    fn steering_forces_follow_the_registered_behaviours()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::new(MAX_SPEED, 0.0, 0.0));
+        let other = bird_at(Vector3::new(0.3, 0.2, 0.0), Vector3::new(0.0, MAX_SPEED, 0.0));
+        let mut flock = quiet_flock(&[bird, other]);
+        let forces = flock.steering_forces(0).unwrap();
+        assert_ne!(forces.alignment, Vector3::zeros());
+
+        flock.set_behaviour_weight("cohesion", 2.0).unwrap();
+        assert!(flock.remove_behaviour("alignment"));
+        let reweighted = flock.steering_forces(0).unwrap();
+        assert_eq!(reweighted.alignment, Vector3::zeros());
+        assert_eq!(reweighted.cohesion, forces.cohesion * 2.0);
+        assert_eq!(reweighted.separation, forces.separation);
+
+        flock.birds[0].leader = true;
+        let leading = flock.steering_forces(0).unwrap();
+        assert_eq!(leading.cohesion, Vector3::zeros());
+    }
+
+    #[test]
+    fn overlapping_birds_are_pushed_apart_and_reported()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros()), bird_at(Vector3::new(0.05, 0.0, 0.0), Vector3::zeros())]);
+        flock.config.bird_radius = 0.1;
+        let events = flock.subscribe();
+        flock.update();
+
+        let gap = (flock.birds[1].position - flock.birds[0].position).norm();
+        assert!(gap >= 0.2 - 1e-5, "still only {} apart", gap);
+        assert!(events.try_iter().any(|event| event == FlockEvent::BirdCollision { first: 0, second: 1 }));
+
+        flock.birds[1].position = flock.birds[0].position + Vector3::new(0.0, 0.15, 0.0);
+        assert_eq!(flock.resolve_collisions(), vec![(0, 1)]);
+        assert!((flock.birds[1].position - flock.birds[0].position).norm() >= 0.2 - 1e-5);
+    }
+
+    #[test]
+    fn observers_hear_each_event_once()
+    {
+        struct Counter(std::sync::atomic::AtomicUsize);
+
+        impl FlockObserver for Counter
+        {
+            fn on_event(&self, event: &FlockEvent)
+            {
+                if let FlockEvent::BirdCollision { .. } = event {
+                    self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                }
+            }
+        }
+
+        let count = |counter: &Counter| counter.0.load(std::sync::atomic::Ordering::SeqCst);
+        let counter = Arc::new(Counter(Default::default()));
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros()), bird_at(Vector3::new(0.05, 0.0, 0.0), Vector3::zeros())]);
+        flock.config.bird_radius = 0.1;
+        flock.add_observer(counter.clone());
+        flock.update();
+        assert_eq!(count(&counter), 1);
+
+        flock.update();
+        assert_eq!(count(&counter), 1);
+
+        flock.birds[1].position = flock.birds[0].position;
+        flock.clear_observers();
+        flock.update();
+        assert_eq!(count(&counter), 1);
+    }
+
+    #[test]
+    fn topological_neighbours_ignore_all_but_the_nearest_k()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::new(MAX_SPEED, 0.0, 0.0));
+        let near = bird_at(Vector3::new(0.3, 0.0, 0.0), Vector3::new(0.0, MAX_SPEED, 0.0));
+        let far = bird_at(Vector3::new(0.8, 0.1, 0.0), Vector3::new(0.0, 0.0, MAX_SPEED));
+
+        let alignment = |interaction: InteractionModel, birds: &[Bird]| {
+            let mut flock = quiet_flock(birds);
+            flock.config.interaction = interaction;
+            flock.steering_forces(0).unwrap().alignment
+        };
+
+        let metric = InteractionModel::Metric;
+        assert_ne!(alignment(metric, &[bird, near, far]), alignment(metric, &[bird, near]));
+        let nearest = InteractionModel::Topological { k: 1 };
+        assert_ne!(alignment(nearest, &[bird, near]), Vector3::zeros());
+        assert_eq!(alignment(nearest, &[bird, near, far]), alignment(nearest, &[bird, near]));
+    }
+
+    #[test]
+    fn followers_steer_toward_the_nearest_leader()
+    {
+        let mut near = bird_at(Vector3::new(0.0, 3.0, 0.0), Vector3::zeros());
+        near.leader = true;
+        let mut far = bird_at(Vector3::new(-8.0, 0.0, 0.0), Vector3::zeros());
+        far.leader = true;
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros()), near, far]);
+        flock.update();
+
+        let velocity = flock.birds[0].velocity;
+        assert!((velocity.normalize() - Vector3::y()).norm() < 1e-5, "followed {:?}", velocity);
+        assert_eq!(flock.birds[1].velocity, Vector3::zeros());
+    }
+
+    #[test]
+    fn hungry_bird_forages_toward_food_then_feeds()
+    {
+        let mut hungry = bird_at(Vector3::zeros(), Vector3::zeros());
+        hungry.energy = 0.5;
+        let mut flock = quiet_flock(&[hungry]);
+        flock.config.behaviour_states = true;
+        flock.food.push(FoodSource { position: Vector3::new(0.6, 0.0, 0.0), amount: FOOD_AMOUNT, feeders: 0 });
+
+        flock.update();
+        assert_eq!(flock.birds[0].behaviour, BehaviourState::Flocking);
+        assert!(flock.birds[0].velocity.x > 0.0, "not drawn to the food: {:?}", flock.birds[0].velocity);
+
+        let fed_at = (0..300).find(|_| {
+            flock.update();
+            flock.birds[0].behaviour == BehaviourState::Feeding
+        });
+        assert!(fed_at.is_some(), "never fed, ended at {:?}", flock.birds[0].position);
+        let energy = flock.birds[0].energy;
+        flock.update();
+        assert!(flock.birds[0].energy > energy);
+        assert_eq!(flock.food[0].feeders, 1);
+    }
+
+    #[test]
+    fn feeding_depletes_food_until_it_despawns()
+    {
+        let mut hungry = bird_at(Vector3::new(0.6, 0.0, 0.0), Vector3::zeros());
+        hungry.energy = 0.5;
+        let mut flock = quiet_flock(&[hungry]);
+        flock.config.behaviour_states = true;
+        flock.config.num_food_sources = 0;
+        flock.food.push(FoodSource { position: Vector3::new(0.6, 0.0, 0.0), amount: 0.02, feeders: 0 });
+
+        flock.update();
+        assert!((flock.food[0].amount - (0.02 - ENERGY_RECOVERY)).abs() < 1e-6, "left {}", flock.food[0].amount);
+        for _ in 0..10 {
+            flock.update();
+        }
+        assert!(flock.food.is_empty());
+        assert!(flock.birds[0].energy < 1.0);
+
+        flock.config.num_food_sources = 1;
+        flock.config.food_spawn_rate = 2.0;
+        flock.update();
+        assert_eq!(flock.food.len(), 1);
+        assert_eq!(flock.food[0].amount, flock.config.food_amount);
+    }
+
+    #[test]
+    fn bird_flees_a_predator_inside_the_fear_radius()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros())]);
+        let position = Vector3::new(0.6, 0.3, 0.0);
+        flock.predators.push(Predator { position, velocity: Vector3::zeros(), previous_position: position });
+        flock.update();
+
+        let velocity = flock.birds[0].velocity;
+        assert!(velocity.dot(&-position) > 0.0, "fled along {:?}", velocity);
+        assert!((velocity.normalize() + position.normalize()).norm() < 1e-3);
+    }
+
+    #[test]
+    fn perched_birds_are_avoided_but_not_followed()
+    {
+        let mut perched = bird_at(Vector3::new(0.3, 0.0, 0.0), Vector3::zeros());
+        perched.state = BirdState::Perched;
+        let flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::new(MAX_SPEED, 0.0, 0.0)), perched]);
+        let forces = flock.steering_forces(0).unwrap();
+
+        assert!(forces.separation.x < 0.0);
+        assert_eq!(forces.alignment, Vector3::zeros());
+        assert_eq!(forces.cohesion, Vector3::zeros());
+    }
+
+    #[test]
+    fn neighbour_indexes_agree_with_brute_force()
+    {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut random_point = || Vector3::new(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0));
+        let positions: Vec<Vector3<f32>> = (0..600).map(|_| random_point()).collect();
+        let queries: Vec<Vector3<f32>> = (0..50).map(|_| random_point()).collect();
+
+        let octree = Octree::build(positions.clone(), 5.0, OCTREE_CAPACITY);
+        let grid = SpatialGrid::build(positions.clone(), 1.0);
+        let indexes: [&dyn NeighbourIndex; 2] = [&octree, &grid];
+
+        let mut found = Vec::new();
+        for &point in queries.iter().chain(positions.iter().take(20)) {
+            for &radius in [0.3, 1.0, 2.5].iter() {
+                let expected: Vec<usize> = (0..positions.len()).filter(|&i| (point - positions[i]).norm() < radius).collect();
+                for index in indexes.iter() {
+                    index.query_radius_into(point, radius, &mut found);
+                    assert_eq!(found, expected, "query at {:?} radius {}", point, radius);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn step_reuses_its_buffers_after_warm_up()
+    {
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 50, seed: Some(8), ..FlockConfig::default() }, 8);
+        flock.update();
+        flock.update();
+
+        let buffers = |flock: &Flock| {
+            let mut pair = [flock.birds.as_ptr() as usize, flock.back.as_ptr() as usize];
+            pair.sort_unstable();
+            (pair, flock.positions.as_ptr(), flock.birds.capacity(), flock.back.capacity(), flock.positions.capacity())
+        };
+        let warm = buffers(&flock);
+        assert_ne!(warm.0[0], warm.0[1]);
+
+        for _ in 0..100 {
+            flock.update();
+            assert_eq!(buffers(&flock), warm);
+        }
+    }
+
+    #[test]
+    fn remove_swaps_in_the_last_bird_and_buffers_follow()
+    {
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 10, seed: Some(4), ..FlockConfig::default() }, 4);
+        flock.update();
+        let doomed = flock.birds[3];
+        let last = flock.birds[9];
+
+        assert_eq!(flock.remove(3), Ok(doomed));
+        assert_eq!(flock.birds.len(), 9);
+        assert_eq!(flock.config.num_birds, 9);
+        assert_eq!(flock.birds[3], last);
+        assert!(!flock.birds.contains(&doomed));
+
+        flock.update();
+        assert_eq!(flock.back.len(), flock.birds.len());
+        assert_eq!(flock.positions.len(), flock.birds.len());
+
+        let index = flock.spawn(doomed);
+        assert_eq!(index, 9);
+        flock.update();
+        assert_eq!((flock.back.len(), flock.positions.len(), flock.config.num_birds), (10, 10, 10));
+    }
+
+    #[test]
+    fn remove_out_of_range_is_an_error()
+    {
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 3, seed: Some(4), ..FlockConfig::default() }, 4);
+
+        assert!(flock.remove(3).is_err());
+        assert_eq!(flock.birds.len(), 3);
+
+        while !flock.birds.is_empty() {
+            flock.remove(0).unwrap();
+        }
+        assert!(flock.remove(0).is_err());
+        flock.update();
+        assert!(flock.back.is_empty() && flock.positions.is_empty());
+    }
+
+    struct Push(Vector3<f32>);
+
+    impl SteeringBehaviour for Push
+    {
+        fn name(&self) -> &str
+        {
+            "push"
+        }
+
+        fn force(&self, _bird: &Bird, _neighbours: &Neighbourhood, _ctx: &SteeringContext) -> Vector3<f32>
+        {
+            self.0
+        }
+    }
+
+    #[test]
+    fn behaviours_apply_their_weighted_force()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), Vector3::zeros())]);
+        flock.add_behaviour(Push(Vector3::new(0.0, 0.0, 0.0005)), 3.0);
+        assert_eq!(flock.behaviours().last().map(|entry| entry.behaviour.name()), Some("push"));
+
+        flock.update();
+        assert!((flock.birds[0].velocity - Vector3::new(0.0, 0.0, 0.0015)).norm() < 1e-7);
+        assert!(!flock.has_default_behaviours());
+    }
+
+    #[test]
+    fn disabled_behaviours_contribute_nothing()
+    {
+        let velocity = Vector3::new(0.01, 0.0, 0.0);
+        let mut flock = quiet_flock(&[bird_at(Vector3::zeros(), velocity)]);
+        flock.set_gravity(Vector3::new(0.0, -0.0005, 0.0));
+        flock.add_behaviour(Push(Vector3::new(0.0, 0.0, 0.0005)), 1.0);
+
+        flock.set_behaviour_weight("gravity", 0.0).unwrap();
+        assert!(flock.remove_behaviour("push"));
+        assert!(flock.set_behaviour_weight("push", 1.0).is_err());
+        for _ in 0..10 {
+            flock.update();
+        }
+        assert_eq!(flock.birds[0].velocity, velocity);
+
+        flock.reset_behaviours();
+        assert!(flock.has_default_behaviours());
+        flock.update();
+        assert!(flock.birds[0].velocity.y < 0.0);
+    }
+
+    #[test]
+    fn saved_flock_loads_back_equal()
+    {
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 30, seed: Some(7), ..FlockConfig::default() }, 7);
+        for _ in 0..20 {
+            flock.update();
+        }
+        let path = std::env::temp_dir().join(format!("flock-round-trip-{}.json", std::process::id()));
+        flock.save_to_path(&path).unwrap();
+        let mut loaded = Flock::load_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for bird in flock.birds.iter_mut() {
+            bird.previous_position = bird.position;
+        }
+        assert_eq!(loaded.config, flock.config);
+        assert_eq!(loaded.birds, flock.birds);
+
+        for _ in 0..20 {
+            flock.update();
+            loaded.update();
+        }
+        assert_eq!(loaded.birds, flock.birds);
+    }
+
+    #[test]
+    fn v_formation_scene_loads_and_round_trips()
+    {
+        let scene = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/v_formation.json");
+        let mut flock = Flock::load_from_path(&scene).unwrap();
+
+        assert_eq!(flock.birds.len(), 9);
+        assert_eq!(flock.config.max_speed, MAX_SPEED);
+        assert_eq!(flock.birds[0].position, Vector3::new(0.6, 0.0, 0.0));
+        assert_eq!(flock.birds[8].position, Vector3::new(-0.6, -0.8, 0.0));
+        assert!(flock.birds.iter().all(|bird| bird.velocity == Vector3::new(0.01, 0.0, 0.0) && bird.energy == 1.0));
+        assert!(flock.validate().is_ok());
+        assert_eq!(flock.clamp_to_boundary(), 0);
+
+        let path = std::env::temp_dir().join(format!("flock-scene-{}.json", std::process::id()));
+        flock.save_to_path(&path).unwrap();
+        let loaded = Flock::load_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.birds, flock.birds);
+        assert_eq!(loaded.config, flock.config);
+    }
+
+    #[test]
+    fn clamp_to_boundary_pulls_stray_birds_inside()
+    {
+        let mut flock = quiet_flock(&[bird_at(Vector3::new(80.0, 0.0, 0.0), Vector3::zeros()), bird_at(Vector3::zeros(), Vector3::zeros())]);
+
+        assert_eq!(flock.clamp_to_boundary(), 1);
+        assert!(flock.birds[0].position.x <= 50.0);
+        assert_eq!(flock.birds[1].position, Vector3::zeros());
+    }
+
+    #[test]
+    fn normalize_or_zero_leaves_tiny_vectors_at_zero()
+    {
+        assert_eq!(normalize_or_zero(Vector3::zeros()), Vector3::zeros());
+        assert_eq!(normalize_or_zero(Vector3::new(1e-8, 0.0, 0.0)), Vector3::zeros());
+        assert!((normalize_or_zero(Vector3::new(3.0, 4.0, 0.0)) - Vector3::new(0.6, 0.8, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn sign_checks_reject_nan()
+    {
+        assert!(positive(0.5) && !positive(0.0) && !positive(f32::NAN));
+        assert!(non_negative(0.0) && !non_negative(-1.0) && !non_negative(f64::NAN));
+    }
+
+    #[test]
+    fn perpendicular_is_a_unit_vector_at_right_angles()
+    {
+        for dir in [Vector3::x(), Vector3::y(), -Vector3::y(), Vector3::new(1.0, 2.0, -3.0).normalize()].iter() {
+            let side = perpendicular(*dir);
+            assert!((side.norm() - 1.0).abs() < 1e-6, "{:?} gave {:?}", dir, side);
+            assert!(side.dot(dir).abs() < 1e-6, "{:?} gave {:?}", dir, side);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use nalgebra::Vector3;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::flock::{normalize_or_zero, Bird, FlockConfig};
+
+pub const FOOD_RADIUS: f32 = 1.5;
+pub const FEED_RADIUS: f32 = 0.25;
+pub const FRENZY_GROWTH: f32 = 0.1;
+pub const MAX_FRENZY: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FoodSource
+{
+    pub position: Vector3<f32>,
+    pub amount: f32,
+    #[serde(default)]
+    pub feeders: u32,
+}
+
+impl FoodSource
+{
+    pub fn with_rng<R: Rng + ?Sized>(config: &FlockConfig, rng: &mut R) -> FoodSource
+    {
+        let bird = Bird::with_rng(config, rng);
+        FoodSource { position: bird.position, amount: config.food_amount, feeders: 0 }
+    }
+
+    pub fn in_reach(&self, bird: &Bird) -> bool
+    {
+        (self.position - bird.position).norm() < FEED_RADIUS
+    }
+
+    pub fn radius(&self) -> f32
+    {
+        FOOD_RADIUS * (1.0 + FRENZY_GROWTH * self.feeders as f32).min(MAX_FRENZY)
+    }
+
+    pub fn force(&self, bird: &Bird, weight: f32) -> Vector3<f32>
+    {
+        let offset = self.position - bird.position;
+        let dist = offset.norm();
+        let radius = self.radius();
+        if dist >= radius {
+            return Vector3::zeros();
+        }
+
+        let hunger = 1.0 - bird.energy;
+        normalize_or_zero(offset) * (weight * hunger * (1.0 - dist / radius))
+    }
+}
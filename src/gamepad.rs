@@ -0,0 +1,114 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use std::time::Duration;
+
+use crate::camera::Camera;
+
+const DEAD_ZONE: f32 = 0.15;
+const LOOK_RATE: f32 = 12.0;
+const ORBIT_RATE: f32 = 4.0;
+const TRIGGER_THRESHOLD: f32 = 0.1;
+const PULSE_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadAction
+{
+    TogglePause,
+    ToggleDebug,
+    ToggleFreeCamera,
+    ClearAttractors,
+}
+
+pub struct Gamepad
+{
+    gilrs: Gilrs,
+    active: Option<GamepadId>,
+    since_pulse: Duration,
+}
+
+fn dead_zone(value: f32) -> f32
+{
+    if value.abs() < DEAD_ZONE {
+        0.0
+    } else {
+        (value - DEAD_ZONE * value.signum()) / (1.0 - DEAD_ZONE)
+    }
+}
+
+impl Gamepad
+{
+    pub fn new() -> Result<Gamepad, String>
+    {
+        let gilrs = Gilrs::new().map_err(|err| format!("could not open gamepads: {}", err))?;
+        let active = gilrs.gamepads().next().map(|(id, _)| id);
+        Ok(Gamepad { gilrs, active, since_pulse: PULSE_INTERVAL })
+    }
+
+    pub fn poll(&mut self) -> Vec<PadAction>
+    {
+        let mut actions = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            self.active = Some(event.id);
+            let action = match event.event {
+                EventType::ButtonPressed(Button::South, _) => PadAction::TogglePause,
+                EventType::ButtonPressed(Button::East, _) => PadAction::ToggleDebug,
+                EventType::ButtonPressed(Button::North, _) => PadAction::ToggleFreeCamera,
+                EventType::ButtonPressed(Button::West, _) => PadAction::ClearAttractors,
+                EventType::Disconnected => {
+                    self.active = self.gilrs.gamepads().map(|(id, _)| id).find(|&id| id != event.id);
+                    continue;
+                },
+                _ => continue,
+            };
+            actions.push(action);
+        }
+        actions
+    }
+
+    fn axis(&self, axis: Axis) -> f32
+    {
+        self.active.and_then(|id| self.gilrs.connected_gamepad(id)).map_or(0.0, |pad| dead_zone(pad.value(axis)))
+    }
+
+    fn trigger(&self, button: Button) -> f32
+    {
+        self.active
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+            .and_then(|pad| pad.button_data(button).map(|data| data.value()))
+            .unwrap_or(0.0)
+    }
+
+    pub fn drive(&self, camera: &mut Camera)
+    {
+        let forward = self.axis(Axis::LeftStickY);
+        let right = self.axis(Axis::LeftStickX);
+        if forward != 0.0 || right != 0.0 {
+            let fast = self.active.and_then(|id| self.gilrs.connected_gamepad(id)).map_or(false, |pad| pad.is_pressed(Button::LeftThumb));
+            camera.travel(forward, right, fast);
+        }
+
+        let dx = self.axis(Axis::RightStickX);
+        let dy = -self.axis(Axis::RightStickY);
+        if dx != 0.0 || dy != 0.0 {
+            if camera.free {
+                camera.turn(dx * LOOK_RATE, dy * LOOK_RATE);
+            } else {
+                camera.orbit(dx * ORBIT_RATE, dy * ORBIT_RATE);
+            }
+        }
+    }
+
+    pub fn pulse(&mut self, elapsed: Duration) -> Option<f32>
+    {
+        self.since_pulse += elapsed;
+        let strength = self.trigger(Button::RightTrigger2) - self.trigger(Button::LeftTrigger2);
+        if strength.abs() < TRIGGER_THRESHOLD {
+            self.since_pulse = PULSE_INTERVAL;
+            return None;
+        }
+        if self.since_pulse < PULSE_INTERVAL {
+            return None;
+        }
+        self.since_pulse = Duration::from_secs(0);
+        Some(strength)
+    }
+}
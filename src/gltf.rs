@@ -0,0 +1,311 @@
+use nalgebra::{Rotation3, UnitQuaternion};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::draw;
+use crate::flock::Bird;
+
+pub const GLTF_RATE: f32 = 30.0;
+
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const JSON_CHUNK: u32 = 0x4E4F_534A;
+const BIN_CHUNK: u32 = 0x004E_4942;
+
+#[derive(Serialize)]
+struct Asset
+{
+    version: &'static str,
+    generator: &'static str,
+}
+
+#[derive(Serialize)]
+struct Scene
+{
+    nodes: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Node
+{
+    name: String,
+    mesh: usize,
+}
+
+#[derive(Serialize)]
+struct Attributes
+{
+    #[serde(rename = "POSITION")]
+    position: usize,
+    #[serde(rename = "NORMAL")]
+    normal: usize,
+}
+
+#[derive(Serialize)]
+struct Primitive
+{
+    attributes: Attributes,
+}
+
+#[derive(Serialize)]
+struct Mesh
+{
+    name: &'static str,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer
+{
+    byte_length: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView
+{
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor
+{
+    buffer_view: usize,
+    component_type: u32,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+struct Sampler
+{
+    input: usize,
+    output: usize,
+    interpolation: &'static str,
+}
+
+#[derive(Serialize)]
+struct Target
+{
+    node: usize,
+    path: &'static str,
+}
+
+#[derive(Serialize)]
+struct Channel
+{
+    sampler: usize,
+    target: Target,
+}
+
+#[derive(Serialize)]
+struct Animation
+{
+    name: &'static str,
+    samplers: Vec<Sampler>,
+    channels: Vec<Channel>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Document
+{
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    meshes: Vec<Mesh>,
+    buffers: Vec<Buffer>,
+    buffer_views: Vec<BufferView>,
+    accessors: Vec<Accessor>,
+    animations: Vec<Animation>,
+}
+
+struct Builder
+{
+    data: Vec<u8>,
+    views: Vec<BufferView>,
+    accessors: Vec<Accessor>,
+}
+
+impl Builder
+{
+    fn push(&mut self, values: &[f32], kind: &'static str, components: usize, target: Option<u32>, bounds: bool) -> usize
+    {
+        let byte_offset = self.data.len();
+        for value in values {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+        self.views.push(BufferView { buffer: 0, byte_offset, byte_length: values.len() * 4, target });
+
+        let (min, max) = if bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![f32::NEG_INFINITY; components];
+            for item in values.chunks(components) {
+                for (index, &value) in item.iter().enumerate() {
+                    min[index] = min[index].min(value);
+                    max[index] = max[index].max(value);
+                }
+            }
+            (Some(min), Some(max))
+        } else {
+            (None, None)
+        };
+        self.accessors.push(Accessor
+        {
+            buffer_view: self.views.len() - 1,
+            component_type: FLOAT,
+            count: values.len() / components,
+            kind,
+            min,
+            max,
+        });
+        self.accessors.len() - 1
+    }
+}
+
+fn quaternion(bird: &Bird) -> [f32; 4]
+{
+    let rotation = Rotation3::from_matrix_unchecked(draw::bird_orientation(bird.velocity, bird.steering));
+    let coords = UnitQuaternion::from_rotation_matrix(&rotation).coords;
+    [coords.x, coords.y, coords.z, coords.w]
+}
+
+pub struct GltfBaker
+{
+    out: BufWriter<File>,
+    interval: f32,
+    start: f32,
+    next_time: f32,
+    times: Vec<f32>,
+    translations: Vec<Vec<f32>>,
+    rotations: Vec<Vec<f32>>,
+}
+
+impl GltfBaker
+{
+    pub fn create<P: AsRef<Path>>(path: P, rate: f32) -> io::Result<GltfBaker>
+    {
+        let out = BufWriter::new(File::create(path)?);
+        Ok(GltfBaker { out, interval: 1.0 / rate.max(1e-3), start: 0.0, next_time: f32::NEG_INFINITY, times: Vec::new(), translations: Vec::new(), rotations: Vec::new() })
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.times.is_empty()
+    }
+
+    pub fn record(&mut self, time: f32, birds: &[Bird])
+    {
+        if time < self.next_time {
+            return;
+        }
+        if self.times.is_empty() {
+            self.start = time;
+            self.translations = vec![Vec::new(); birds.len()];
+            self.rotations = vec![Vec::new(); birds.len()];
+        }
+        let sample = self.times.len();
+        self.times.push(time - self.start);
+        self.next_time = time + self.interval;
+
+        for (index, (translations, rotations)) in self.translations.iter_mut().zip(self.rotations.iter_mut()).enumerate() {
+            match birds.get(index) {
+                Some(bird) => {
+                    translations.extend_from_slice(&<[f32; 3]>::from(bird.position));
+                    let mut rotation = quaternion(bird);
+                    if sample > 0 {
+                        let previous = &rotations[rotations.len() - 4..];
+                        if previous.iter().zip(rotation.iter()).map(|(a, b)| a * b).sum::<f32>() < 0.0 {
+                            rotation = [-rotation[0], -rotation[1], -rotation[2], -rotation[3]];
+                        }
+                    }
+                    rotations.extend_from_slice(&rotation);
+                },
+                None => {
+                    let last = translations.len() - 3;
+                    translations.extend_from_within(last..);
+                    let last = rotations.len() - 4;
+                    rotations.extend_from_within(last..);
+                },
+            }
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()>
+    {
+        let mut builder = Builder { data: Vec::new(), views: Vec::new(), accessors: Vec::new() };
+        let mesh = draw::bird_mesh();
+        let positions: Vec<f32> = mesh.iter().flat_map(|vertex| vertex.position.iter().copied()).collect();
+        let normals: Vec<f32> = mesh.iter().flat_map(|vertex| vertex.normal.iter().copied()).collect();
+        let position = builder.push(&positions, "VEC3", 3, Some(ARRAY_BUFFER), true);
+        let normal = builder.push(&normals, "VEC3", 3, Some(ARRAY_BUFFER), false);
+        let times = builder.push(&self.times, "SCALAR", 1, None, true);
+
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for (node, (translations, rotations)) in self.translations.iter().zip(self.rotations.iter()).enumerate() {
+            let translation = builder.push(translations, "VEC3", 3, None, false);
+            let rotation = builder.push(rotations, "VEC4", 4, None, false);
+            for (output, path) in [(translation, "translation"), (rotation, "rotation")].iter() {
+                channels.push(Channel { sampler: samplers.len(), target: Target { node, path } });
+                samplers.push(Sampler { input: times, output: *output, interpolation: "LINEAR" });
+            }
+        }
+
+        let count = self.translations.len();
+        let document = Document
+        {
+            asset: Asset { version: "2.0", generator: "bird_flock" },
+            scene: 0,
+            scenes: vec![Scene { nodes: (0..count).collect() }],
+            nodes: (0..count).map(|index| Node { name: format!("bird{}", index), mesh: 0 }).collect(),
+            meshes: vec![Mesh { name: "bird", primitives: vec![Primitive { attributes: Attributes { position, normal } }] }],
+            buffers: vec![Buffer { byte_length: builder.data.len() }],
+            buffer_views: builder.views,
+            accessors: builder.accessors,
+            animations: if count > 0 { vec![Animation { name: "flock", samplers, channels }] } else { Vec::new() },
+        };
+
+        let mut json = serde_json::to_vec(&document)?;
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let mut data = builder.data;
+        while !data.len().is_multiple_of(4) {
+            data.push(0);
+        }
+
+        let mut out = self.out;
+        let total = 12 + 8 + json.len() + 8 + data.len();
+        out.write_all(&GLB_MAGIC.to_le_bytes())?;
+        out.write_all(&2u32.to_le_bytes())?;
+        out.write_all(&(total as u32).to_le_bytes())?;
+        out.write_all(&(json.len() as u32).to_le_bytes())?;
+        out.write_all(&JSON_CHUNK.to_le_bytes())?;
+        out.write_all(&json)?;
+        out.write_all(&(data.len() as u32).to_le_bytes())?;
+        out.write_all(&BIN_CHUNK.to_le_bytes())?;
+        out.write_all(&data)?;
+        out.flush()
+    }
+}
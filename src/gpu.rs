@@ -0,0 +1,468 @@
+use glium::backend::Facade;
+use glium::program::ComputeShader;
+use glium::uniforms::UniformBuffer;
+use glium::{Frame, Program, Surface, VertexBuffer};
+
+use bird_flock::behaviour::BehaviourState;
+use bird_flock::draw::FrameUniforms;
+use bird_flock::flock::{Bird, BoundaryShape, Flock, FlockConfig, InteractionModel, BOUNDARY_FORCE, BOUNDARY_MARGIN, MAX_FORCE, REFERENCE_DT};
+use bird_flock::wind::WindMode;
+use nalgebra::Vector3;
+
+use crate::render;
+use crate::sky::Fog;
+
+pub const VALIDATION_STEPS: u32 = 10;
+pub const VALIDATION_TOLERANCE: f32 = 1e-3;
+const WORK_GROUP_SIZE: u32 = 64;
+
+const COMPUTE_SHADER_SRC: &str = r#"
+    #version 430
+
+    layout(local_size_x = 64) in;
+
+    layout(std140) buffer Source {
+        vec4 source[];
+    };
+
+    layout(std140) buffer Target {
+        vec4 target[];
+    };
+
+    uniform uint num_birds;
+    uniform float max_speed;
+    uniform float neighbour_radius;
+    uniform float separation_weight;
+    uniform float alignment_weight;
+    uniform float cohesion_weight;
+    uniform float half_size;
+    uniform float view_cos;
+    uniform vec3 gravity;
+    uniform float boundary_force;
+    uniform float boundary_margin;
+    uniform vec3 wind;
+    uniform float max_force;
+    uniform float scale;
+
+    vec3 safe_normalize(vec3 v) {
+        float len = length(v);
+        return len > 1e-6 ? v / len : vec3(0.0);
+    }
+
+    vec3 cap_magnitude(vec3 v, float max_length) {
+        float len = length(v);
+        return len > max_length ? v * (max_length / len) : v;
+    }
+
+    vec3 steer(vec3 desired, vec3 vel) {
+        vec3 direction = safe_normalize(desired);
+        if (direction == vec3(0.0)) {
+            return vec3(0.0);
+        }
+        return cap_magnitude(direction * max_speed - vel, max_force);
+    }
+
+    void main() {
+        uint i = gl_GlobalInvocationID.x;
+        if (i >= num_birds) {
+            return;
+        }
+
+        vec3 pos = source[2 * i].xyz;
+        vec3 vel = source[2 * i + 1].xyz;
+        vec3 heading = safe_normalize(vel);
+
+        vec3 separation = vec3(0.0);
+        vec3 alignment = vec3(0.0);
+        vec3 cohesion = vec3(0.0);
+        uint count = 0;
+
+        for (uint j = 0; j < num_birds; j++) {
+            vec3 other = source[2 * j].xyz;
+            vec3 to_other = safe_normalize(other - pos);
+            bool visible = view_cos <= -1.0 || heading == vec3(0.0) || to_other == vec3(0.0) || dot(heading, to_other) >= view_cos;
+
+            float dist = distance(pos, other);
+            if (j != i && dist < neighbour_radius && visible) {
+                if (dist > 0.0) {
+                    separation += (pos - other) / (dist * dist);
+                }
+                alignment += source[2 * j + 1].xyz;
+                cohesion += other;
+                count++;
+            }
+        }
+
+        vec3 acc = vec3(0.0);
+        if (count > 0) {
+            float inv_count = 1.0 / float(count);
+            acc += steer(separation, vel) * separation_weight;
+            acc += safe_normalize(steer(alignment * inv_count, vel)) * alignment_weight;
+            acc += safe_normalize(steer(cohesion * inv_count - pos, vel)) * cohesion_weight;
+        }
+
+        acc += gravity;
+        acc += wind;
+
+        for (int k = 0; k < 3; k++) {
+            float depth = abs(pos[k]) - (half_size - boundary_margin);
+            if (depth > 0.0) {
+                acc[k] -= sign(pos[k]) * boundary_force * min(depth / boundary_margin, 1.0);
+            }
+        }
+
+        vel = cap_magnitude(vel + acc * scale, max_speed);
+        pos += vel * scale;
+
+        for (int k = 0; k < 3; k++) {
+            if (abs(pos[k]) > half_size) {
+                vel[k] = -vel[k] * 0.8;
+                pos[k] = pos[k] > 0.0 ? half_size : -half_size;
+            }
+        }
+
+        target[2 * i] = vec4(pos, 1.0);
+        target[2 * i + 1] = vec4(vel, 0.0);
+    }
+"#;
+
+const BIRD_VERTEX_SHADER_SRC: &str = r#"
+    #version 430
+
+    in vec3 position;
+    in vec3 normal;
+
+    out vec3 v_normal;
+    out float v_distance;
+
+    layout(std140) buffer Birds {
+        vec4 birds[];
+    };
+
+    uniform mat4 view;
+    uniform mat4 projection;
+
+    void main() {
+        vec3 offset = birds[2 * gl_InstanceID].xyz;
+        vec3 vel = birds[2 * gl_InstanceID + 1].xyz;
+        vec3 forward = length(vel) > 1e-6 ? normalize(vel) : vec3(1.0, 0.0, 0.0);
+        vec3 reference = abs(forward.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+        vec3 right = normalize(cross(forward, reference));
+        mat3 orientation = mat3(right, forward, cross(right, forward));
+
+        v_normal = orientation * normal;
+        vec4 eye = view * vec4(orientation * position + offset, 1.0);
+        v_distance = length(eye.xyz);
+        gl_Position = projection * eye;
+    }
+"#;
+
+const BIRD_FRAGMENT_SHADER_SRC: &str = r#"
+    #version 430
+
+    in vec3 v_normal;
+    in float v_distance;
+    out vec4 color;
+
+    uniform vec3 bird_color;
+    uniform vec3 light_dir;
+    uniform vec3 light_color;
+    uniform float ambient;
+    uniform vec3 fog_color;
+    uniform float fog_density;
+
+    void main() {
+        float diffuse = max(dot(normalize(v_normal), light_dir), 0.0);
+        vec3 lit = bird_color * (ambient + (1.0 - ambient) * diffuse * light_color);
+        float fog = exp(-pow(fog_density * v_distance, 2.0));
+        color = vec4(mix(fog_color, lit, fog), 1.0);
+    }
+"#;
+
+pub struct GpuFlock
+{
+    shader: ComputeShader,
+    program: Program,
+    front: UniformBuffer<[[f32; 4]]>,
+    back: UniformBuffer<[[f32; 4]]>,
+    num_birds: usize,
+}
+
+impl GpuFlock
+{
+    pub fn is_supported<F: ?Sized + Facade>(facade: &F) -> bool
+    {
+        ComputeShader::is_supported(&**facade.get_context())
+    }
+
+    pub fn new<F: ?Sized + Facade>(facade: &F, flock: &Flock) -> Result<GpuFlock, String>
+    {
+        let shader = ComputeShader::from_source(facade, COMPUTE_SHADER_SRC).map_err(|err| err.to_string())?;
+        let program = render::compile_program(facade, "GPU bird", BIRD_VERTEX_SHADER_SRC, BIRD_FRAGMENT_SHADER_SRC)?;
+        let bytes = flock.birds.len().max(1) * 2 * std::mem::size_of::<[f32; 4]>();
+        let front = UniformBuffer::empty_unsized(facade, bytes).map_err(|err| format!("{:?}", err))?;
+        let back = UniformBuffer::empty_unsized(facade, bytes).map_err(|err| format!("{:?}", err))?;
+
+        let mut gpu = GpuFlock { shader, program, front, back, num_birds: 0 };
+        gpu.upload(facade, flock)?;
+        Ok(gpu)
+    }
+
+    pub fn upload<F: ?Sized + Facade>(&mut self, facade: &F, flock: &Flock) -> Result<(), String>
+    {
+        if flock.birds.len() != self.num_birds {
+            let bytes = flock.birds.len().max(1) * 2 * std::mem::size_of::<[f32; 4]>();
+            self.front = UniformBuffer::empty_unsized(facade, bytes).map_err(|err| format!("{:?}", err))?;
+            self.back = UniformBuffer::empty_unsized(facade, bytes).map_err(|err| format!("{:?}", err))?;
+            self.num_birds = flock.birds.len();
+        }
+
+        let mut mapping = self.front.map();
+        for (index, bird) in flock.birds.iter().enumerate() {
+            mapping[2 * index] = [bird.position.x, bird.position.y, bird.position.z, 1.0];
+            mapping[2 * index + 1] = [bird.velocity.x, bird.velocity.y, bird.velocity.z, 0.0];
+        }
+
+        Ok(())
+    }
+
+    pub fn draw<T: Copy + glium::Vertex>(&self, target: &mut Frame, shape: &VertexBuffer<T>, frame: &FrameUniforms, colour: [f32; 3], fog: &Fog, params: &glium::DrawParameters) -> Result<(), glium::DrawError>
+    {
+        let uniforms = uniform! {
+            Birds: &*self.front,
+            view: frame.view,
+            projection: frame.projection,
+            bird_color: colour,
+            light_dir: frame.light_dir,
+            light_color: frame.light_colour,
+            ambient: frame.ambient,
+            fog_color: fog.colour,
+            fog_density: fog.density,
+        };
+
+        if self.num_birds == 0 {
+            return Ok(());
+        }
+
+        let instances = glium::vertex::EmptyInstanceAttributes { len: self.num_birds };
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+        target.draw((shape, instances), indices, &self.program, &uniforms, params)
+    }
+
+    pub fn step(&mut self, config: &FlockConfig, dt: f32)
+    {
+        if self.num_birds == 0 {
+            return;
+        }
+
+        let uniforms = uniform! {
+            Source: &*self.front,
+            Target: &*self.back,
+            num_birds: self.num_birds as u32,
+            max_speed: config.max_speed,
+            neighbour_radius: config.neighbour_radius,
+            separation_weight: config.separation_weight,
+            alignment_weight: config.alignment_weight,
+            cohesion_weight: config.cohesion_weight,
+            half_size: config.boundary_size / 2.0,
+            view_cos: config.view_cos(),
+            gravity: <[f32; 3]>::from(config.gravity),
+            boundary_force: BOUNDARY_FORCE,
+            boundary_margin: BOUNDARY_MARGIN,
+            wind: <[f32; 3]>::from(config.wind),
+            max_force: MAX_FORCE,
+            scale: dt / REFERENCE_DT,
+        };
+
+        let groups = (self.num_birds as u32).div_ceil(WORK_GROUP_SIZE);
+        self.shader.execute(uniforms, groups.max(1), 1, 1);
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    pub fn max_position_error(&mut self, birds: &[Bird]) -> f32
+    {
+        let mapping = self.front.map_read();
+        birds.iter().enumerate().map(|(index, bird)| {
+            let [x, y, z, _] = mapping[2 * index];
+            (Vector3::new(x, y, z) - bird.position).norm()
+        }).fold(0.0, f32::max)
+    }
+}
+
+pub fn unsupported_features(flock: &Flock) -> Vec<&'static str>
+{
+    let mut ignored = Vec::new();
+    if !flock.species.is_empty() {
+        ignored.push("species");
+    }
+    if !flock.obstacles.is_empty() {
+        ignored.push("obstacles");
+    }
+    if !flock.attractors.is_empty() || flock.attractor.is_some() {
+        ignored.push("attractors");
+    }
+    if flock.config.perch_probability > 0.0 {
+        ignored.push("perching");
+    }
+    if flock.config.energy_drain > 0.0 {
+        ignored.push("energy");
+    }
+    if flock.config.day_length > 0.0 && !flock.roosts.is_empty() {
+        ignored.push("roosting");
+    }
+    if flock.config.bird_radius > 0.0 {
+        ignored.push("collisions");
+    }
+    if flock.config.wander_weight > 0.0 {
+        ignored.push("wander");
+    }
+    if flock.birds.iter().any(|bird| bird.mass != 1.0 || bird.speed_factor != 1.0 || bird.sociability != 1.0) {
+        ignored.push("per-bird traits");
+    }
+    if flock.config.behaviour_states {
+        ignored.push("behaviour states");
+    }
+    if flock.config.plane.is_some() {
+        ignored.push("2D mode");
+    }
+    if flock.config.boundary_shape != BoundaryShape::Cube {
+        ignored.push("non-cube boundaries");
+    }
+    if flock.config.wind_mode != WindMode::Constant {
+        ignored.push("turbulent wind");
+    }
+    if flock.config.interaction != InteractionModel::Metric {
+        ignored.push("topological interaction");
+    }
+    if flock.terrain.is_some() {
+        ignored.push("terrain");
+    }
+    if flock.birds.iter().any(|bird| bird.leader) {
+        ignored.push("leaders");
+    }
+    if !flock.predators.is_empty() {
+        ignored.push("predators");
+    }
+    if !flock.food.is_empty() {
+        ignored.push("food");
+    }
+    if !flock.has_default_behaviours() {
+        ignored.push("custom steering behaviours");
+    }
+    ignored
+}
+
+pub fn cpu_reference(flock: &Flock) -> Flock
+{
+    let mut reference = flock.clone();
+    reference.species.clear();
+    reference.obstacles.clear();
+    reference.attractors.clear();
+    reference.attractor = None;
+    reference.config.perch_probability = 0.0;
+    reference.config.energy_drain = 0.0;
+    reference.roosts.clear();
+    reference.config.bird_radius = 0.0;
+    reference.config.wander_weight = 0.0;
+    reference.config.mass_variance = 0.0;
+    reference.config.speed_variance = 0.0;
+    reference.config.sociability_variance = 0.0;
+    reference.config.behaviour_states = false;
+    reference.config.plane = None;
+    reference.config.boundary_shape = BoundaryShape::Cube;
+    reference.config.wind_mode = WindMode::Constant;
+    reference.config.interaction = InteractionModel::Metric;
+    reference.terrain = None;
+    reference.config.num_leaders = 0;
+    for bird in reference.birds.iter_mut() {
+        bird.leader = false;
+        bird.mass = 1.0;
+        bird.speed_factor = 1.0;
+        bird.sociability = 1.0;
+        bird.behaviour = BehaviourState::Flocking;
+    }
+    reference.config.num_predators = 0;
+    reference.predators.clear();
+    reference.config.num_food_sources = 0;
+    reference.food.clear();
+    reference.reset_behaviours();
+    reference
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn plain_config() -> FlockConfig
+    {
+        FlockConfig
+        {
+            num_birds: 50,
+            wander_weight: 0.0,
+            mass_variance: 0.0,
+            speed_variance: 0.0,
+            sociability_variance: 0.0,
+            behaviour_states: false,
+            perch_probability: 0.0,
+            energy_drain: 0.0,
+            bird_radius: 0.0,
+            num_leaders: 0,
+            num_predators: 0,
+            num_food_sources: 0,
+            seed: Some(3),
+            ..FlockConfig::default()
+        }
+    }
+
+    #[test]
+    fn plain_flock_needs_nothing_disabled()
+    {
+        let flock = Flock::with_seed(plain_config(), 3);
+        assert_eq!(unsupported_features(&flock), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn unsupported_features_names_what_the_shader_ignores()
+    {
+        let config = FlockConfig
+        {
+            boundary_shape: BoundaryShape::Sphere { radius: 5.0 },
+            wind_mode: WindMode::Turbulent,
+            wander_weight: 0.5,
+            ..plain_config()
+        };
+        let mut flock = Flock::with_seed(config, 3);
+        flock.add_obstacle(Vector3::zeros(), 1.0);
+
+        let ignored = unsupported_features(&flock);
+        for feature in ["obstacles", "wander", "non-cube boundaries", "turbulent wind"].iter() {
+            assert!(ignored.contains(feature), "{} missing from {:?}", feature, ignored);
+        }
+        assert!(!ignored.contains(&"species"));
+    }
+
+    #[test]
+    fn cpu_reference_keeps_the_birds_and_drops_the_rest()
+    {
+        let config = FlockConfig
+        {
+            boundary_shape: BoundaryShape::Sphere { radius: 5.0 },
+            wind_mode: WindMode::Turbulent,
+            wander_weight: 0.5,
+            mass_variance: 0.3,
+            num_leaders: 2,
+            ..plain_config()
+        };
+        let mut flock = Flock::with_seed(config, 3);
+        flock.add_obstacle(Vector3::zeros(), 1.0);
+        assert!(!unsupported_features(&flock).is_empty());
+
+        let reference = cpu_reference(&flock);
+        assert_eq!(unsupported_features(&reference), Vec::<&str>::new());
+        assert_eq!(reference.birds.len(), flock.birds.len());
+        for (kept, original) in reference.birds.iter().zip(flock.birds.iter()) {
+            assert_eq!((kept.position, kept.velocity), (original.position, original.velocity));
+        }
+    }
+}
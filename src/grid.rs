@@ -0,0 +1,107 @@
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct SpatialGrid
+{
+    cell_size: f32,
+    flat_axis: Option<usize>,
+    cells: HashMap<[i32; 3], Vec<usize>>,
+    positions: Vec<Vector3<f32>>,
+}
+
+impl SpatialGrid
+{
+    pub fn build(positions: Vec<Vector3<f32>>, cell_size: f32) -> SpatialGrid
+    {
+        SpatialGrid::with_axis(positions, cell_size, None)
+    }
+
+    pub fn build_planar(positions: Vec<Vector3<f32>>, cell_size: f32, flat_axis: usize) -> SpatialGrid
+    {
+        SpatialGrid::with_axis(positions, cell_size, Some(flat_axis))
+    }
+
+    fn with_axis(positions: Vec<Vector3<f32>>, cell_size: f32, flat_axis: Option<usize>) -> SpatialGrid
+    {
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(positions, cell_size, flat_axis);
+        grid
+    }
+
+    /// Re-bins `positions` in place, reusing the cell vectors of the last build.
+    pub fn rebuild<I: IntoIterator<Item = Vector3<f32>>>(&mut self, positions: I, cell_size: f32, flat_axis: Option<usize>)
+    {
+        self.cell_size = cell_size.max(1e-3);
+        self.flat_axis = flat_axis;
+        self.cells.retain(|_, indices| !indices.is_empty());
+        for indices in self.cells.values_mut() {
+            indices.clear();
+        }
+        self.positions.clear();
+        self.positions.extend(positions);
+
+        for index in 0..self.positions.len() {
+            let key = self.cell(self.positions[index]);
+            self.cells.entry(key).or_default().push(index);
+        }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.positions.is_empty()
+    }
+
+    fn cell(&self, point: Vector3<f32>) -> [i32; 3]
+    {
+        let mut cell = [
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+            (point.z / self.cell_size).floor() as i32,
+        ];
+        if let Some(axis) = self.flat_axis {
+            cell[axis] = 0;
+        }
+        cell
+    }
+
+    pub fn query_radius(&self, point: Vector3<f32>, radius: f32) -> Vec<usize>
+    {
+        let mut found = Vec::new();
+        self.query_radius_into(point, radius, &mut found);
+        found
+    }
+
+    pub fn query_radius_into(&self, point: Vector3<f32>, radius: f32, found: &mut Vec<usize>)
+    {
+        found.clear();
+        let center = self.cell(point);
+        let reach = (radius / self.cell_size).ceil() as i32;
+        let mut reaches = [reach; 3];
+        if let Some(axis) = self.flat_axis {
+            reaches[axis] = 0;
+        }
+
+        for dx in -reaches[0]..=reaches[0] {
+            for dy in -reaches[1]..=reaches[1] {
+                for dz in -reaches[2]..=reaches[2] {
+                    let key = [center[0] + dx, center[1] + dy, center[2] + dz];
+                    if let Some(indices) = self.cells.get(&key) {
+                        for &index in indices.iter() {
+                            if (point - self.positions[index]).norm() < radius {
+                                found.push(index);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        found.sort_unstable();
+    }
+}
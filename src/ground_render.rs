@@ -0,0 +1,104 @@
+use glium::backend::Facade;
+use glium::{Frame, IndexBuffer, Program, Surface, VertexBuffer};
+use std::path::Path;
+
+use bird_flock::terrain::Terrain;
+
+use crate::light::Light;
+use crate::mesh::LitVertex;
+use crate::render;
+use crate::shaders::ShaderSource;
+use crate::sky::Fog;
+
+const MAX_GROUND_CELLS: usize = 256;
+
+pub fn load_heightmap(path: &Path, size: f32, base: f32, relief: f32) -> Result<Terrain, String>
+{
+    let image = image::open(path).map_err(|err| format!("could not load heightmap {}: {}", path.display(), err))?.into_luma8();
+    let (width, height) = image.dimensions();
+    let resolution = width.min(height) as usize;
+    if resolution < 2 {
+        return Err(format!("heightmap {} must be at least 2x2 pixels", path.display()));
+    }
+
+    let mut heights = Vec::with_capacity(resolution * resolution);
+    for row in 0..resolution {
+        for column in 0..resolution {
+            heights.push(image.get_pixel(column as u32, row as u32)[0] as f32 / 255.0);
+        }
+    }
+    Terrain::from_heights(size, base, relief, resolution, heights)
+}
+
+pub struct GroundRenderer
+{
+    vertex_buffer: VertexBuffer<LitVertex>,
+    index_buffer: IndexBuffer<u32>,
+    program: Program,
+    base: f32,
+    relief: f32,
+}
+
+impl GroundRenderer
+{
+    pub fn new<F: ?Sized + Facade>(facade: &F, terrain: &Terrain, shaders: &ShaderSource) -> Result<GroundRenderer, String>
+    {
+        let program = render::compile_program(facade, "ground", &shaders.vertex, &shaders.fragment)?;
+
+        let cells = (terrain.resolution - 1).min(MAX_GROUND_CELLS);
+        let step = terrain.size / cells as f32;
+        let mut vertices = Vec::with_capacity((cells + 1) * (cells + 1));
+        for row in 0..=cells {
+            for column in 0..=cells {
+                let x = column as f32 * step - terrain.size / 2.0;
+                let z = row as f32 * step - terrain.size / 2.0;
+                vertices.push(LitVertex { position: [x, terrain.height_at(x, z), z], normal: terrain.normal_at(x, z).into() });
+            }
+        }
+
+        let mut indices = Vec::with_capacity(cells * cells * 6);
+        let stride = (cells + 1) as u32;
+        for row in 0..cells as u32 {
+            for column in 0..cells as u32 {
+                let corner = row * stride + column;
+                indices.extend_from_slice(&[corner, corner + stride, corner + 1, corner + 1, corner + stride, corner + stride + 1]);
+            }
+        }
+
+        let vertex_buffer = VertexBuffer::new(facade, &vertices).map_err(|err| err.to_string())?;
+        let index_buffer = IndexBuffer::new(facade, glium::index::PrimitiveType::TrianglesList, &indices).map_err(|err| err.to_string())?;
+
+        Ok(GroundRenderer
+        {
+            vertex_buffer,
+            index_buffer,
+            program,
+            base: terrain.base,
+            relief: terrain.relief,
+        })
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn reload<F: ?Sized + Facade>(&mut self, facade: &F, shaders: &ShaderSource) -> Result<(), String>
+    {
+        self.program = render::compile_program(facade, "ground", &shaders.vertex, &shaders.fragment)?;
+        Ok(())
+    }
+
+    pub fn draw(&self, target: &mut Frame, view: [[f32; 4]; 4], projection: [[f32; 4]; 4], light: &Light, fog: &Fog, params: &glium::DrawParameters) -> Result<(), String>
+    {
+        let uniforms = uniform! {
+            view: view,
+            projection: projection,
+            light_dir: light.direction,
+            light_color: light.colour,
+            ambient: light.ambient,
+            base: self.base,
+            relief: self.relief,
+            fog_color: fog.colour,
+            fog_density: fog.density,
+        };
+
+        target.draw(&self.vertex_buffer, &self.index_buffer, &self.program, &uniforms, params).map_err(|err| err.to_string())
+    }
+}
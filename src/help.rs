@@ -0,0 +1,73 @@
+use egui_glium::egui_winit::egui;
+
+const KEYS: &[(&str, &str)] = &[
+    ("H", "show or hide this list"),
+    ("F1 / F2", "HUD / tuning panel"),
+    ("F3 / Ctrl+D", "debug overlay"),
+    ("Tab", "free-fly camera (WASD, Shift to speed up)"),
+    ("WASD", "move the camera target, or fly while free"),
+    ("K", "follow a bird"),
+    ("[ / ]", "previous / next bird while following"),
+    ("+ / -", "time scale (replay speed in a replay)"),
+    ("Space / Period", "pause / step one tick while paused"),
+    ("1 - 9", "switch preset"),
+    ("Ctrl+1 - Ctrl+6", "select separation, alignment, cohesion, radius, max speed or view angle"),
+    ("Ctrl+= / Ctrl+-", "scale the selected parameter by 10%"),
+    ("Ctrl+0", "reset the selected parameter"),
+    ("Shift+1 - Shift+6", "nudge the wind along -/+ x, y and z"),
+    ("Shift+0", "calm the wind"),
+    ("Arrows", "turn and tilt the wind"),
+    ("Shift+= / Shift+-", "wind strength"),
+    ("G", "constant or turbulent wind"),
+    ("N / M", "add or remove a bird (Shift: 100)"),
+    ("R", "reset the flock"),
+    ("C / T", "colour mode / trails"),
+    ("B / O", "show the boundary / change its shape"),
+    ("V", "stereo"),
+    ("Escape", "clear attractors"),
+    ("F5 / F9", "save / load a snapshot"),
+    ("F12 / Ctrl+S", "screenshot"),
+    ("F10", "record frames"),
+    ("F11", "fullscreen"),
+    ("Left / Right, R", "in a replay: seek 60 frames (Shift: 600), rewind"),
+];
+
+const MOUSE: &[(&str, &str)] = &[
+    ("Drag / wheel", "orbit / zoom"),
+    ("Middle-drag", "pan (Shift: herd the flock)"),
+    ("Click / right-click", "repel / attract pulse (Ctrl: lasting)"),
+    ("Shift+click", "add a waypoint (right-click clears them)"),
+    ("Alt+click", "inspect a bird"),
+];
+
+const NOTES: &[&str] = &[
+    "Tuning keys take Ctrl because the plain digits switch presets and plain +/- set the time scale.",
+    "Wind strength takes Shift for the same reason.",
+    "D and S are WASD movement keys, so the debug overlay and screenshots take Ctrl.",
+];
+
+fn bindings(ui: &mut egui::Ui, id: &str, rows: &[(&str, &str)])
+{
+    egui::Grid::new(id).num_columns(2).striped(true).show(ui, |ui| {
+        for (keys, action) in rows.iter() {
+            ui.strong(*keys);
+            ui.label(*action);
+            ui.end_row();
+        }
+    });
+}
+
+pub fn help_panel(ctx: &egui::Context) -> bool
+{
+    let mut open = true;
+    egui::Window::new("Keys").open(&mut open).default_width(420.0).show(ctx, |ui| {
+        bindings(ui, "keys", KEYS);
+        ui.separator();
+        bindings(ui, "mouse", MOUSE);
+        ui.separator();
+        for note in NOTES.iter() {
+            ui.label(*note);
+        }
+    });
+    open
+}
@@ -0,0 +1,340 @@
+use glium::backend::Facade;
+use glium::texture::{RawImage2d, Texture2d};
+use glium::uniforms::MagnifySamplerFilter;
+use glium::{Frame, Program, Surface, VertexBuffer};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use bird_flock::daynight;
+use bird_flock::metrics::FlockMetrics;
+use bird_flock::modulation::BandEnergies;
+
+use crate::render;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+const PIXEL_SCALE: f32 = 2.0;
+const MARGIN: f32 = 8.0;
+const MAX_CHARS: usize = 256;
+const FPS_WINDOW: usize = 60;
+const RELOAD_ERROR_CHARS: usize = 48;
+pub const METRICS_INTERVAL: u32 = 30;
+
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+];
+
+#[derive(Copy, Clone)]
+struct HudVertex
+{
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+
+implement_vertex!(HudVertex, position, tex_coords);
+
+pub struct Stats
+{
+    pub fps: f32,
+    pub steps_per_second: f32,
+    pub step_ms: f32,
+    pub bird_count: usize,
+    pub avg_speed: f32,
+    pub max_speed: f32,
+    pub wind: [f32; 3],
+    pub param_name: &'static str,
+    pub param_value: f32,
+    pub time_scale: f32,
+    pub paused: bool,
+    pub metrics: Option<FlockMetrics>,
+    pub replay: Option<(usize, usize)>,
+    pub time_of_day: Option<f32>,
+    pub audio: Option<BandEnergies>,
+    pub reload_error: Option<String>,
+}
+
+pub struct FrameTimer
+{
+    last: Instant,
+    frames: VecDeque<(Duration, u32)>,
+}
+
+impl FrameTimer
+{
+    pub fn new() -> FrameTimer
+    {
+        FrameTimer
+        {
+            last: Instant::now(),
+            frames: VecDeque::with_capacity(FPS_WINDOW),
+        }
+    }
+
+    pub fn tick(&mut self, steps: u32)
+    {
+        let now = Instant::now();
+        if self.frames.len() == FPS_WINDOW {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((now - self.last, steps));
+        self.last = now;
+    }
+
+    pub fn fps(&self) -> f32
+    {
+        self.rate(self.frames.len() as f32)
+    }
+
+    pub fn steps_per_second(&self) -> f32
+    {
+        self.rate(self.frames.iter().map(|&(_, steps)| steps as f32).sum())
+    }
+
+    fn rate(&self, count: f32) -> f32
+    {
+        let total: f32 = self.frames.iter().map(|(d, _)| d.as_secs_f32()).sum();
+        if total > 0.0 {
+            count / total
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct Hud
+{
+    pub visible: bool,
+    pixel_scale: f32,
+    atlas: Texture2d,
+    atlas_width: usize,
+    vertex_buffer: VertexBuffer<HudVertex>,
+    program: Program,
+}
+
+impl Hud
+{
+    pub fn new<F: ?Sized + Facade>(facade: &F) -> Result<Hud, String>
+    {
+        let atlas_width = GLYPHS.len() * CELL_WIDTH;
+        let mut pixels = vec![0u8; atlas_width * CELL_HEIGHT * 4];
+
+        for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        let offset = (y * atlas_width + index * CELL_WIDTH + x) * 4;
+                        pixels[offset..offset + 4].copy_from_slice(&[255, 255, 255, 255]);
+                    }
+                }
+            }
+        }
+
+        let image = RawImage2d::from_raw_rgba_reversed(&pixels, (atlas_width as u32, CELL_HEIGHT as u32));
+        let atlas = Texture2d::new(facade, image).map_err(|err| format!("could not create the HUD font atlas: {}", err))?;
+        let vertex_buffer = VertexBuffer::empty_dynamic(facade, MAX_CHARS * 6).map_err(|err| format!("could not create the HUD vertex buffer: {}", err))?;
+
+        let vertex_shader_src = r#"
+            #version 140
+
+            in vec2 position;
+            in vec2 tex_coords;
+
+            out vec2 v_tex_coords;
+
+            void main() {
+                v_tex_coords = tex_coords;
+                gl_Position = vec4(position, 0.0, 1.0);
+            }
+        "#;
+
+        let fragment_shader_src = r#"
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D atlas;
+
+            void main() {
+                color = vec4(1.0, 1.0, 1.0, texture(atlas, v_tex_coords).a);
+            }
+        "#;
+
+        let program = render::compile_program(facade, "HUD", vertex_shader_src, fragment_shader_src)?;
+
+        Ok(Hud
+        {
+            visible: true,
+            pixel_scale: PIXEL_SCALE,
+            atlas,
+            atlas_width,
+            vertex_buffer,
+            program,
+        })
+    }
+
+    pub fn toggle(&mut self)
+    {
+        self.visible = !self.visible;
+    }
+
+    pub fn set_scale(&mut self, scale_factor: f64)
+    {
+        self.pixel_scale = (PIXEL_SCALE * scale_factor as f32).round().max(1.0);
+    }
+
+    pub fn draw(&mut self, target: &mut Frame, stats: &Stats) -> Result<(), glium::DrawError>
+    {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let mut lines = vec![
+            format!("FPS {:.1}", stats.fps),
+            format!("TICKS {:.1}/S STEP {:.2} MS", stats.steps_per_second, stats.step_ms),
+            format!("BIRDS {}", stats.bird_count),
+            format!("SPEED AVG {:.4} MAX {:.4}", stats.avg_speed, stats.max_speed),
+            format!("WIND {:.4} {:.4} {:.4}", stats.wind[0], stats.wind[1], stats.wind[2]),
+            format!("TUNE {} {:.4}", stats.param_name, stats.param_value),
+            format!("TIME X{:.3}{}", stats.time_scale, if stats.paused { " PAUSED" } else { "" }),
+        ];
+        if let Some(time_of_day) = stats.time_of_day {
+            let (hours, minutes) = daynight::clock(time_of_day);
+            lines.push(format!("DAY {:02}:{:02}", hours, minutes));
+        }
+        if let Some(audio) = &stats.audio {
+            lines.push(format!("AUDIO B {:.2} M {:.2} T {:.2}", audio.bass, audio.mid, audio.treble));
+        }
+        if let Some(error) = &stats.reload_error {
+            lines.push(format!("RELOAD ERROR {}", error.chars().take(RELOAD_ERROR_CHARS).collect::<String>()));
+        }
+        if let Some((frame, frames)) = stats.replay {
+            lines.push(format!("REPLAY {}/{}", frame + 1, frames));
+        }
+        if let Some(metrics) = &stats.metrics {
+            lines.push(format!("ORDER {:.3} NND {:.3}", metrics.order, metrics.average_nearest_distance));
+            lines.push(format!("DISP {:.3} GROUPS {}", metrics.dispersion, metrics.groups));
+        }
+
+        let (width, height) = target.get_dimensions();
+        let vertices = self.layout(&lines, width as f32, height as f32);
+        let slice = match self.vertex_buffer.slice(0..vertices.len()) {
+            Some(slice) if !vertices.is_empty() => slice,
+            _ => return Ok(()),
+        };
+        slice.write(&vertices);
+
+        let uniforms = uniform! {
+            atlas: self.atlas.sampled().magnify_filter(MagnifySamplerFilter::Nearest),
+        };
+
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            .. Default::default()
+        };
+
+        target.draw(
+            slice,
+            glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+            &self.program,
+            &uniforms,
+            &params,
+        )
+    }
+
+    fn layout(&self, lines: &[String], width: f32, height: f32) -> Vec<HudVertex>
+    {
+        let mut vertices = Vec::new();
+        let advance_x = CELL_WIDTH as f32 * self.pixel_scale;
+        let advance_y = CELL_HEIGHT as f32 * self.pixel_scale;
+        let glyph_w = GLYPH_WIDTH as f32 * self.pixel_scale;
+        let glyph_h = GLYPH_HEIGHT as f32 * self.pixel_scale;
+        let margin = MARGIN * self.pixel_scale / PIXEL_SCALE;
+
+        for (line_index, line) in lines.iter().enumerate() {
+            let top = margin + line_index as f32 * advance_y;
+
+            for (char_index, c) in line.chars().enumerate() {
+                if vertices.len() + 6 > MAX_CHARS * 6 {
+                    return vertices;
+                }
+
+                let glyph = glyph_index(c);
+                if glyph == 0 {
+                    continue;
+                }
+
+                let left = margin + char_index as f32 * advance_x;
+                let x0 = left / width * 2.0 - 1.0;
+                let x1 = (left + glyph_w) / width * 2.0 - 1.0;
+                let y0 = 1.0 - top / height * 2.0;
+                let y1 = 1.0 - (top + glyph_h) / height * 2.0;
+
+                let u0 = (glyph * CELL_WIDTH) as f32 / self.atlas_width as f32;
+                let u1 = (glyph * CELL_WIDTH + GLYPH_WIDTH) as f32 / self.atlas_width as f32;
+                let v0 = 1.0;
+                let v1 = 1.0 - GLYPH_HEIGHT as f32 / CELL_HEIGHT as f32;
+
+                let top_left = HudVertex { position: [x0, y0], tex_coords: [u0, v0] };
+                let top_right = HudVertex { position: [x1, y0], tex_coords: [u1, v0] };
+                let bottom_left = HudVertex { position: [x0, y1], tex_coords: [u0, v1] };
+                let bottom_right = HudVertex { position: [x1, y1], tex_coords: [u1, v1] };
+
+                vertices.extend_from_slice(&[top_left, bottom_left, top_right, top_right, bottom_left, bottom_right]);
+            }
+        }
+
+        vertices
+    }
+}
+
+fn glyph_index(c: char) -> usize
+{
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().position(|(g, _)| *g == c).unwrap_or(0)
+}
@@ -0,0 +1,110 @@
+use egui_glium::egui_winit::egui;
+use nalgebra::Vector3;
+
+use bird_flock::behaviour::BehaviourState;
+use bird_flock::bird::{Bird, BirdState};
+use bird_flock::flock::Flock;
+use bird_flock::steering::SteeringForces;
+
+pub const PICK_RADIUS: f32 = 0.06;
+
+pub struct BirdReport
+{
+    pub index: usize,
+    pub species: u8,
+    pub state: BirdState,
+    pub behaviour: BehaviourState,
+    pub leader: bool,
+    pub position: Vector3<f32>,
+    pub speed: f32,
+    pub mass: f32,
+    pub speed_factor: f32,
+    pub sociability: f32,
+    pub neighbours: u32,
+    pub forces: SteeringForces,
+    pub steering: Vector3<f32>,
+}
+
+impl BirdReport
+{
+    pub fn new(flock: &Flock, index: usize) -> Option<BirdReport>
+    {
+        let bird = flock.birds.get(index)?;
+        Some(BirdReport
+        {
+            index,
+            species: bird.species,
+            state: bird.state,
+            behaviour: bird.behaviour,
+            leader: bird.leader,
+            position: bird.position,
+            speed: bird.speed(),
+            mass: bird.mass,
+            speed_factor: bird.speed_factor,
+            sociability: bird.sociability,
+            neighbours: bird.neighbour_count,
+            forces: flock.steering_forces(index)?,
+            steering: bird.steering,
+        })
+    }
+}
+
+pub fn pick_bird(origin: Vector3<f32>, ray: Vector3<f32>, birds: &[Bird], radius: f32) -> Option<usize>
+{
+    let length = ray.norm();
+    if length == 0.0 {
+        return None;
+    }
+    let ray = ray / length;
+
+    birds.iter().enumerate()
+        .filter_map(|(index, bird)| {
+            let offset = bird.position - origin;
+            let along = offset.dot(&ray);
+            let miss = offset.norm_squared() - along * along;
+            if miss > radius * radius {
+                return None;
+            }
+            let hit = along - (radius * radius - miss).sqrt();
+            if along + radius < 0.0 { None } else { Some((index, hit)) }
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(index, _)| index)
+}
+
+fn vector_row(ui: &mut egui::Ui, label: &str, value: Vector3<f32>)
+{
+    ui.label(label);
+    ui.label(format!("{:.3}", value.norm()));
+    ui.label(format!("({:.3}, {:.3}, {:.3})", value.x, value.y, value.z));
+    ui.end_row();
+}
+
+pub fn inspector_panel(ctx: &egui::Context, report: &BirdReport, following: &mut bool) -> bool
+{
+    let mut open = true;
+    egui::Window::new("Bird inspector").open(&mut open).default_width(300.0).show(ctx, |ui| {
+        let state = match report.state {
+            BirdState::Flying => "flying",
+            BirdState::Perched => "perched",
+        };
+        ui.label(format!("Bird {} (species {}, {}{})", report.index, report.species, state, if report.leader { ", leader" } else { "" }));
+        ui.label(format!("position ({:.2}, {:.2}, {:.2})", report.position.x, report.position.y, report.position.z));
+        ui.label(format!("speed {:.4}, {}", report.speed, report.behaviour.name()));
+        ui.label(format!("mass {:.2}, top speed x{:.2}, sociability {:.2}", report.mass, report.speed_factor, report.sociability));
+        ui.label(format!("neighbours {}", report.neighbours));
+        ui.separator();
+
+        let flocking = report.forces.separation + report.forces.alignment + report.forces.cohesion;
+        egui::Grid::new("forces").num_columns(3).show(ui, |ui| {
+            vector_row(ui, "separation", report.forces.separation);
+            vector_row(ui, "alignment", report.forces.alignment);
+            vector_row(ui, "cohesion", report.forces.cohesion);
+            vector_row(ui, "other", report.steering - flocking);
+            vector_row(ui, "total", report.steering);
+        });
+        ui.separator();
+        ui.checkbox(following, "Follow with the camera");
+    });
+    open
+}
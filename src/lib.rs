@@ -0,0 +1,30 @@
+extern crate rayon;
+
+pub mod behaviour;
+pub mod bird;
+pub mod daynight;
+pub mod draw;
+pub mod events;
+pub mod export;
+pub mod flock;
+pub mod food;
+pub mod gltf;
+pub mod grid;
+pub mod metrics;
+pub mod modulation;
+pub mod octree;
+pub mod params;
+pub mod predator;
+pub mod preset;
+pub mod profile;
+pub mod replay;
+pub mod steering;
+pub mod terrain;
+pub mod timestep;
+pub mod trail;
+pub mod wind;
+#[cfg(feature = "renderer-wgpu")]
+pub mod wgpu_render;
+
+pub use flock::{Bird, Flock, FlockConfig};
+pub use metrics::FlockMetrics;
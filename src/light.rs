@@ -0,0 +1,45 @@
+use nalgebra::Vector3;
+
+use bird_flock::draw::FrameUniforms;
+
+pub const LIGHT_DIRECTION: [f32; 3] = [0.4, 1.0, 0.3];
+pub const LIGHT_COLOUR: [f32; 3] = [1.0, 0.96, 0.9];
+pub const AMBIENT: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light
+{
+    pub direction: [f32; 3],
+    pub colour: [f32; 3],
+    pub ambient: f32,
+}
+
+impl Default for Light
+{
+    fn default() -> Light
+    {
+        Light { direction: Vector3::from(LIGHT_DIRECTION).normalize().into(), colour: LIGHT_COLOUR, ambient: AMBIENT }
+    }
+}
+
+impl Light
+{
+    pub fn new(direction: Vector3<f32>) -> Result<Light, String>
+    {
+        let direction = direction.try_normalize(1e-6)
+            .ok_or_else(|| "light-dir must not be the zero vector".to_string())?;
+        Ok(Light { direction: direction.into(), ..Light::default() })
+    }
+
+    pub fn at_daylight(&self, daylight: f32) -> Light
+    {
+        let brightness = 0.15 + 0.85 * daylight;
+        let colour = [self.colour[0] * brightness, self.colour[1] * brightness, self.colour[2] * brightness];
+        Light { colour, ambient: self.ambient * (0.4 + 0.6 * daylight), ..*self }
+    }
+
+    pub fn frame_uniforms(&self, view: [[f32; 4]; 4], projection: [[f32; 4]; 4]) -> FrameUniforms
+    {
+        FrameUniforms { view, projection, light_dir: self.direction, light_colour: self.colour, ambient: self.ambient, flap_time: 0.0 }
+    }
+}
@@ -1,315 +1,1509 @@
 #[macro_use]
 extern crate glium;
 extern crate winit;
-extern crate rayon;
-
-use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
-use rand::random;
-use rayon::prelude::*;
-use std::sync::Arc;
-
-const NUM_BIRDS: usize = 10;
-const MAX_SPEED: f32 = 0.02;
-const NEIGHBOUR_RADIUS: f32 = 1.0;
-const SEPARATION_WEIGHT: f32 = 1.5;
-const ALIGNMENT_WEIGHT: f32 = 1.0;
-const COHESION_WEIGHT: f32 = 1.0;
-const GRAVITY: f32 = 0.0005;
-const BOUNDARY_SIZE: f32 = 5.0;
-const BOUNDARY_FORCE: f32 = 0.1;
-
-#[derive(Clone, Copy)]
-struct Bird
+
+#[cfg(feature = "audio")]
+mod audio;
+mod bird_render;
+mod camera;
+mod capture;
+mod cli;
+mod colour;
+mod config;
+mod debug_render;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gpu;
+mod help;
+mod ground_render;
+mod hud;
+mod inspector;
+mod light;
+mod mesh;
+mod projection;
+mod render;
+#[cfg(feature = "serve")]
+mod server;
+mod shaders;
+mod sky;
+mod stereo;
+mod trail_render;
+mod tuning;
+#[cfg(feature = "hot-reload")]
+mod watch;
+mod window;
+
+use bird_render::BirdRenderer;
+use camera::MoveKeys;
+use capture::FrameRecorder;
+use clap::Parser;
+use colour::ColourMode;
+use config::SimConfig;
+use debug_render::DebugRenderer;
+use gpu::GpuFlock;
+use ground_render::GroundRenderer;
+use hud::{FrameTimer, Hud, Stats};
+use inspector::BirdReport;
+use light::Light;
+use bird_flock::daynight;
+use bird_flock::metrics::{self, FlockMetrics, MetricsLog};
+use bird_flock::modulation;
+use bird_flock::flock::{positive, BoundaryShape, Flock, NeighbourSearch, ObstacleShape, ATTRACTOR_STRENGTH, REFERENCE_DT};
+use bird_flock::draw::{BirdInstance, FlockRenderer, FrameUniforms};
+use bird_flock::export::{ExportFormat, Exporter};
+use bird_flock::gltf::GltfBaker;
+use bird_flock::params::SimParam;
+use bird_flock::preset::{self, PresetBlend};
+use bird_flock::replay::{self, Replay, ReplayWriter};
+use bird_flock::terrain::{Terrain, GROUND_EXTENT};
+use bird_flock::timestep::{FixedTimestep, FrameLimiter};
+use bird_flock::trail::Trails;
+use nalgebra::Vector3;
+use projection::Projection;
+use shaders::ShaderSet;
+use sky::{Fog, Skybox};
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use trail_render::TrailRenderer;
+
+const CLICK_TOLERANCE: f64 = 4.0;
+const WIND_STEP: f32 = 0.0005;
+const WIND_TURN: f32 = 0.1;
+const WIND_GAIN: f32 = 1.25;
+const MAX_STEPS_PER_FRAME: u32 = 5;
+const PARAM_GAIN: f32 = 1.1;
+const MAX_INTERACTIVE_BIRDS: usize = 20_000;
+const PREDATOR_SCALE: f32 = 2.5;
+const LEADER_SCALE: f32 = 1.5;
+const SELECTED_SCALE: f32 = 2.0;
+const TIME_SCALE_GAIN: f32 = 2.0;
+const MIN_TIME_SCALE: f32 = 0.125;
+const MAX_TIME_SCALE: f32 = 4.0;
+
+fn warn_if_clamped(flock: &mut Flock)
 {
-    position: [f32; 3],
-    velocity: [f32; 3],
-    acceleration: [f32; 3],
+    let clamped = flock.clamp_to_boundary();
+    if clamped > 0 {
+        eprintln!("warning: {} bird(s) were outside the boundary and have been clamped", clamped);
+    }
+
+    let clamped = flock.clamp_waypoints();
+    if clamped > 0 {
+        eprintln!("warning: {} waypoint(s) were outside the boundary and have been clamped", clamped);
+    }
 }
 
-impl Bird
+fn run_headless(flock: &mut Flock, steps: u32, exporter: &mut Option<Exporter>, gltf: &mut Option<GltfBaker>, metrics_log: &mut Option<MetricsLog>, replay_writer: &mut Option<ReplayWriter>)
 {
-    fn new() -> Bird
-    {
-        let pos_x = random::<f32>() * BOUNDARY_SIZE - BOUNDARY_SIZE/2.0;
-        let pos_y = random::<f32>() * BOUNDARY_SIZE - BOUNDARY_SIZE/2.0;
-        let pos_z = random::<f32>() * BOUNDARY_SIZE - BOUNDARY_SIZE/2.0;
-        
-        let vel_x = random::<f32>() * 0.02 - 0.01;
-        let vel_y = random::<f32>() * 0.02 - 0.01;
-        let vel_z = random::<f32>() * 0.02 - 0.01;
-        
-        Bird
-        {
-            position: [pos_x, pos_y, pos_z],
-            velocity: [vel_x, vel_y, vel_z],
-            acceleration: [0.0, 0.0, 0.0],
+    println!("{}", metrics::CSV_HEADER);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut simulated = std::time::Duration::from_secs(0);
+    for step in 1..=steps {
+        let start = std::time::Instant::now();
+        flock.update();
+        simulated += start.elapsed();
+        if let Some(exporter) = exporter {
+            exporter.record(&flock.birds);
+        }
+        record_gltf(gltf, flock);
+        record_metrics(metrics_log, flock);
+        record_replay(replay_writer, flock);
+        if let Err(err) = flock.metrics().write_row(&mut out, step as u64) {
+            eprintln!("error: could not write metrics: {}", err);
+            break;
         }
     }
 
-    fn update(&mut self)
-    {
-        for i in 0..3
-        {
-            self.velocity[i] += self.acceleration[i];
-            
-            let vx = self.velocity[0];
-            let vy = self.velocity[1];
-            let vz = self.velocity[2];
-            let speed = (vx*vx + vy*vy + vz*vz).sqrt();
-            
-            if speed > MAX_SPEED {
-                let scale = MAX_SPEED / speed;
-                self.velocity[i] *= scale;
-            }
-            
-            self.position[i] += self.velocity[i];
-            
-            if self.position[i].abs() > BOUNDARY_SIZE/2.0 {
-                self.velocity[i] = -self.velocity[i] * 0.8;
-                if self.position[i] > 0.0 {
-                    self.position[i] = BOUNDARY_SIZE/2.0;
-                } else {
-                    self.position[i] = -BOUNDARY_SIZE/2.0;
-                }
-            }
-            
-            self.acceleration[i] = 0.0;
+    let seconds = simulated.as_secs_f64();
+    if steps > 0 && seconds > 0.0 {
+        eprintln!("{} steps of {} birds in {:.3} s ({:.1} steps/s)", steps, flock.birds.len(), seconds, steps as f64 / seconds);
+    }
+}
+
+fn record_metrics(metrics_log: &mut Option<MetricsLog>, flock: &Flock)
+{
+    if let Some(log) = metrics_log {
+        if let Err(err) = log.record(flock) {
+            eprintln!("error: could not write metrics, logging stopped: {}", err);
+            *metrics_log = None;
         }
     }
+}
 
-    fn apply_force(&mut self, force: [f32; 3])
-    {
-        self.acceleration[0] += force[0];
-        self.acceleration[1] += force[1];
-        self.acceleration[2] += force[2];
+fn finish_metrics(metrics_log: &mut Option<MetricsLog>)
+{
+    if let Some(log) = metrics_log.take() {
+        if let Err(err) = log.finish() {
+            eprintln!("error: could not finish metrics log: {}", err);
+        }
     }
+}
 
-    fn distance_to(&self, other: &Bird) -> f32 {
-        let dx = self.position[0] - other.position[0];
-        let dy = self.position[1] - other.position[1];
-        let dz = self.position[2] - other.position[2];
-        let distance = (dx * dx + dy * dy + dz * dz).sqrt();
-        return distance;
+fn record_replay(replay_writer: &mut Option<ReplayWriter>, flock: &Flock)
+{
+    if let Some(writer) = replay_writer {
+        if let Err(err) = writer.record(flock) {
+            eprintln!("error: could not write replay, recording stopped: {}", err);
+            *replay_writer = None;
+        }
     }
 }
 
-struct Flock
+#[cfg(feature = "serve")]
+fn publish(server: &Option<server::FlockServer>, flock: &Flock)
 {
-    birds: Vec<Bird>,
+    if let Some(server) = server {
+        server.publish(flock);
+    }
 }
 
-impl Flock
+fn finish_replay(replay_writer: &mut Option<ReplayWriter>)
 {
-    fn new() -> Flock
-    {
-        let mut birds = Vec::new();
-        
-        for i in 0..NUM_BIRDS 
-        {
-            let bird = Bird::new();
-            birds.push(bird);
+    if let Some(writer) = replay_writer.take() {
+        if let Err(err) = writer.finish() {
+            eprintln!("error: could not finish replay: {}", err);
         }
-        
-        Flock { birds }
     }
+}
 
-    fn update(&mut self)
-    {
-        let birds_copy = self.birds.clone();
-        let birds_shared = Arc::new(birds_copy);
-        
-        self.birds.par_iter_mut().for_each(|bird| {
-            let mut separation = [0.0, 0.0, 0.0];
-            let mut alignment = [0.0, 0.0, 0.0];
-            let mut cohesion = [0.0, 0.0, 0.0];
-            let mut neighbour_count = 0;
-
-            for other in birds_shared.iter() 
-            {
-                let bird_ptr = bird as *const _ as usize;
-                let other_ptr = other as *const _ as usize;
-                
-                if bird_ptr == other_ptr
-                {
-                    continue;
-                }
+fn seek_replay(replay: &mut Replay, flock: &mut Flock, position: usize)
+{
+    replay.seek(position);
+    if let Err(err) = replay.apply(flock) {
+        eprintln!("error: could not read replay frame {}: {}", position, err);
+    }
+}
 
-                let dist = bird.distance_to(other);
+fn record_gltf(gltf: &mut Option<GltfBaker>, flock: &Flock)
+{
+    if let Some(baker) = gltf {
+        baker.record(flock.time * REFERENCE_DT, &flock.birds);
+    }
+}
 
-                if dist < NEIGHBOUR_RADIUS
-                 {
-                    separation[0] += (bird.position[0] - other.position[0]);
-                    separation[1] += (bird.position[1] - other.position[1]);
-                    separation[2] += (bird.position[2] - other.position[2]);
+fn finish_gltf(gltf: &mut Option<GltfBaker>)
+{
+    if let Some(baker) = gltf.take() {
+        let samples = baker.len();
+        match baker.finish() {
+            Ok(()) => println!("Baked {} animation sample(s) to glTF", samples),
+            Err(err) => eprintln!("error: could not finish glTF export: {}", err),
+        }
+    }
+}
 
-                    alignment[0] += other.velocity[0];
-                    alignment[1] += other.velocity[1];
-                    alignment[2] += other.velocity[2];
+fn bake_replay(replay: &mut Replay, flock: &mut Flock, baker: &mut GltfBaker) -> std::io::Result<()>
+{
+    for position in 0..replay.len() {
+        replay.seek(position);
+        replay.apply(flock)?;
+        baker.record(flock.time * REFERENCE_DT, &flock.birds);
+    }
+    Ok(())
+}
 
-                    cohesion[0] += other.position[0];
-                    cohesion[1] += other.position[1];
-                    cohesion[2] += other.position[2];
+#[cfg(feature = "hot-reload")]
+fn reload_config(args: &cli::Args, flock: &mut Flock) -> Result<(), String>
+{
+    let settings = SimConfig::load(&args.config)?;
+    settings.validate()?;
+    let mut config = settings.flock;
+    args.apply(&mut config);
 
-                    neighbour_count += 1;
-                }
-            }
+    let previous_config = std::mem::replace(&mut flock.config, config);
+    let previous_obstacles = match settings.obstacles {
+        Some(obstacles) => Some(std::mem::replace(&mut flock.obstacles, obstacles)),
+        None => None,
+    };
+    let previous_waypoints = std::mem::replace(&mut flock.waypoints, settings.waypoints);
+    let previous_roosts = std::mem::replace(&mut flock.roosts, settings.roosts);
+    if let Err(message) = flock.validate() {
+        flock.config = previous_config;
+        if let Some(obstacles) = previous_obstacles {
+            flock.obstacles = obstacles;
+        }
+        flock.waypoints = previous_waypoints;
+        flock.roosts = previous_roosts;
+        return Err(format!("{}: {}", args.config.display(), message));
+    }
+    warn_if_clamped(flock);
+    Ok(())
+}
 
-            if neighbour_count > 0 {
-                
-                separation[0] *= SEPARATION_WEIGHT;
-                separation[1] *= SEPARATION_WEIGHT;
-                separation[2] *= SEPARATION_WEIGHT;
-
-                alignment[0] = alignment[0] / neighbour_count as f32;
-                alignment[1] = alignment[1] / neighbour_count as f32;
-                alignment[2] = alignment[2] / neighbour_count as f32;
-                alignment[0] = (alignment[0] - bird.velocity[0]) * ALIGNMENT_WEIGHT;
-                alignment[1] = (alignment[1] - bird.velocity[1]) * ALIGNMENT_WEIGHT;
-                alignment[2] = (alignment[2] - bird.velocity[2]) * ALIGNMENT_WEIGHT;
-
-
-                cohesion[0] = cohesion[0] / neighbour_count as f32;
-                cohesion[1] = cohesion[1] / neighbour_count as f32;
-                cohesion[2] = cohesion[2] / neighbour_count as f32;
-                cohesion[0] = (cohesion[0] - bird.position[0]) * COHESION_WEIGHT;
-                cohesion[1] = (cohesion[1] - bird.position[1]) * COHESION_WEIGHT;
-                cohesion[2] = (cohesion[2] - bird.position[2]) * COHESION_WEIGHT;
-
-                bird.apply_force(separation);
-                bird.apply_force(alignment);
-                bird.apply_force(cohesion);
-            }
+fn finish_export(exporter: &mut Option<Exporter>)
+{
+    if let Some(exporter) = exporter.take() {
+        if let Err(err) = exporter.finish() {
+            eprintln!("error: could not finish export: {}", err);
+        }
+    }
+}
 
-            bird.apply_force([0.0, -GRAVITY, 0.0]);
-
-            for i in 0..3 {
-                if bird.position[i].abs() > BOUNDARY_SIZE/2.0 - 1.0 
-                {
-                    let boundary_force = -bird.position[i].signum() * BOUNDARY_FORCE;
-                    
-                    if i == 0 {
-                        bird.apply_force([boundary_force, 0.0, 0.0]);
-                    } else if i == 1 {
-                        bird.apply_force([0.0, boundary_force, 0.0]);
-                    } else {
-                        bird.apply_force([0.0, 0.0, boundary_force]);
-                    }
-                }
-            }
+fn save_snapshot(flock: &Flock, path: &Path)
+{
+    match flock.save_to_path(path) {
+        Ok(()) => println!("Saved flock to {} at step {}", path.display(), flock.steps),
+        Err(err) => eprintln!("Failed to save flock to {}: {}", path.display(), err),
+    }
+}
 
-            bird.update();
-        });
+fn finish_capture(recorder: FrameRecorder)
+{
+    let path = recorder.path().display().to_string();
+    match recorder.finish() {
+        Ok(saved) => println!("Saved {} frame(s) to {}", saved, path),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
+
+fn preset_key(key: winit::keyboard::PhysicalKey) -> Option<usize>
+{
+    use winit::keyboard::{KeyCode, PhysicalKey};
+
+    let digits = [
+        KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+        KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+        KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+    match key {
+        PhysicalKey::Code(code) => digits.iter().position(|&digit| digit == code),
+        _ => None,
+    }
+}
+
+fn grab_cursor(window: &winit::window::Window, grab: bool)
+{
+    let result = if grab {
+        window.set_cursor_grab(winit::window::CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(winit::window::CursorGrabMode::Confined))
+    } else {
+        window.set_cursor_grab(winit::window::CursorGrabMode::None)
+    };
+    if let Err(err) = result {
+        eprintln!("warning: could not grab the cursor: {}", err);
+    }
+    window.set_cursor_visible(!grab);
+}
+
+fn upload_to_gpu<F: ?Sized + glium::backend::Facade>(gpu: &mut Option<GpuFlock>, facade: &F, flock: &Flock)
+{
+    if let Some(gpu_flock) = gpu {
+        if let Err(err) = gpu_flock.upload(facade, flock) {
+            eprintln!("warning: could not upload the flock to the GPU, falling back to the CPU: {}", err);
+            *gpu = None;
+        }
     }
 }
 
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
 fn main() {
+    if let Err(err) = run(cli::Args::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(if err.is::<UsageError>() { 2 } else { 1 });
+    }
+}
+
+fn run(args: cli::Args) -> Result<(), Box<dyn Error>> {
     #[allow(unused_imports)]
     use glium::{glutin, Surface};
 
+    #[cfg(feature = "profile")]
+    let _profile = match &args.profile {
+        Some(path) => Some(bird_flock::profile::record_to(path).map_err(|err| format!("could not create profile {}: {}", path.display(), err))?),
+        None => None,
+    };
+
+    let settings = SimConfig::load(&args.config)?;
+    settings.validate().map_err(UsageError)?;
+
+    let mut flock = match &args.scene {
+        Some(path) => Flock::load_from_path(path).map_err(|err| format!("could not load scene {}: {}", path.display(), err))?,
+        None => {
+            let mut config = settings.flock.clone();
+            args.apply(&mut config);
+            let mut species = args.species(&config);
+            if species.is_empty() {
+                species = settings.flocks.iter().map(|own| {
+                    let mut own = own.clone();
+                    let num_birds = own.num_birds;
+                    args.apply(&mut own);
+                    own.num_birds = num_birds;
+                    own
+                }).collect();
+            }
+            let mut flock = Flock::with_species(config, species);
+            match &settings.obstacles {
+                Some(obstacles) => flock.obstacles = obstacles.clone(),
+                None => {
+                    flock.add_obstacle(Vector3::new(0.0, 0.0, 0.0), 0.5);
+                    flock.add_obstacle(Vector3::new(1.2, -0.8, 0.5), 0.3);
+                },
+            }
+            flock.waypoints = settings.waypoints.clone();
+            flock.roosts = settings.roosts.clone();
+            flock
+        },
+    };
+
+    if args.scene.is_some() {
+        args.apply(&mut flock.config);
+    }
+    let mut rng = flock.config.rng();
+    if args.scene.is_some() {
+        flock.spawn_predators(&mut rng);
+        flock.spawn_food(&mut rng);
+        if flock.config.num_leaders > 0 {
+            flock.assign_leaders();
+        }
+    }
+    let ground_size = flock.config.boundary_size * GROUND_EXTENT;
+    let ground_base = -flock.config.boundary_size / 2.0;
+    if let Some(path) = &args.heightmap {
+        flock.terrain = Some(ground_render::load_heightmap(path, ground_size, ground_base, args.terrain_height)?);
+    } else if args.ground {
+        flock.terrain = Some(Terrain::flat(ground_size, ground_base));
+    }
+    let mut replay = match &args.replay {
+        Some(path) => {
+            let mut replay = Replay::open(path).map_err(|err| format!("could not open replay {}: {}", path.display(), err))?;
+            flock = replay.scene();
+            replay.apply(&mut flock).map_err(|err| format!("could not read replay {}: {}", path.display(), err))?;
+            Some(replay)
+        },
+        None => None,
+    };
+    if !positive(args.sim_hz) {
+        return Err(UsageError(format!("sim-hz must be positive, got {}", args.sim_hz)).into());
+    }
+    if !positive(args.gltf_rate) {
+        return Err(UsageError(format!("gltf-rate must be positive, got {}", args.gltf_rate)).into());
+    }
+    if !positive(args.capture_fps) {
+        return Err(UsageError(format!("capture-fps must be positive, got {}", args.capture_fps)).into());
+    }
+    let max_fps = args.max_fps.unwrap_or(settings.window.max_fps);
+    if !(max_fps >= 0.0 && max_fps.is_finite()) {
+        return Err(UsageError(format!("max-fps must be zero or positive, got {}", max_fps)).into());
+    }
+    flock.validate().map_err(UsageError)?;
+    warn_if_clamped(&mut flock);
+
+    let mut exporter = match &args.export {
+        Some(path) => {
+            let format = args.export_format.unwrap_or_else(|| ExportFormat::from_path(path));
+            Some(Exporter::create(path, format, args.export_every).map_err(|err| format!("could not create export file {}: {}", path.display(), err))?)
+        },
+        None => None,
+    };
+
+    let mut gltf = match &args.export_gltf {
+        Some(path) => Some(GltfBaker::create(path, args.gltf_rate).map_err(|err| format!("could not create glTF file {}: {}", path.display(), err))?),
+        None => None,
+    };
+    if let (Some(replay), Some(baker)) = (&mut replay, &mut gltf) {
+        bake_replay(replay, &mut flock, baker).map_err(|err| format!("could not read replay frame {}: {}", replay.position(), err))?;
+        finish_gltf(&mut gltf);
+        return Ok(());
+    }
+
+    let mut metrics_log = match &args.metrics {
+        Some(path) => Some(MetricsLog::create(path, args.metrics_every).map_err(|err| format!("could not create metrics file {}: {}", path.display(), err))?),
+        None => None,
+    };
+
+    let mut replay_writer = match &args.save_replay {
+        Some(path) => Some(ReplayWriter::create(path, &flock, args.replay_every).map_err(|err| format!("could not create replay {}: {}", path.display(), err))?),
+        None => None,
+    };
+
+    if let Some(steps) = args.headless {
+        run_headless(&mut flock, steps, &mut exporter, &mut gltf, &mut metrics_log, &mut replay_writer);
+        finish_export(&mut exporter);
+        finish_gltf(&mut gltf);
+        finish_metrics(&mut metrics_log);
+        finish_replay(&mut replay_writer);
+        if let Some(path) = &args.save_on_exit {
+            save_snapshot(&flock, path);
+        }
+        return Ok(());
+    }
+
+    #[cfg(feature = "audio")]
+    let mut audio = match (&args.audio_file, args.audio_input) {
+        (Some(path), _) => Some(audio::AudioInput::open_file(path)?),
+        (None, true) => Some(audio::AudioInput::open_device()?),
+        (None, false) => None,
+    };
+    #[cfg(not(feature = "audio"))]
+    {
+        if args.audio_file.is_some() || args.audio_input {
+            return Err("audio input needs a build with --features audio".into());
+        }
+    }
+    #[cfg(feature = "serve")]
+    let server = match args.serve {
+        Some(address) => {
+            let server = server::FlockServer::start(address, args.serve_format)?;
+            println!("streaming flock state on ws://{}", address);
+            Some(server)
+        },
+        None => None,
+    };
+    let audio_mappings = if settings.audio.is_empty() { modulation::default_mappings() } else { settings.audio.clone() };
+    let presets = if settings.presets.is_empty() { preset::default_presets() } else { settings.presets.clone() };
+    let mut preset_blend: Option<PresetBlend> = None;
+
     let event_loop = glium::winit::event_loop::EventLoop::builder()
         .build()
-        .expect("event loop building");
-    let (window, display) = glium::backend::glutin::SimpleWindowBuilder::new()
-        .with_title("Bird Flock Simulation")
-        .build(&event_loop);
-
-    let mut flock = Flock::new();
+        .map_err(|err| format!("could not create the event loop: {}", err))?;
+    let vsync = args.vsync.map_or(settings.window.vsync, |vsync| vsync == cli::Switch::On);
+    let (window, display) = window::create_window(&event_loop, &settings.window, vsync)?;
+    let monitor = match args.monitor.or(settings.window.monitor) {
+        Some(index) => Some(window::select_monitor(&window, index)?),
+        None => None,
+    };
+    if let Some(monitor) = &monitor {
+        window::move_to_monitor(&window, monitor);
+    }
+    if args.fullscreen || settings.window.fullscreen {
+        window::set_fullscreen(&window, true, monitor);
+    }
 
-    #[derive(Copy, Clone)]
-    struct Vertex {
-        position: [f32; 2],
+    let light = match &args.light_dir {
+        Some(direction) => Light::new(Vector3::new(direction[0], direction[1], direction[2]))?,
+        None => Light::default(),
+    };
+    let mut fog = settings.sky.fog();
+    if let Some(density) = args.fog_density {
+        fog.density = density;
     }
+    if let Some(colour) = &args.fog_colour {
+        fog.colour = [colour[0], colour[1], colour[2]];
+    }
+    fog.validate().map_err(UsageError)?;
+    let separation = args.eye_separation.unwrap_or(settings.camera.eye_separation);
+    stereo::validate_separation(separation).map_err(UsageError)?;
+    let mut eye_separation = if args.stereo || settings.camera.stereo { Some(separation) } else { None };
+
+    let shape = mesh::bird_mesh();
 
-    implement_vertex!(Vertex, position);
+    let vertex_buffer = glium::VertexBuffer::new(&display, &shape)?;
 
-    let vertex1 = Vertex { position: [-0.05, -0.0288] };
-    let vertex2 = Vertex { position: [ 0.00,  0.0577] };
-    let vertex3 = Vertex { position: [ 0.05, -0.0288] };
-    let shape = vec![vertex1, vertex2, vertex3];
+    let bird_params = glium::DrawParameters {
+        depth: render::depth_test(true),
+        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
+        .. Default::default()
+    };
+    #[cfg(feature = "hot-reload")]
+    let (mut shaders, watcher) = if args.watch {
+        let shaders = watch::load_shaders(&args.shader_dir)?;
+        let watcher = watch::FileWatcher::new(&args.config, &args.shader_dir)?;
+        println!("Watching {} and {} for changes", args.config.display(), args.shader_dir.display());
+        (shaders, Some(watcher))
+    } else {
+        (ShaderSet::builtin(), None)
+    };
+    #[cfg(not(feature = "hot-reload"))]
+    let shaders = ShaderSet::builtin();
+    #[cfg(feature = "hot-reload")]
+    let mut reload_error: Option<String> = None;
+    #[cfg(not(feature = "hot-reload"))]
+    let reload_error: Option<String> = None;
 
-    let vertex_buffer = glium::VertexBuffer::new(&display, &shape).unwrap();
-    let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+    let mut bird_renderer = BirdRenderer::new(&display, bird_params.clone(), &shaders)?;
+    #[cfg_attr(not(feature = "hot-reload"), allow(unused_mut))]
+    let mut skybox = match args.skybox.as_ref().or(settings.sky.skybox.as_ref()) {
+        Some(path) => Some(Skybox::load(&display, path, &shaders.sky)?),
+        None => None,
+    };
+    let mut bird_instances: Vec<BirdInstance> = Vec::new();
+    let depth_params = glium::DrawParameters {
+        depth: render::depth_test(true),
+        .. Default::default()
+    };
 
-    let vertex_shader_src = r#"
+    let (sphere_vertices, sphere_indices) = mesh::uv_sphere(12, 16);
+    let sphere_vertex_buffer = glium::VertexBuffer::new(&display, &sphere_vertices)?;
+    let sphere_index_buffer = glium::IndexBuffer::new(&display, glium::index::PrimitiveType::TrianglesList, &sphere_indices)?;
+
+    let obstacle_vertex_shader_src = r#"
         #version 140
 
-        in vec2 position;
+        in vec3 position;
 
         uniform mat4 model;
         uniform mat4 view;
         uniform mat4 projection;
 
         void main() {
-            gl_Position = projection * view * model * vec4(position, 0.0, 1.0);
+            gl_Position = projection * view * model * vec4(position, 1.0);
         }
     "#;
 
-    let fragment_shader_src = r#"
+    let obstacle_fragment_shader_src = r#"
         #version 140
 
         out vec4 color;
 
+        uniform vec3 tint;
+
         void main() {
-            color = vec4(1.0, 0.0, 0.0, 1.0);
+            color = vec4(tint, 1.0);
         }
     "#;
 
-    let program = glium::Program::from_source(&display, vertex_shader_src, fragment_shader_src, None).unwrap();
+    let obstacle_program = render::compile_program(&display, "obstacle", obstacle_vertex_shader_src, obstacle_fragment_shader_src)?;
+    let obstacle_params = glium::DrawParameters {
+        polygon_mode: glium::draw_parameters::PolygonMode::Line,
+        depth: render::depth_test(true),
+        .. Default::default()
+    };
+
+    let box_vertex_buffer = glium::VertexBuffer::new(&display, &mesh::wireframe_cube(2.0))?;
+
+    let mut ground_renderer = match &flock.terrain {
+        Some(terrain) => Some(GroundRenderer::new(&display, terrain, &shaders.ground)?),
+        None => None,
+    };
+
+    let mut boundary_size = flock.config.boundary_size;
+    let mut boundary_vertex_buffer = glium::VertexBuffer::new(&display, &mesh::wireframe_cube(boundary_size))?;
+    let mut show_boundary = true;
+
+    let mut gpu = if !args.gpu {
+        None
+    } else if !GpuFlock::is_supported(&display) {
+        eprintln!("warning: compute shaders are not available, falling back to the CPU simulation");
+        None
+    } else {
+        match GpuFlock::new(&display, &flock) {
+            Ok(gpu) => {
+                let ignored = gpu::unsupported_features(&flock);
+                if !ignored.is_empty() {
+                    eprintln!("warning: ==================================================================");
+                    eprintln!("warning: --gpu runs the core flocking rules only; these features are DISABLED:");
+                    for feature in ignored.iter() {
+                        eprintln!("warning:   - {}", feature);
+                    }
+                    eprintln!("warning: run without --gpu to keep them");
+                    eprintln!("warning: ==================================================================");
+                }
+                Some(gpu)
+            },
+            Err(err) => {
+                eprintln!("warning: could not start the GPU simulation, falling back to the CPU: {}", err);
+                None
+            },
+        }
+    };
+    let mut gpu_reference = if gpu.is_some() && args.gpu_validate { Some(gpu::cpu_reference(&flock)) } else { None };
+    let mut validation_steps = 0;
+
+    let mut egui_glium = egui_glium::EguiGlium::new(egui_glium::egui_winit::egui::ViewportId::ROOT, &display, &window, &event_loop);
+    let mut show_tuning = false;
+    let mut show_help = false;
+    let mut screenshot_requested = false;
+    let mut recorder = match &args.capture {
+        Some(path) => Some(FrameRecorder::create(path, args.capture_every, args.capture_fps)?),
+        None => None,
+    };
+
+    let mut hud = Hud::new(&display)?;
+    hud.set_scale(window.scale_factor());
+    let mut trail_renderer = TrailRenderer::new(&display)?;
+    let mut debug_renderer = DebugRenderer::new(&display)?;
+    let mut trails = Trails::new(args.trail_length);
+    let mut frame_timer = FrameTimer::new();
+    let mut flock_metrics: Option<FlockMetrics> = None;
+    let mut clusters: Vec<usize> = Vec::new();
+    let mut frame_index = 0u32;
+    let mut cursor: Option<(f64, f64)> = None;
+    let mut herding = false;
+    let mut shift_held = false;
+    let mut control_held = false;
+    let mut alt_held = false;
+    let mut pan_last: Option<(f64, f64)> = None;
+    let mut selected_param = SimParam::SeparationWeight;
+    let mut colour_mode = if flock.species.len() > 1 { ColourMode::Species } else { ColourMode::Speed };
+    let mut paused = false;
+    let mut step_once = false;
+    #[cfg(feature = "serve")]
+    let serving = server.is_some();
+    #[cfg(not(feature = "serve"))]
+    let serving = false;
+    let pipeline = args.pipeline != Some(cli::Switch::Off) && exporter.is_none() && gltf.is_none() && metrics_log.is_none() && replay_writer.is_none() && !serving;
+    let mut spare: Option<Flock> = None;
+    let mut time_scale = 1.0;
+    let mut frame_limiter = FrameLimiter::new(max_fps);
+    let max_steps = frame_limiter.interval().map_or(MAX_STEPS_PER_FRAME, |interval| MAX_STEPS_PER_FRAME.max((interval.as_secs_f64() * args.sim_hz).ceil() as u32 + 1));
+    let mut timestep = FixedTimestep::new(args.sim_hz, max_steps);
+    let mut last_frame = std::time::Instant::now();
+
+    let initial_size = window.inner_size();
+    let mut projection = Projection::new(initial_size.width, initial_size.height, settings.camera.fov.to_radians());
+
+    let mut camera = settings.camera.camera();
+    if let Some(plane) = flock.config.plane {
+        camera.face_plane(plane);
+    }
+    if let Some(index) = args.follow {
+        if index >= flock.birds.len() {
+            return Err(UsageError(format!("follow index {} is out of range for a flock of {}", index, flock.birds.len())).into());
+        }
+        if gpu.is_none() {
+            camera.follow(index);
+        } else {
+            eprintln!("warning: the follow camera is CPU-only and has been disabled");
+        }
+    }
+    let mut move_keys = MoveKeys::default();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = match gamepad::Gamepad::new() {
+        Ok(gamepad) => Some(gamepad),
+        Err(err) => {
+            eprintln!("warning: {}", err);
+            None
+        },
+    };
+    let mut left_press: Option<(f64, f64)> = None;
+    let mut drag_last: Option<(f64, f64)> = None;
+    let mut selected: Option<usize> = None;
 
     #[allow(deprecated)] 
-    let _ = event_loop.run(move |event, window_target| {
+    event_loop.run(move |event, window_target| {
         match event {
+            winit::event::Event::WindowEvent { ref event, .. } if egui_glium.on_event(&window, event).consumed && (show_tuning || show_help || selected.is_some()) => (),
             winit::event::Event::WindowEvent { event, .. } => match event {
 
-                winit::event::WindowEvent::CloseRequested => window_target.exit(),
+                winit::event::WindowEvent::CloseRequested => {
+                    finish_export(&mut exporter);
+                    finish_gltf(&mut gltf);
+                    finish_metrics(&mut metrics_log);
+                    finish_replay(&mut replay_writer);
+                    if let Some(path) = &args.save_on_exit {
+                        save_snapshot(&flock, path);
+                    }
+                    if let Some(active) = recorder.take() {
+                        finish_capture(active);
+                    }
+                    window_target.exit();
+                },
 
                 winit::event::WindowEvent::Resized(window_size) => {
                     display.resize(window_size.into());
+                    projection.resize(window_size.width, window_size.height);
+                },
+
+                winit::event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => hud.set_scale(scale_factor),
+
+                winit::event::WindowEvent::KeyboardInput { event, .. } => {
+                    let pressed = event.state == winit::event::ElementState::Pressed;
+                    let moving = pressed && !control_held;
+                    match event.physical_key {
+                        winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyW) => move_keys.forward = moving,
+                        winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyS) => move_keys.back = moving,
+                        winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyA) => move_keys.left = moving,
+                        winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyD) => move_keys.right = moving,
+                        _ => (),
+                    }
+
+                    if pressed && gpu.is_none() {
+                        match event.physical_key {
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyN) if shift_held => {
+                                flock.resize((flock.birds.len() + tuning::SPAWN_BATCH).min(MAX_INTERACTIVE_BIRDS), &mut rng);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyM) if shift_held => {
+                                flock.resize(flock.birds.len().saturating_sub(tuning::SPAWN_BATCH), &mut rng);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyN) if flock.birds.len() < MAX_INTERACTIVE_BIRDS => {
+                                flock.spawn_near_centroid(&mut rng);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyM) if !flock.birds.is_empty() => {
+                                let index = rng.gen_range(0..flock.birds.len());
+                                let _ = flock.remove(index);
+                            },
+                            _ => (),
+                        }
+                    }
+
+                    if pressed && replay.is_some() {
+                        let frames = replay::REPLAY_SEEK * if shift_held { 10 } else { 1 };
+                        match (event.physical_key, &mut replay) {
+                            (winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowLeft), Some(replay)) => {
+                                let position = replay.position().saturating_sub(frames);
+                                seek_replay(replay, &mut flock, position);
+                                trails.clear();
+                            },
+                            (winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowRight), Some(replay)) => {
+                                let position = replay.position() + frames;
+                                seek_replay(replay, &mut flock, position);
+                                trails.clear();
+                            },
+                            _ => (),
+                        }
+                    }
+
+                    if pressed && !event.repeat {
+                        match preset_key(event.physical_key) {
+                            Some(index) if control_held => {
+                                if let Some(&param) = SimParam::ALL.get(index) {
+                                    selected_param = param;
+                                    println!("Tuning {} = {}", selected_param.name(), selected_param.get(&flock.config));
+                                }
+                            },
+                            Some(index) if !shift_held => {
+                                if let Some(chosen) = presets.get(index) {
+                                    preset_blend = Some(PresetBlend::new(chosen, &flock.config, preset::BLEND_SECONDS));
+                                    println!("Preset {}: {}", index + 1, chosen.name);
+                                }
+                            },
+                            _ => (),
+                        }
+
+                        match event.physical_key {
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F1) => hud.toggle(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F2) => show_tuning = !show_tuning,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F3) => debug_renderer.toggle(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyD) if control_held => debug_renderer.toggle(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyH) => show_help = !show_help,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Tab) => {
+                                camera.toggle_free();
+                                grab_cursor(&window, camera.free);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F11) => window::toggle_fullscreen(&window),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F12) => screenshot_requested = true,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyS) if control_held => screenshot_requested = true,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F10) => {
+                                if let Some(active) = recorder.take() {
+                                    finish_capture(active);
+                                } else {
+                                    match FrameRecorder::create(capture::CAPTURE_DIR, 1, args.capture_fps) {
+                                        Ok(active) => {
+                                            println!("Recording frames to {}/", capture::CAPTURE_DIR);
+                                            recorder = Some(active);
+                                        },
+                                        Err(err) => eprintln!("error: {}", err),
+                                    }
+                                }
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Escape) => flock.clear_attractors(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Space) => paused = !paused,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::BracketRight) => {
+                                if let Some(follow) = &mut camera.follow {
+                                    follow.index = (follow.index + 1) % flock.birds.len().max(1);
+                                    selected = selected.map(|_| follow.index);
+                                }
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::BracketLeft) => {
+                                if let Some(follow) = &mut camera.follow {
+                                    follow.index = (follow.index + flock.birds.len().max(1) - 1) % flock.birds.len().max(1);
+                                    selected = selected.map(|_| follow.index);
+                                }
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyK) if gpu.is_none() => {
+                                let was_following = camera.follow.take().is_some();
+                                if !was_following && !flock.birds.is_empty() {
+                                    camera.follow(0);
+                                }
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyC) => colour_mode = colour_mode.next(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit1) if shift_held => flock.adjust_wind(Vector3::new(-WIND_STEP, 0.0, 0.0)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit2) if shift_held => flock.adjust_wind(Vector3::new(WIND_STEP, 0.0, 0.0)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit3) if shift_held => flock.adjust_wind(Vector3::new(0.0, -WIND_STEP, 0.0)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit4) if shift_held => flock.adjust_wind(Vector3::new(0.0, WIND_STEP, 0.0)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit5) if shift_held => flock.adjust_wind(Vector3::new(0.0, 0.0, -WIND_STEP)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit6) if shift_held => flock.adjust_wind(Vector3::new(0.0, 0.0, WIND_STEP)),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit0) if shift_held => flock.config.wind = Vector3::zeros(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowLeft) | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowRight) if replay.is_some() => (),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyR) if replay.is_some() => {
+                                if let Some(replay) = &mut replay {
+                                    seek_replay(replay, &mut flock, 0);
+                                    trails.clear();
+                                }
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowLeft) => flock.turn_wind(-WIND_TURN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowRight) => flock.turn_wind(WIND_TURN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowUp) => flock.tilt_wind(WIND_TURN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::ArrowDown) => flock.tilt_wind(-WIND_TURN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Equal)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadAdd) if control_held => {
+                                flock.scale_param(selected_param, PARAM_GAIN);
+                                println!("{} = {}", selected_param.name(), selected_param.get(&flock.config));
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Minus)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadSubtract) if control_held => {
+                                flock.scale_param(selected_param, 1.0 / PARAM_GAIN);
+                                println!("{} = {}", selected_param.name(), selected_param.get(&flock.config));
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Digit0) if control_held => {
+                                flock.reset_param(selected_param);
+                                println!("{} reset to {}", selected_param.name(), selected_param.get(&flock.config));
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Equal)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadAdd) if shift_held => flock.scale_wind(WIND_GAIN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Minus)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadSubtract) if shift_held => flock.scale_wind(1.0 / WIND_GAIN),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Equal)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadAdd) => time_scale = (time_scale * TIME_SCALE_GAIN).min(MAX_TIME_SCALE),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Minus)
+                            | winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::NumpadSubtract) => time_scale = (time_scale / TIME_SCALE_GAIN).max(MIN_TIME_SCALE),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyG) => flock.config.wind_mode = flock.config.wind_mode.next(),
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyB) => show_boundary = !show_boundary,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyV) => eye_separation = if eye_separation.is_some() { None } else { Some(separation) },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyO) => {
+                                flock.config.boundary_shape = flock.config.boundary_shape.next(flock.config.boundary_size);
+                                println!("Boundary: {}", flock.config.boundary_shape.name());
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyT) => {
+                                trail_renderer.toggle();
+                                trails.clear();
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Period) if paused => step_once = true,
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyR) => {
+                                let obstacles = std::mem::take(&mut flock.obstacles);
+                                let terrain = flock.terrain.take();
+                                let waypoints = std::mem::take(&mut flock.waypoints);
+                                let roosts = std::mem::take(&mut flock.roosts);
+                                flock = Flock::with_species(flock.config.clone(), flock.species.clone());
+                                flock.obstacles = obstacles;
+                                flock.terrain = terrain;
+                                flock.waypoints = waypoints;
+                                flock.roosts = roosts;
+                                trails.clear();
+                                upload_to_gpu(&mut gpu, &display, &flock);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F5) => {
+                                save_snapshot(&flock, &args.snapshot);
+                            },
+                            winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::F9) => {
+                                match Flock::load_from_path(&args.snapshot) {
+                                    Ok(loaded) => {
+                                        flock = loaded;
+                                        ground_renderer = match &flock.terrain {
+                                            Some(terrain) => GroundRenderer::new(&display, terrain, &shaders.ground).map_err(|err| eprintln!("warning: could not build the ground: {}", err)).ok(),
+                                            None => None,
+                                        };
+                                        trails.clear();
+                                        upload_to_gpu(&mut gpu, &display, &flock);
+                                        warn_if_clamped(&mut flock);
+                                        println!("Loaded flock from {} at step {}", args.snapshot.display(), flock.steps);
+                                    },
+                                    Err(err) => eprintln!("Failed to load flock from {}: {}", args.snapshot.display(), err),
+                                }
+                            },
+                            _ => (),
+                        }
+                    }
+                },
+
+                winit::event::WindowEvent::CursorMoved { position, .. } => {
+                    cursor = Some((position.x, position.y));
+
+                    if let Some(last) = drag_last {
+                        camera.orbit((position.x - last.0) as f32, (position.y - last.1) as f32);
+                        drag_last = Some((position.x, position.y));
+                    }
+
+                    if let Some(last) = pan_last {
+                        camera.pan((position.x - last.0) as f32, (position.y - last.1) as f32);
+                        pan_last = Some((position.x, position.y));
+                    }
+
+                    if herding {
+                        let size = window.inner_size();
+                        if let Some(point) = camera.unproject((position.x, position.y), (size.width, size.height), projection.matrix()) {
+                            flock.attractor = Some(point);
+                        }
+                    }
+                },
+
+                winit::event::WindowEvent::CursorLeft { .. } => {
+                    cursor = None;
+                },
+
+                winit::event::WindowEvent::ModifiersChanged(modifiers) => {
+                    shift_held = modifiers.state().shift_key();
+                    control_held = modifiers.state().control_key();
+                    alt_held = modifiers.state().alt_key();
+                },
+
+                winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, button: winit::event::MouseButton::Middle, .. } => {
+                    if !shift_held {
+                        pan_last = cursor;
+                    } else {
+                        herding = true;
+
+                        if let Some(cursor) = cursor {
+                            let size = window.inner_size();
+                            flock.attractor = camera.unproject(cursor, (size.width, size.height), projection.matrix());
+                        }
+                    }
+                },
+
+                winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Released, button: winit::event::MouseButton::Middle, .. } => {
+                    pan_last = None;
+                    if herding {
+                        herding = false;
+                        flock.attractor = None;
+                    }
+                },
+
+                winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, button: winit::event::MouseButton::Left, .. } => {
+                    left_press = cursor;
+                    drag_last = cursor;
+                },
+
+                winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Released, button: winit::event::MouseButton::Left, .. } => {
+                    drag_last = None;
+
+                    if let (Some(press), Some(cursor)) = (left_press.take(), cursor) {
+                        let moved = ((cursor.0 - press.0).powi(2) + (cursor.1 - press.1).powi(2)).sqrt();
+                        if moved < CLICK_TOLERANCE {
+                            let size = window.inner_size();
+                            if alt_held {
+                                if gpu.is_none() {
+                                    selected = camera.ray(cursor, (size.width, size.height), projection.matrix())
+                                        .and_then(|(origin, ray)| inspector::pick_bird(origin, ray, &flock.birds, inspector::PICK_RADIUS));
+                                }
+                            } else if let Some(point) = camera.unproject(cursor, (size.width, size.height), projection.matrix()) {
+                                if shift_held {
+                                    flock.add_waypoint(point);
+                                } else if control_held {
+                                    flock.add_attractor(point, -ATTRACTOR_STRENGTH);
+                                } else {
+                                    flock.add_pulse(point, -ATTRACTOR_STRENGTH);
+                                }
+                            }
+                        }
+                    }
+                },
+
+                winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, button: winit::event::MouseButton::Right, .. } => {
+                    if let Some(cursor) = cursor {
+                        let size = window.inner_size();
+                        if let Some(point) = camera.unproject(cursor, (size.width, size.height), projection.matrix()) {
+                            if shift_held {
+                                flock.clear_waypoints();
+                            } else if control_held {
+                                flock.add_attractor(point, ATTRACTOR_STRENGTH);
+                            } else {
+                                flock.add_pulse(point, ATTRACTOR_STRENGTH);
+                            }
+                        }
+                    }
+                },
+
+                winit::event::WindowEvent::MouseWheel { delta, .. } => {
+                    match delta {
+                        winit::event::MouseScrollDelta::LineDelta(_, y) => camera.zoom(y),
+                        winit::event::MouseScrollDelta::PixelDelta(position) => camera.zoom(position.y as f32 / 50.0),
+                    }
                 },
 
                 winit::event::WindowEvent::RedrawRequested => {
-                    let next_frame_time = std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
-                    winit::event_loop::ControlFlow::WaitUntil(next_frame_time);
+                    let now = std::time::Instant::now();
+                    let elapsed = if recorder.is_some() { std::time::Duration::from_secs_f64(1.0 / args.capture_fps) } else { now - last_frame };
+                    last_frame = now;
+                    frame_limiter.frame(now);
+
+                    #[cfg(feature = "audio")]
+                    let audio_energies = audio.as_mut().map(|audio| audio.update(elapsed));
+                    #[cfg(not(feature = "audio"))]
+                    let audio_energies: Option<modulation::BandEnergies> = None;
+                    if let Some(energies) = &audio_energies {
+                        modulation::apply(&audio_mappings, energies, &mut flock.config);
+                    }
+                    if let Some(blend) = &mut preset_blend {
+                        if blend.advance(elapsed.as_secs_f32(), &mut flock.config) {
+                            preset_blend = None;
+                        }
+                    }
+
+                    #[cfg(feature = "gamepad")]
+                    if let Some(pad) = &mut gamepad {
+                        for action in pad.poll() {
+                            match action {
+                                gamepad::PadAction::TogglePause => paused = !paused,
+                                gamepad::PadAction::ToggleDebug => debug_renderer.toggle(),
+                                gamepad::PadAction::ToggleFreeCamera => camera.toggle_free(),
+                                gamepad::PadAction::ClearAttractors => flock.clear_attractors(),
+                            }
+                        }
+                        pad.drive(&mut camera);
+                        if let Some(strength) = pad.pulse(elapsed) {
+                            flock.add_pulse(camera.focus().coords, strength * ATTRACTOR_STRENGTH);
+                        }
+                    }
+
+                    #[cfg(feature = "hot-reload")]
+                    if let Some(watcher) = &watcher {
+                        let changes = watcher.poll();
+                        if changes.config {
+                            match reload_config(&args, &mut flock) {
+                                Ok(()) => {
+                                    if gpu.is_none() && flock.config.num_birds != flock.birds.len() {
+                                        let count = flock.config.num_birds;
+                                        flock.resize(count, &mut rng);
+                                    }
+                                    println!("Reloaded {}", args.config.display());
+                                    reload_error = None;
+                                },
+                                Err(err) => {
+                                    eprintln!("error: could not reload the config: {}", err);
+                                    reload_error = Some(err);
+                                },
+                            }
+                        }
+                        if changes.shaders {
+                            let reloaded = watch::load_shaders(&args.shader_dir).and_then(|loaded| {
+                                bird_renderer.reload(&display, &loaded)?;
+                                if let Some(ground) = &mut ground_renderer {
+                                    ground.reload(&display, &loaded.ground)?;
+                                }
+                                if let Some(skybox) = &mut skybox {
+                                    skybox.reload(&display, &loaded.sky)?;
+                                }
+                                Ok(loaded)
+                            });
+                            match reloaded {
+                                Ok(loaded) => {
+                                    shaders = loaded;
+                                    println!("Reloaded shaders from {}", args.shader_dir.display());
+                                    reload_error = None;
+                                },
+                                Err(err) => {
+                                    eprintln!("error: could not reload the shaders: {}", err);
+                                    reload_error = Some(err);
+                                },
+                            }
+                        }
+                    }
+
+                    let steps = if paused {
+                        std::mem::replace(&mut step_once, false) as u32
+                    } else {
+                        timestep.advance(elapsed)
+                    };
+                    frame_timer.tick(steps);
+
+                    let step_start = std::time::Instant::now();
+                    let overlap = pipeline && steps > 0 && gpu.is_none() && replay.is_none();
+                    let stepping = if overlap {
+                        let mut next = spare.take().unwrap_or_else(|| flock.clone());
+                        next.copy_from(&flock);
+                        let dt = timestep.step().as_secs_f32() * time_scale;
+                        let (sender, receiver) = std::sync::mpsc::channel();
+                        rayon::spawn(move || {
+                            let start = std::time::Instant::now();
+                            for _ in 0..steps {
+                                bird_flock::profile_scope!("simulation");
+                                next.step(dt);
+                            }
+                            let _ = sender.send((next, start.elapsed()));
+                        });
+                        Some(receiver)
+                    } else {
+                        None
+                    };
+                    let serial_steps = if overlap { 0 } else { steps };
+                    for _ in 0..serial_steps {
+                        bird_flock::profile_scope!("simulation");
+                        let dt = timestep.step().as_secs_f32() * time_scale;
+                        if let Some(replay) = &mut replay {
+                            if replay.play(time_scale) {
+                                if let Err(err) = replay.apply(&mut flock) {
+                                    eprintln!("error: could not read replay frame {}: {}", replay.position(), err);
+                                }
+                                #[cfg(feature = "serve")]
+                                publish(&server, &flock);
+                                if trail_renderer.visible {
+                                    trails.record(&flock.birds);
+                                }
+                            }
+                            continue;
+                        }
+                        if let Some(gpu_flock) = &mut gpu {
+                            gpu_flock.step(&flock.config, dt);
+
+                            if let Some(reference) = &mut gpu_reference {
+                                reference.step(dt);
+                                validation_steps += 1;
+                                let error = gpu_flock.max_position_error(&reference.birds);
+
+                                if error > gpu::VALIDATION_TOLERANCE {
+                                    eprintln!("error: GPU validation failed after {} step(s): positions differ by {} (tolerance {}), falling back to the CPU", validation_steps, error, gpu::VALIDATION_TOLERANCE);
+                                    flock = reference.clone();
+                                    gpu = None;
+                                    gpu_reference = None;
+                                    break;
+                                } else if validation_steps >= gpu::VALIDATION_STEPS {
+                                    println!("GPU validation passed: {} steps within {} (max error {})", validation_steps, gpu::VALIDATION_TOLERANCE, error);
+                                    gpu_reference = None;
+                                }
+                            }
+                            continue;
+                        }
 
-                    flock.update();
+                        flock.step(dt);
+                        if let Some(exporter) = &mut exporter {
+                            exporter.record(&flock.birds);
+                        }
+                        record_gltf(&mut gltf, &flock);
+                        record_metrics(&mut metrics_log, &flock);
+                        record_replay(&mut replay_writer, &flock);
+                        #[cfg(feature = "serve")]
+                        publish(&server, &flock);
+                        if trail_renderer.visible {
+                            trails.record(&flock.birds);
+                        }
+                    }
+                    if gpu.is_some() && steps > 0 {
+                        display.finish();
+                    }
+                    let mut step_ms = if steps > 0 && !overlap { step_start.elapsed().as_secs_f32() * 1000.0 / steps as f32 } else { 0.0 };
+                    let alpha = if paused { 1.0 } else { timestep.alpha() };
 
+                    bird_flock::profile_scope!("render");
                     let mut target = display.draw();
 
-                    target.clear_color(0.0, 0.0, 0.0, 1.0);
+                    let time_of_day = flock.time_of_day();
+                    let frame_fog = Fog
+                    {
+                        colour: time_of_day.map_or(fog.colour, daynight::sky_colour),
+                        density: if flock.config.plane.is_some() { 0.0 } else { fog.density },
+                    };
+                    let [red, green, blue] = if time_of_day.is_some() || frame_fog.density > 0.0 { frame_fog.colour } else { [0.0; 3] };
+                    target.clear_color_and_depth((red, green, blue, 1.0), 1.0);
+                    let light = time_of_day.map_or(light, |time| light.at_daylight(daynight::daylight(time)));
 
-                    let perspective = Perspective3::new(1.0, std::f32::consts::FRAC_PI_3, 0.1, 100.0);
-                    let projection_matrix: [[f32; 4]; 4] = *perspective.as_matrix().as_ref();
+                    projection.set_orthographic(flock.config.plane.map(|_| camera.distance));
+                    move_keys.apply(&mut camera, shift_held);
+                    if let Some(follow) = &mut camera.follow {
+                        match flock.birds.get(follow.index) {
+                            Some(bird) => follow.update(bird.interpolated_position(alpha), bird.velocity, elapsed.as_secs_f32()),
+                            None => camera.follow = None,
+                        }
+                    }
+                    let eyes = stereo::eye_views(&camera, &projection, target.get_dimensions(), eye_separation);
 
-                    let eye = Point3::new(0.0, 0.0, 5.0); 
-                    let look = Point3::new(0.0, 0.0, 0.0);  
-                    let up = Vector3::new(0.0, 1.0, 0.0);  
-                    let view_matrix: [[f32; 4]; 4] = *Matrix4::look_at_rh(&eye, &look, &up).as_ref();
+                    if flock.config.boundary_size != boundary_size {
+                        boundary_size = flock.config.boundary_size;
+                        match glium::VertexBuffer::new(&display, &mesh::wireframe_cube(boundary_size)) {
+                            Ok(buffer) => boundary_vertex_buffer = buffer,
+                            Err(err) => eprintln!("warning: could not rebuild the boundary wireframe: {}", err),
+                        }
+                    }
 
-                    for bird in &flock.birds {
-                        let model_matrix = [
-                            [1.0, 0.0, 0.0, 0.0],
-                            [0.0, 1.0, 0.0, 0.0],
-                            [0.0, 0.0, 1.0, 0.0],
-                            [bird.position[0], bird.position[1], bird.position[2], 1.0],
-                        ];
+                    let show_debug = debug_renderer.visible && gpu.is_none();
+                    if show_debug {
+                        debug_renderer.build(&flock, alpha, flock.config.neighbour_search == NeighbourSearch::Grid);
+                    }
 
-                        let uniforms = uniform! {
-                            model: model_matrix,
-                            view: view_matrix,
-                            projection: projection_matrix,
-                        };
+                    let mut markers: Vec<(Vector3<f32>, [f32; 3])> = flock.attractors.iter().map(|attractor| {
+                        let tint = if attractor.strength > 0.0 { [0.2, 1.0, 0.2] } else { [1.0, 0.6, 0.1] };
+                        (attractor.position, tint)
+                    }).collect();
+                    if let Some(point) = flock.attractor {
+                        markers.push((point, [0.2, 0.8, 1.0]));
+                    }
+                    for (index, &waypoint) in flock.waypoints.iter().enumerate() {
+                        let tint = if index == flock.waypoint_index { [1.0, 0.3, 1.0] } else { [0.5, 0.2, 0.5] };
+                        markers.push((waypoint, tint));
+                    }
+                    for source in flock.food.iter() {
+                        let left = 0.3 + 0.7 * (source.amount / flock.config.food_amount).min(1.0);
+                        markers.push((source.position, [0.9 * left, 0.6 * left, 0.1]));
+                    }
 
-                        target.draw(&vertex_buffer, &indices, &program, &uniforms, &Default::default()).unwrap();
+                    let mut speed_sum = 0.0;
+                    let mut speed_max: f32 = 0.0;
+
+                    if gpu.is_none() {
+                        if colour_mode == ColourMode::Cluster && (clusters.len() != flock.birds.len() || frame_index.is_multiple_of(colour::CLUSTER_INTERVAL)) {
+                            clusters = flock.clusters();
+                        }
+
+                        bird_instances.clear();
+                        for (index, bird) in flock.birds.iter().enumerate() {
+                            let speed = bird.speed();
+                            speed_sum += speed;
+                            speed_max = speed_max.max(speed);
+
+                            if selected == Some(index) {
+                                bird_instances.push(BirdInstance::from_bird(bird, alpha, flock.config.max_speed, colour::SELECTED_COLOUR, SELECTED_SCALE));
+                            } else if bird.leader {
+                                bird_instances.push(BirdInstance::from_bird(bird, alpha, flock.config.max_speed, colour::LEADER_COLOUR, LEADER_SCALE));
+                            } else {
+                                let cluster = clusters.get(index).copied().unwrap_or(index);
+                                let colour = colour_mode.colour(bird, speed, cluster, flock.species_config(bird.species));
+                                bird_instances.push(BirdInstance::from_bird(bird, alpha, flock.config.max_speed, colour, 1.0));
+                            }
+                        }
+                        for predator in &flock.predators {
+                            bird_instances.push(BirdInstance::new(predator.interpolated_position(alpha), predator.velocity, Vector3::zeros(), colour::PREDATOR_COLOUR, PREDATOR_SCALE));
+                        }
+                        bird_renderer.fog = frame_fog;
                     }
 
-                    target.finish().unwrap();
+                    for eye in eyes.iter() {
+                        let view_matrix = eye.view;
+                        let projection_matrix = eye.projection;
+                        let bird_params = glium::DrawParameters { viewport: eye.viewport, .. bird_params.clone() };
+                        let depth_params = glium::DrawParameters { viewport: eye.viewport, .. depth_params.clone() };
+                        let obstacle_params = glium::DrawParameters { viewport: eye.viewport, .. obstacle_params.clone() };
+
+                        if let Some(skybox) = &skybox {
+                            if flock.config.plane.is_none() {
+                                let brightness = time_of_day.map_or(1.0, |time| 0.15 + 0.85 * daynight::daylight(time));
+                                render::log_draw_error("the skybox", skybox.draw(&mut target, view_matrix, projection_matrix, brightness, eye.viewport));
+                            }
+                        }
+                        if let Some(ground) = &ground_renderer {
+                            render::log_draw_error("the ground", ground.draw(&mut target, view_matrix, projection_matrix, &light, &frame_fog, &depth_params));
+                        }
+
+                        if show_boundary && !matches!(flock.config.boundary_shape, BoundaryShape::Unbounded | BoundaryShape::Soft { .. }) {
+                            let scale = match flock.config.boundary_shape {
+                                BoundaryShape::Sphere { radius } => radius,
+                                _ => 1.0,
+                            };
+                            let uniforms = uniform! {
+                                model: [
+                                    [scale, 0.0, 0.0, 0.0],
+                                    [0.0, scale, 0.0, 0.0],
+                                    [0.0, 0.0, scale, 0.0],
+                                    [0.0, 0.0, 0.0, 1.0f32],
+                                ],
+                                view: view_matrix,
+                                projection: projection_matrix,
+                                tint: [0.5f32, 0.5, 0.55],
+                            };
+
+                            match flock.config.boundary_shape {
+                                BoundaryShape::Sphere { .. } => {
+                                    render::log_draw_error("the boundary", target.draw(&sphere_vertex_buffer, &sphere_index_buffer, &obstacle_program, &uniforms, &obstacle_params));
+                                },
+                                _ => {
+                                    let lines = glium::index::NoIndices(glium::index::PrimitiveType::LinesList);
+                                    render::log_draw_error("the boundary", target.draw(&boundary_vertex_buffer, lines, &obstacle_program, &uniforms, &depth_params));
+                                },
+                            }
+                        }
+
+                        for obstacle in &flock.obstacles {
+                            let scale = match obstacle.shape {
+                                ObstacleShape::Sphere { radius } => Vector3::new(radius, radius, radius),
+                                ObstacleShape::Box { half_extents } => half_extents,
+                            };
+                            let model_matrix = [
+                                [scale.x, 0.0, 0.0, 0.0],
+                                [0.0, scale.y, 0.0, 0.0],
+                                [0.0, 0.0, scale.z, 0.0],
+                                [obstacle.center[0], obstacle.center[1], obstacle.center[2], 1.0],
+                            ];
+
+                            let uniforms = uniform! {
+                                model: model_matrix,
+                                view: view_matrix,
+                                projection: projection_matrix,
+                                tint: [0.4f32, 0.4, 0.4],
+                            };
+
+                            let drawn = match obstacle.shape {
+                                ObstacleShape::Sphere { .. } => target.draw(&sphere_vertex_buffer, &sphere_index_buffer, &obstacle_program, &uniforms, &obstacle_params),
+                                ObstacleShape::Box { .. } => {
+                                    let lines = glium::index::NoIndices(glium::index::PrimitiveType::LinesList);
+                                    target.draw(&box_vertex_buffer, lines, &obstacle_program, &uniforms, &depth_params)
+                                },
+                            };
+                            render::log_draw_error("an obstacle", drawn);
+                        }
+
+                        render::log_draw_error("trails", trail_renderer.draw(&display, &mut target, &trails, view_matrix, projection_matrix, eye.viewport));
+
+                        if show_debug {
+                            render::log_draw_error("the debug overlay", debug_renderer.draw(&display, &mut target, view_matrix, projection_matrix, eye.viewport));
+
+                            let shown = selected.or_else(|| camera.follow.as_ref().map(|follow| follow.index)).unwrap_or(0);
+                            if let Some(bird) = flock.birds.get(shown) {
+                                let r = flock.species_config(bird.species).neighbour_radius;
+                                let position = bird.interpolated_position(alpha);
+                                let uniforms = uniform! {
+                                    model: [
+                                        [r, 0.0, 0.0, 0.0],
+                                        [0.0, r, 0.0, 0.0],
+                                        [0.0, 0.0, r, 0.0],
+                                        [position.x, position.y, position.z, 1.0],
+                                    ],
+                                    view: view_matrix,
+                                    projection: projection_matrix,
+                                    tint: [1.0f32, 1.0, 0.3],
+                                };
+                                render::log_draw_error("the neighbour radius", target.draw(&sphere_vertex_buffer, &sphere_index_buffer, &obstacle_program, &uniforms, &obstacle_params));
+                            }
+                        }
+
+                        for &(position, tint) in markers.iter() {
+                            let r = 0.08;
+                            let model_matrix = [
+                                [r, 0.0, 0.0, 0.0],
+                                [0.0, r, 0.0, 0.0],
+                                [0.0, 0.0, r, 0.0],
+                                [position[0], position[1], position[2], 1.0],
+                            ];
+
+                            let uniforms = uniform! {
+                                model: model_matrix,
+                                view: view_matrix,
+                                projection: projection_matrix,
+                                tint: tint,
+                            };
+
+                            render::log_draw_error("a marker", target.draw(&sphere_vertex_buffer, &sphere_index_buffer, &obstacle_program, &uniforms, &obstacle_params));
+                        }
+
+                        let uniforms = FrameUniforms { flap_time: alpha * timestep.step().as_secs_f32(), ..light.frame_uniforms(view_matrix, projection_matrix) };
+                        if let Some(gpu) = &gpu {
+                            render::log_draw_error("GPU birds", gpu.draw(&mut target, &vertex_buffer, &uniforms, colour::SOLID_COLOUR, &frame_fog, &bird_params));
+                        } else {
+                            bird_renderer.viewport = eye.viewport;
+                            render::log_draw_error("birds", bird_renderer.draw_birds(&mut target, &bird_instances, &uniforms));
+                        }
+                    }
+
+                    if let Some(receiver) = stepping {
+                        match receiver.recv() {
+                            Ok((next, elapsed)) => {
+                                spare = Some(std::mem::replace(&mut flock, next));
+                                step_ms = elapsed.as_secs_f32() * 1000.0 / steps as f32;
+                                if trail_renderer.visible {
+                                    trails.record(&flock.birds);
+                                }
+                            },
+                            Err(_) => eprintln!("warning: the simulation thread stopped without returning the flock"),
+                        }
+                    }
+
+                    if gpu.is_some() || !hud.visible {
+                        flock_metrics = None;
+                    } else if flock_metrics.is_none() || frame_index.is_multiple_of(hud::METRICS_INTERVAL) {
+                        flock_metrics = Some(flock.metrics());
+                    }
+                    frame_index = frame_index.wrapping_add(1);
+
+                    let bird_count = flock.birds.len();
+                    let stats = Stats
+                    {
+                        fps: frame_timer.fps(),
+                        steps_per_second: frame_timer.steps_per_second(),
+                        step_ms,
+                        bird_count,
+                        avg_speed: if bird_count > 0 { speed_sum / bird_count as f32 } else { 0.0 },
+                        max_speed: speed_max,
+                        wind: flock.config.wind.into(),
+                        param_name: selected_param.name(),
+                        param_value: selected_param.get(&flock.config),
+                        time_scale,
+                        paused,
+                        metrics: flock_metrics,
+                        replay: replay.as_ref().map(|replay| (replay.position(), replay.len())),
+                        time_of_day,
+                        audio: audio_energies,
+                        reload_error: reload_error.clone(),
+                    };
+                    render::log_draw_error("the HUD", hud.draw(&mut target, &stats));
+
+                    let inspected = selected.filter(|_| gpu.is_none()).and_then(|index| BirdReport::new(&flock, index));
+                    if inspected.is_none() {
+                        selected = None;
+                    }
+
+                    if show_tuning || show_help || inspected.is_some() {
+                        let max_birds = if gpu.is_none() { Some(MAX_INTERACTIVE_BIRDS) } else { None };
+                        let was_following = inspected.as_ref().is_some_and(|report| camera.follow.as_ref().map(|follow| follow.index) == Some(report.index));
+                        let mut following = was_following;
+                        let mut inspector_open = true;
+                        egui_glium.run(&window, |ctx| {
+                            if show_tuning {
+                                tuning::tuning_panel(ctx, &mut flock, max_birds);
+                            }
+                            if show_help {
+                                show_help = help::help_panel(ctx);
+                            }
+                            if let Some(report) = &inspected {
+                                inspector_open = inspector::inspector_panel(ctx, report, &mut following);
+                            }
+                        });
+                        if let Some(report) = &inspected {
+                            if following && !was_following {
+                                camera.follow(report.index);
+                            } else if !following && was_following {
+                                camera.follow = None;
+                            }
+                            if !inspector_open {
+                                selected = None;
+                            }
+                        }
+                        if gpu.is_none() && flock.config.num_birds != flock.birds.len() {
+                            let count = flock.config.num_birds;
+                            flock.resize(count, &mut rng);
+                        }
+                        egui_glium.paint(&display, &mut target);
+                    }
+
+                    if let Err(err) = target.finish() {
+                        eprintln!("warning: could not present the frame: {}", err);
+                    }
+
+                    if screenshot_requested {
+                        let path = capture::screenshot_path();
+                        let saved = display.read_front_buffer::<glium::texture::RawImage2d<'_, u8>>()
+                            .map_err(|err| format!("{:?}", err))
+                            .and_then(|raw| capture::save_png(raw, &path));
+
+                        match saved {
+                            Ok(()) => println!("Saved screenshot to {}", path.display()),
+                            Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+                        }
+                        screenshot_requested = false;
+                    }
+
+                    if let Some(active) = &mut recorder {
+                        active.capture(|| display.read_front_buffer().ok());
+                    }
                 },
                 _ => (),
             },                
+            winit::event::Event::DeviceEvent { event: winit::event::DeviceEvent::MouseMotion { delta }, .. } if camera.free => {
+                camera.turn(delta.0 as f32, delta.1 as f32);
+            },
             winit::event::Event::AboutToWait => {
-                window.request_redraw();
+                match frame_limiter.wait(std::time::Instant::now()) {
+                    Some(deadline) => window_target.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(deadline)),
+                    None => {
+                        window_target.set_control_flow(winit::event_loop::ControlFlow::Wait);
+                        window.request_redraw();
+                    },
+                }
             },
             _ => (),
         };
-    });
+    })?;
+
+    Ok(())
 }
@@ -0,0 +1,132 @@
+use bird_flock::draw;
+use std::f32::consts::PI;
+
+#[derive(Copy, Clone)]
+pub struct Vertex3
+{
+    pub position: [f32; 3],
+}
+
+implement_vertex!(Vertex3, position);
+
+#[derive(Copy, Clone)]
+pub struct LitVertex
+{
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+implement_vertex!(LitVertex, position, normal);
+
+pub fn bird_mesh() -> Vec<LitVertex>
+{
+    draw::bird_mesh().iter().map(|vertex| LitVertex { position: vertex.position, normal: vertex.normal }).collect()
+}
+
+pub fn bird_silhouette() -> Vec<LitVertex>
+{
+    bird_mesh().into_iter().take(3).collect()
+}
+
+pub fn uv_sphere(stacks: u16, slices: u16) -> (Vec<Vertex3>, Vec<u16>)
+{
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for i in 0..=stacks
+    {
+        let phi = PI * i as f32 / stacks as f32;
+        let y = phi.cos();
+        let ring = phi.sin();
+
+        for j in 0..=slices
+        {
+            let theta = 2.0 * PI * j as f32 / slices as f32;
+            vertices.push(Vertex3 { position: [ring * theta.cos(), y, ring * theta.sin()] });
+        }
+    }
+
+    for i in 0..stacks
+    {
+        for j in 0..slices
+        {
+            let a = i * (slices + 1) + j;
+            let b = a + slices + 1;
+            indices.extend_from_slice(&[a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+pub fn wireframe_cube(size: f32) -> Vec<Vertex3>
+{
+    let h = size / 2.0;
+    let corner = |i: usize| Vertex3 {
+        position: [
+            if i & 1 != 0 { h } else { -h },
+            if i & 2 != 0 { h } else { -h },
+            if i & 4 != 0 { h } else { -h },
+        ],
+    };
+
+    let mut vertices = Vec::with_capacity(24);
+    for i in 0..8 {
+        for axis in 0..3 {
+            let bit = 1 << axis;
+            if i & bit == 0 {
+                vertices.push(corner(i));
+                vertices.push(corner(i | bit));
+            }
+        }
+    }
+
+    vertices
+}
+
+pub fn skybox_cube() -> Vec<Vertex3>
+{
+    let mut vertices = Vec::with_capacity(36);
+    for axis in 0..3 {
+        for &side in [-1.0f32, 1.0].iter() {
+            let corner = |a: f32, b: f32| {
+                let mut position = [0.0; 3];
+                position[axis] = side;
+                position[(axis + 1) % 3] = a;
+                position[(axis + 2) % 3] = b;
+                Vertex3 { position }
+            };
+            vertices.extend_from_slice(&[corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)]);
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn wireframe_cube_has_twelve_axis_aligned_edges()
+    {
+        let vertices = wireframe_cube(4.0);
+        assert_eq!(vertices.len(), 24);
+        assert!(vertices.iter().all(|v| v.position.iter().all(|&c| c == 2.0 || c == -2.0)));
+
+        let mut edges = Vec::new();
+        for pair in vertices.chunks(2) {
+            let (a, b) = (pair[0].position, pair[1].position);
+            let differing: Vec<usize> = (0..3).filter(|&axis| a[axis] != b[axis]).collect();
+            assert_eq!(differing.len(), 1, "{:?} - {:?} is not an edge", a, b);
+            let (low, high) = if a < b { (a, b) } else { (b, a) };
+            assert!(!edges.contains(&(low, high)), "edge {:?} - {:?} repeated", low, high);
+            edges.push((low, high));
+        }
+
+        for vertex in vertices.iter() {
+            assert_eq!(vertices.iter().filter(|other| other.position == vertex.position).count(), 3);
+        }
+    }
+}
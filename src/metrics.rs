@@ -0,0 +1,188 @@
+use nalgebra::Vector3;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::flock::{Bird, Flock};
+use crate::grid::SpatialGrid;
+
+pub const CSV_HEADER: &str = "step,com_x,com_y,com_z,avg_speed,avg_nearest,order,dispersion,groups";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlockMetrics
+{
+    pub center_of_mass: Vector3<f32>,
+    pub average_speed: f32,
+    pub average_nearest_distance: f32,
+    pub order: f32,
+    pub dispersion: f32,
+    pub groups: usize,
+}
+
+impl FlockMetrics
+{
+    pub fn measure(flock: &Flock) -> FlockMetrics
+    {
+        let center_of_mass = flock.centroid().unwrap_or_else(Vector3::zeros);
+
+        FlockMetrics
+        {
+            center_of_mass,
+            average_speed: flock.average_speed(),
+            average_nearest_distance: flock.average_nearest_distance(),
+            order: flock.order_parameter(),
+            dispersion: dispersion(&flock.birds, center_of_mass),
+            groups: group_count(&flock.birds, flock.config.neighbour_radius),
+        }
+    }
+
+    pub fn write_row<W: Write>(&self, writer: &mut W, step: u64) -> io::Result<()>
+    {
+        let centre = self.center_of_mass;
+        writeln!(
+            writer,
+            "{},{:.4},{:.4},{:.4},{:.5},{:.4},{:.4},{:.4},{}",
+            step, centre.x, centre.y, centre.z, self.average_speed, self.average_nearest_distance, self.order, self.dispersion, self.groups
+        )
+    }
+}
+
+pub struct MetricsLog
+{
+    out: BufWriter<File>,
+    every: u64,
+}
+
+impl MetricsLog
+{
+    pub fn create<P: AsRef<Path>>(path: P, every: u32) -> io::Result<MetricsLog>
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", CSV_HEADER)?;
+        Ok(MetricsLog { out, every: every.max(1) as u64 })
+    }
+
+    pub fn record(&mut self, flock: &Flock) -> io::Result<()>
+    {
+        if !flock.steps.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        FlockMetrics::measure(flock).write_row(&mut self.out, flock.steps)
+    }
+
+    pub fn finish(mut self) -> io::Result<()>
+    {
+        self.out.flush()
+    }
+}
+
+pub fn dispersion(birds: &[Bird], center: Vector3<f32>) -> f32
+{
+    if birds.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f32 = birds.iter().map(|bird| (bird.position - center).norm_squared()).sum();
+    (sum / birds.len() as f32).sqrt()
+}
+
+fn root(parents: &mut [usize], mut index: usize) -> usize
+{
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+pub fn clusters(birds: &[Bird], radius: f32) -> Vec<usize>
+{
+    let grid = SpatialGrid::build(birds.iter().map(|bird| bird.position).collect(), radius);
+    let mut parents: Vec<usize> = (0..birds.len()).collect();
+    let mut found = Vec::new();
+
+    for (index, bird) in birds.iter().enumerate() {
+        grid.query_radius_into(bird.position, radius, &mut found);
+        for &other in found.iter() {
+            let (a, b) = (root(&mut parents, index), root(&mut parents, other));
+            if a != b {
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    (0..birds.len()).map(|index| root(&mut parents, index)).collect()
+}
+
+pub fn group_count(birds: &[Bird], radius: f32) -> usize
+{
+    clusters(birds, radius).iter().enumerate().filter(|&(index, &cluster)| index == cluster).count()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::flock::FlockConfig;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn flock_of(birds: &[(Vector3<f32>, Vector3<f32>)]) -> Flock
+    {
+        let config = FlockConfig { num_birds: 0, ..FlockConfig::default() };
+        let mut flock = Flock::with_seed(config.clone(), 1);
+        let mut rng = StdRng::seed_from_u64(1);
+        for &(position, velocity) in birds.iter() {
+            let mut bird = Bird::with_rng(&config, &mut rng);
+            bird.position = position;
+            bird.velocity = velocity;
+            flock.spawn(bird);
+        }
+        flock
+    }
+
+    #[test]
+    fn aligned_line_of_birds()
+    {
+        let birds: Vec<_> = (0..10).map(|i| (Vector3::new(i as f32 * 2.0, 0.0, 0.0), Vector3::new(0.0, 0.005 * (1 + i % 2) as f32, 0.0))).collect();
+        let metrics = flock_of(&birds).metrics();
+
+        assert!((metrics.order - 1.0).abs() < 1e-6);
+        assert!((metrics.center_of_mass - Vector3::new(9.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!((metrics.average_speed - 0.0075).abs() < 1e-6);
+        assert!((metrics.average_nearest_distance - 2.0).abs() < 1e-5);
+        assert_eq!(metrics.groups, 10);
+    }
+
+    #[test]
+    fn random_headings_have_low_order()
+    {
+        let mut rng = StdRng::seed_from_u64(8);
+        let birds: Vec<_> = (0..500).map(|_| {
+            let heading = Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            (Vector3::new(rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)), heading * 0.01)
+        }).collect();
+
+        assert!(flock_of(&birds).metrics().order < 0.2);
+    }
+
+    #[test]
+    fn opposite_headings_cancel()
+    {
+        let birds = [(Vector3::zeros(), Vector3::new(0.01, 0.0, 0.0)), (Vector3::new(0.0, 0.5, 0.0), Vector3::new(-0.01, 0.0, 0.0))];
+        assert!(flock_of(&birds).metrics().order < 1e-6);
+    }
+
+    #[test]
+    fn separate_clusters_are_counted()
+    {
+        let birds: Vec<_> = [-10.0, 10.0].iter().flat_map(|&x| {
+            (0..5).map(move |i| (Vector3::new(x, i as f32 * 0.2, 0.0), Vector3::zeros()))
+        }).collect();
+        let flock = flock_of(&birds);
+
+        assert_eq!(group_count(&flock.birds, 0.5), 2);
+        assert!((dispersion(&flock.birds, flock.centroid().unwrap()) - 10.0).abs() < 0.1);
+        assert_eq!(flock_of(&[]).metrics().groups, 0);
+    }
+}
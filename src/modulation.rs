@@ -0,0 +1,209 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+use crate::flock::{normalize_or_zero, FlockConfig, MAX_WIND};
+use crate::params::SimParam;
+
+pub const BASS_CUTOFF: f32 = 200.0;
+pub const TREBLE_CUTOFF: f32 = 2000.0;
+pub const ATTACK: f32 = 0.5;
+pub const RELEASE: f32 = 0.05;
+pub const PEAK_DECAY: f32 = 0.999;
+pub const MIN_PEAK: f32 = 1e-3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Band
+{
+    Bass,
+    Mid,
+    Treble,
+    Level,
+}
+
+impl FromStr for Band
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Band, String>
+    {
+        match s {
+            "bass" => Ok(Band::Bass),
+            "mid" => Ok(Band::Mid),
+            "treble" => Ok(Band::Treble),
+            "level" => Ok(Band::Level),
+            _ => Err(format!("unknown band '{}', expected bass, mid, treble or level", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModulationTarget
+{
+    SeparationWeight,
+    AlignmentWeight,
+    CohesionWeight,
+    NeighbourRadius,
+    MaxSpeed,
+    ViewAngle,
+    Wind,
+}
+
+impl ModulationTarget
+{
+    pub fn param(self) -> Option<SimParam>
+    {
+        match self {
+            ModulationTarget::SeparationWeight => Some(SimParam::SeparationWeight),
+            ModulationTarget::AlignmentWeight => Some(SimParam::AlignmentWeight),
+            ModulationTarget::CohesionWeight => Some(SimParam::CohesionWeight),
+            ModulationTarget::NeighbourRadius => Some(SimParam::NeighbourRadius),
+            ModulationTarget::MaxSpeed => Some(SimParam::MaxSpeed),
+            ModulationTarget::ViewAngle => Some(SimParam::ViewAngle),
+            ModulationTarget::Wind => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mapping
+{
+    pub band: Band,
+    pub target: ModulationTarget,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Mapping
+{
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if !self.min.is_finite() || !self.max.is_finite() {
+            return Err(format!("audio mapping range must be finite, got {} to {}", self.min, self.max));
+        }
+        Ok(())
+    }
+
+    pub fn value(&self, energies: &BandEnergies) -> f32
+    {
+        self.min + (self.max - self.min) * energies.get(self.band)
+    }
+}
+
+pub fn default_mappings() -> Vec<Mapping>
+{
+    vec![
+        Mapping { band: Band::Bass, target: ModulationTarget::CohesionWeight, min: 0.0005, max: 0.003 },
+        Mapping { band: Band::Treble, target: ModulationTarget::MaxSpeed, min: 0.015, max: 0.04 },
+        Mapping { band: Band::Level, target: ModulationTarget::Wind, min: 0.0, max: MAX_WIND },
+    ]
+}
+
+pub fn apply(mappings: &[Mapping], energies: &BandEnergies, config: &mut FlockConfig)
+{
+    for mapping in mappings {
+        let value = mapping.value(energies);
+        match mapping.target.param() {
+            Some(param) => param.set(config, value),
+            None => {
+                let mut direction = normalize_or_zero(config.wind);
+                if direction == Vector3::zeros() {
+                    direction = Vector3::x();
+                }
+                config.wind = direction * value.clamp(0.0, MAX_WIND);
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BandEnergies
+{
+    pub bass: f32,
+    pub mid: f32,
+    pub treble: f32,
+    pub level: f32,
+}
+
+impl BandEnergies
+{
+    pub fn get(&self, band: Band) -> f32
+    {
+        match band {
+            Band::Bass => self.bass,
+            Band::Mid => self.mid,
+            Band::Treble => self.treble,
+            Band::Level => self.level,
+        }
+    }
+}
+
+fn low_pass_coefficient(cutoff: f32, sample_rate: f32) -> f32
+{
+    1.0 - (-TAU * cutoff / sample_rate).exp()
+}
+
+#[derive(Clone, Debug)]
+pub struct BandAnalyser
+{
+    bass_coefficient: f32,
+    treble_coefficient: f32,
+    bass_filter: f32,
+    treble_filter: f32,
+    raw: [f32; 4],
+    peaks: [f32; 4],
+    energies: BandEnergies,
+}
+
+impl BandAnalyser
+{
+    pub fn new(sample_rate: u32) -> BandAnalyser
+    {
+        let sample_rate = sample_rate.max(1) as f32;
+        BandAnalyser
+        {
+            bass_coefficient: low_pass_coefficient(BASS_CUTOFF, sample_rate),
+            treble_coefficient: low_pass_coefficient(TREBLE_CUTOFF, sample_rate),
+            bass_filter: 0.0,
+            treble_filter: 0.0,
+            raw: [0.0; 4],
+            peaks: [MIN_PEAK; 4],
+            energies: BandEnergies::default(),
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32])
+    {
+        if samples.is_empty() {
+            return;
+        }
+
+        let mut sums = [0.0; 4];
+        for &sample in samples {
+            self.bass_filter += (sample - self.bass_filter) * self.bass_coefficient;
+            self.treble_filter += (sample - self.treble_filter) * self.treble_coefficient;
+            let bands = [self.bass_filter, self.treble_filter - self.bass_filter, sample - self.treble_filter, sample];
+            for (sum, band) in sums.iter_mut().zip(bands.iter()) {
+                *sum += band * band;
+            }
+        }
+
+        let mut levels = [0.0; 4];
+        for index in 0..4 {
+            let rms = (sums[index] / samples.len() as f32).sqrt();
+            let rate = if rms > self.raw[index] { ATTACK } else { RELEASE };
+            self.raw[index] += (rms - self.raw[index]) * rate;
+            self.peaks[index] = (self.peaks[index] * PEAK_DECAY).max(self.raw[index]).max(MIN_PEAK);
+            levels[index] = (self.raw[index] / self.peaks[index]).min(1.0);
+        }
+        self.energies = BandEnergies { bass: levels[0], mid: levels[1], treble: levels[2], level: levels[3] };
+    }
+
+    pub fn energies(&self) -> BandEnergies
+    {
+        self.energies
+    }
+}
@@ -0,0 +1,376 @@
+use nalgebra::Vector3;
+
+pub const OCTREE_CAPACITY: usize = 16;
+const MAX_DEPTH: u32 = 10;
+const BOUNDS_SLACK: f32 = 1e-4;
+const STACK_SIZE: usize = 8 * (MAX_DEPTH as usize + 1);
+
+struct Node
+{
+    center: Vector3<f32>,
+    half_size: f32,
+    first_child: usize,
+    indices: Vec<usize>,
+}
+
+impl Node
+{
+    fn is_leaf(&self) -> bool
+    {
+        self.first_child == 0
+    }
+
+    fn octant(&self, point: Vector3<f32>) -> usize
+    {
+        let mut octant = 0;
+        for i in 0..3 {
+            if point[i] >= self.center[i] {
+                octant |= 1 << i;
+            }
+        }
+        octant
+    }
+
+    fn distance_squared_to(&self, point: Vector3<f32>) -> f32
+    {
+        let mut total = 0.0;
+        for i in 0..3 {
+            let outside = (point[i] - self.center[i]).abs() - self.half_size * (1.0 + BOUNDS_SLACK);
+            if outside > 0.0 {
+                total += outside * outside;
+            }
+        }
+        total
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aggregate
+{
+    pub count: u32,
+    pub position_sum: Vector3<f32>,
+    pub velocity_sum: Vector3<f32>,
+}
+
+impl Aggregate
+{
+    pub fn centroid(&self) -> Vector3<f32>
+    {
+        if self.count == 0 {
+            Vector3::zeros()
+        } else {
+            self.position_sum / self.count as f32
+        }
+    }
+
+    fn merge(&mut self, other: &Aggregate)
+    {
+        self.count += other.count;
+        self.position_sum += other.position_sum;
+        self.velocity_sum += other.velocity_sum;
+    }
+}
+
+struct NodeStack
+{
+    nodes: [usize; STACK_SIZE],
+    len: usize,
+}
+
+impl NodeStack
+{
+    fn root() -> NodeStack
+    {
+        NodeStack { nodes: [0; STACK_SIZE], len: 1 }
+    }
+
+    fn pop(&mut self) -> Option<usize>
+    {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.nodes[self.len])
+    }
+
+    fn push_children(&mut self, first_child: usize)
+    {
+        for octant in 0..8 {
+            self.nodes[self.len + octant] = first_child + octant;
+        }
+        self.len += 8;
+    }
+
+    fn raise_child(&mut self, octant: usize)
+    {
+        self.nodes.swap(self.len - 8 + octant, self.len - 1);
+    }
+}
+
+#[derive(Default)]
+pub struct Octree
+{
+    nodes: Vec<Node>,
+    positions: Vec<Vector3<f32>>,
+    capacity: usize,
+    aggregates: Vec<Aggregate>,
+    groups: usize,
+    members: Vec<Option<(usize, Vector3<f32>)>>,
+    spare: Vec<Vec<usize>>,
+}
+
+impl Octree
+{
+    pub fn build(positions: Vec<Vector3<f32>>, half_size: f32, capacity: usize) -> Octree
+    {
+        let mut tree = Octree::default();
+        tree.rebuild(positions, half_size, capacity);
+        tree
+    }
+
+    /// Rebuilds the tree in place, recycling the leaf index vectors of the last build.
+    pub fn rebuild<I: IntoIterator<Item = Vector3<f32>>>(&mut self, positions: I, half_size: f32, capacity: usize)
+    {
+        self.positions.clear();
+        self.positions.extend(positions);
+        let mut extent = half_size;
+        for position in self.positions.iter() {
+            for i in 0..3 {
+                extent = extent.max(position[i].abs());
+            }
+        }
+
+        for mut node in self.nodes.drain(..) {
+            if node.indices.capacity() > 0 {
+                node.indices.clear();
+                self.spare.push(node.indices);
+            }
+        }
+        self.capacity = capacity.max(1);
+        self.aggregates.clear();
+        self.members.clear();
+        let root = self.node(Vector3::zeros(), extent);
+        self.nodes.push(root);
+        for index in 0..self.positions.len() {
+            self.insert(0, index, 0);
+        }
+    }
+
+    fn node(&mut self, center: Vector3<f32>, half_size: f32) -> Node
+    {
+        Node { center, half_size, first_child: 0, indices: self.spare.pop().unwrap_or_default() }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.positions.is_empty()
+    }
+
+    fn insert(&mut self, node: usize, index: usize, depth: u32)
+    {
+        let position = self.positions[index];
+
+        if !self.nodes[node].is_leaf() {
+            let child = self.nodes[node].first_child + self.nodes[node].octant(position);
+            self.insert(child, index, depth + 1);
+            return;
+        }
+
+        self.nodes[node].indices.push(index);
+        if self.nodes[node].indices.len() > self.capacity && depth < MAX_DEPTH {
+            self.subdivide(node);
+            let mut indices = std::mem::take(&mut self.nodes[node].indices);
+            for &index in indices.iter() {
+                let child = self.nodes[node].first_child + self.nodes[node].octant(self.positions[index]);
+                self.insert(child, index, depth + 1);
+            }
+            indices.clear();
+            self.spare.push(indices);
+        }
+    }
+
+    fn subdivide(&mut self, node: usize)
+    {
+        let center = self.nodes[node].center;
+        let quarter = self.nodes[node].half_size / 2.0;
+        let first_child = self.nodes.len();
+
+        for octant in 0..8 {
+            let mut child_center = center;
+            for i in 0..3 {
+                child_center[i] += if octant & (1 << i) != 0 { quarter } else { -quarter };
+            }
+            let child = self.node(child_center, quarter);
+            self.nodes.push(child);
+        }
+
+        self.nodes[node].first_child = first_child;
+    }
+
+    pub fn query_radius(&self, point: Vector3<f32>, radius: f32) -> Vec<usize>
+    {
+        let mut found = Vec::new();
+        self.query_radius_into(point, radius, &mut found);
+        found
+    }
+
+    pub fn query_radius_into(&self, point: Vector3<f32>, radius: f32, found: &mut Vec<usize>)
+    {
+        found.clear();
+        let mut stack = NodeStack::root();
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.distance_squared_to(point) >= radius * radius {
+                continue;
+            }
+
+            if node.is_leaf() {
+                for &index in node.indices.iter() {
+                    if (point - self.positions[index]).norm() < radius {
+                        found.push(index);
+                    }
+                }
+            } else {
+                stack.push_children(node.first_child);
+            }
+        }
+
+        found.sort_unstable();
+    }
+
+    /// Finds the point nearest to `point` other than `skip`, with its distance.
+    pub fn nearest(&self, point: Vector3<f32>, skip: usize) -> Option<(usize, f32)>
+    {
+        let mut best: Option<(usize, f32)> = None;
+        let mut stack = NodeStack::root();
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if best.is_some_and(|(_, best)| node.distance_squared_to(point) >= best) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                for &index in node.indices.iter() {
+                    let dist_squared = (point - self.positions[index]).norm_squared();
+                    if index != skip && best.is_none_or(|(_, best)| dist_squared < best) {
+                        best = Some((index, dist_squared));
+                    }
+                }
+            } else {
+                stack.push_children(node.first_child);
+                stack.raise_child(node.octant(point));
+            }
+        }
+
+        best.map(|(index, dist_squared)| (index, dist_squared.sqrt()))
+    }
+
+    /// Sums each cell's birds per group. `member` returns a bird's group, below
+    /// `groups`, and velocity, or `None` to leave the bird out of every aggregate.
+    pub fn aggregate<F: Fn(usize) -> Option<(usize, Vector3<f32>)>>(&mut self, groups: usize, member: F)
+    {
+        let groups = groups.max(1);
+        self.groups = groups;
+        self.members.clear();
+        self.members.extend((0..self.positions.len()).map(|index| member(index).filter(|&(group, _)| group < groups)));
+        self.aggregates.clear();
+        self.aggregates.resize(self.nodes.len() * self.groups, Aggregate::default());
+
+        for node in (0..self.nodes.len()).rev() {
+            let slot = node * self.groups;
+            if self.nodes[node].is_leaf() {
+                for &index in self.nodes[node].indices.iter() {
+                    if let Some((group, velocity)) = self.members[index] {
+                        let total = &mut self.aggregates[slot + group];
+                        total.count += 1;
+                        total.position_sum += self.positions[index];
+                        total.velocity_sum += velocity;
+                    }
+                }
+            } else {
+                let first_child = self.nodes[node].first_child;
+                for child in first_child..first_child + 8 {
+                    for group in 0..self.groups {
+                        let aggregate = self.aggregates[child * self.groups + group];
+                        self.aggregates[slot + group].merge(&aggregate);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects the far field of `group` around `point`: every member at least
+    /// `radius` away, as whole cells once a cell lies outside the radius and its
+    /// width is under `theta` times the distance to its centroid, otherwise one
+    /// bird at a time. Members inside the radius are left to an exact query.
+    pub fn query_far_field(&self, point: Vector3<f32>, radius: f32, theta: f32, group: usize, groups: &mut Vec<Aggregate>)
+    {
+        groups.clear();
+        if group >= self.groups || self.aggregates.is_empty() {
+            return;
+        }
+        let mut stack = NodeStack::root();
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let aggregate = &self.aggregates[index * self.groups + group];
+            if aggregate.count == 0 {
+                continue;
+            }
+
+            if node.distance_squared_to(point) >= radius * radius && node.half_size * 2.0 < theta * (aggregate.centroid() - point).norm() {
+                groups.push(*aggregate);
+            } else if node.is_leaf() {
+                for &other in node.indices.iter() {
+                    match self.members[other] {
+                        Some((member, velocity)) if member == group && (self.positions[other] - point).norm() >= radius => {
+                            groups.push(Aggregate { count: 1, position_sum: self.positions[other], velocity_sum: velocity });
+                        },
+                        _ => (),
+                    }
+                }
+            } else {
+                stack.push_children(node.first_child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn nearest_matches_a_linear_scan()
+    {
+        let mut rng = StdRng::seed_from_u64(7);
+        let positions: Vec<Vector3<f32>> = (0..500).map(|_| Vector3::new(rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0))).collect();
+        let tree = Octree::build(positions.clone(), 50.0, OCTREE_CAPACITY);
+
+        for (index, &point) in positions.iter().enumerate() {
+            let expected = positions.iter().enumerate()
+                .filter(|&(other, _)| other != index)
+                .map(|(_, other)| (point - other).norm())
+                .fold(f32::INFINITY, f32::min);
+            let (_, dist) = tree.nearest(point, index).unwrap();
+            assert_eq!(dist, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_of_a_lone_point_is_none()
+    {
+        let tree = Octree::build(vec![Vector3::zeros()], 10.0, OCTREE_CAPACITY);
+        assert_eq!(tree.nearest(Vector3::zeros(), 0), None);
+    }
+}
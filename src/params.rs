@@ -0,0 +1,141 @@
+use crate::flock::FlockConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimParam
+{
+    SeparationWeight,
+    AlignmentWeight,
+    CohesionWeight,
+    NeighbourRadius,
+    MaxSpeed,
+    ViewAngle,
+}
+
+pub const MAX_WEIGHT: f32 = 10.0;
+pub const MAX_STEERING_WEIGHT: f32 = 0.01;
+pub const MIN_NEIGHBOUR_RADIUS: f32 = 0.05;
+pub const MIN_MAX_SPEED: f32 = 0.001;
+pub const MAX_MAX_SPEED: f32 = 0.2;
+pub const MIN_VIEW_ANGLE: f32 = 10.0;
+pub const MAX_VIEW_ANGLE: f32 = 360.0;
+
+impl SimParam
+{
+    pub const ALL: [SimParam; 6] = [
+        SimParam::SeparationWeight,
+        SimParam::AlignmentWeight,
+        SimParam::CohesionWeight,
+        SimParam::NeighbourRadius,
+        SimParam::MaxSpeed,
+        SimParam::ViewAngle,
+    ];
+
+    pub fn next(self) -> SimParam
+    {
+        match self {
+            SimParam::SeparationWeight => SimParam::AlignmentWeight,
+            SimParam::AlignmentWeight => SimParam::CohesionWeight,
+            SimParam::CohesionWeight => SimParam::NeighbourRadius,
+            SimParam::NeighbourRadius => SimParam::MaxSpeed,
+            SimParam::MaxSpeed => SimParam::ViewAngle,
+            SimParam::ViewAngle => SimParam::SeparationWeight,
+        }
+    }
+
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            SimParam::SeparationWeight => "SEPARATION",
+            SimParam::AlignmentWeight => "ALIGNMENT",
+            SimParam::CohesionWeight => "COHESION",
+            SimParam::NeighbourRadius => "RADIUS",
+            SimParam::MaxSpeed => "MAX SPEED",
+            SimParam::ViewAngle => "VIEW ANGLE",
+        }
+    }
+
+    pub fn get(self, config: &FlockConfig) -> f32
+    {
+        match self {
+            SimParam::SeparationWeight => config.separation_weight,
+            SimParam::AlignmentWeight => config.alignment_weight,
+            SimParam::CohesionWeight => config.cohesion_weight,
+            SimParam::NeighbourRadius => config.neighbour_radius,
+            SimParam::MaxSpeed => config.max_speed,
+            SimParam::ViewAngle => config.view_angle,
+        }
+    }
+
+    pub fn set(self, config: &mut FlockConfig, value: f32)
+    {
+        match self {
+            SimParam::SeparationWeight => config.separation_weight = value.clamp(0.0, MAX_WEIGHT),
+            SimParam::AlignmentWeight => config.alignment_weight = value.clamp(0.0, MAX_STEERING_WEIGHT),
+            SimParam::CohesionWeight => config.cohesion_weight = value.clamp(0.0, MAX_STEERING_WEIGHT),
+            SimParam::NeighbourRadius => config.neighbour_radius = value.max(MIN_NEIGHBOUR_RADIUS).min(config.boundary_size),
+            SimParam::MaxSpeed => config.max_speed = value.clamp(MIN_MAX_SPEED, MAX_MAX_SPEED),
+            SimParam::ViewAngle => config.view_angle = value.clamp(MIN_VIEW_ANGLE, MAX_VIEW_ANGLE),
+        }
+    }
+
+    pub fn scale(self, config: &mut FlockConfig, factor: f32)
+    {
+        self.set(config, self.get(config) * factor);
+    }
+
+    pub fn reset(self, config: &mut FlockConfig)
+    {
+        self.set(config, self.get(&FlockConfig::default()));
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn set_clamps_to_each_parameters_range()
+    {
+        let mut config = FlockConfig::default();
+
+        SimParam::CohesionWeight.set(&mut config, -1.0);
+        assert_eq!(config.cohesion_weight, 0.0);
+        SimParam::SeparationWeight.set(&mut config, 50.0);
+        assert_eq!(config.separation_weight, MAX_WEIGHT);
+        SimParam::NeighbourRadius.set(&mut config, 0.0);
+        assert_eq!(config.neighbour_radius, MIN_NEIGHBOUR_RADIUS);
+        let too_far = config.boundary_size * 2.0;
+        SimParam::NeighbourRadius.set(&mut config, too_far);
+        assert_eq!(config.neighbour_radius, config.boundary_size);
+        SimParam::MaxSpeed.set(&mut config, 1.0);
+        assert_eq!(config.max_speed, MAX_MAX_SPEED);
+        SimParam::ViewAngle.set(&mut config, 0.0);
+        assert_eq!(config.view_angle, MIN_VIEW_ANGLE);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn scale_and_reset_round_trip()
+    {
+        let mut config = FlockConfig::default();
+        for &param in SimParam::ALL.iter() {
+            let default = param.get(&config);
+            param.scale(&mut config, 1.1);
+            assert!(param.get(&config) >= default, "{} did not grow", param.name());
+            param.reset(&mut config);
+            assert_eq!(param.get(&config), default, "{} did not reset", param.name());
+        }
+    }
+
+    #[test]
+    fn next_cycles_through_every_parameter()
+    {
+        let mut param = SimParam::ALL[0];
+        for &expected in SimParam::ALL.iter() {
+            assert_eq!(param, expected);
+            param = param.next();
+        }
+        assert_eq!(param, SimParam::ALL[0]);
+    }
+}
@@ -0,0 +1,82 @@
+use nalgebra::Vector3;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::flock::{normalize_or_zero, Bird, FlockConfig};
+
+pub const FEAR_RADIUS: f32 = 1.5;
+pub const FEAR_WEIGHT: f32 = 3.0;
+pub const PREDATOR_SPEED_FACTOR: f32 = 1.2;
+pub const PREDATOR_FORCE: f32 = 0.0015;
+pub const CATCH_RADIUS: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Predator
+{
+    pub position: Vector3<f32>,
+    #[serde(default = "Vector3::zeros")]
+    pub velocity: Vector3<f32>,
+    #[serde(default = "Vector3::zeros")]
+    pub previous_position: Vector3<f32>,
+}
+
+impl Predator
+{
+    pub fn with_rng<R: Rng + ?Sized>(config: &FlockConfig, rng: &mut R) -> Predator
+    {
+        let bird = Bird::with_rng(config, rng);
+        Predator { position: bird.position, velocity: bird.velocity, previous_position: bird.position }
+    }
+
+    pub fn interpolated_position(&self, alpha: f32) -> Vector3<f32>
+    {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+
+    pub fn nearest_bird<'a>(&self, birds: &'a [Bird]) -> Option<&'a Bird>
+    {
+        birds.iter().min_by(|a, b| {
+            let da = (a.position - self.position).norm_squared();
+            let db = (b.position - self.position).norm_squared();
+            da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+
+    pub fn caught(&self, birds: &[Bird]) -> Option<usize>
+    {
+        birds.iter().position(|bird| {
+            (bird.position - self.position).norm() < CATCH_RADIUS && (bird.previous_position - self.previous_position).norm() >= CATCH_RADIUS
+        })
+    }
+
+    pub fn update(&mut self, birds: &[Bird], config: &FlockConfig, scale: f32)
+    {
+        self.previous_position = self.position;
+        let max_speed = config.max_speed * PREDATOR_SPEED_FACTOR;
+
+        let mut acceleration = config.boundary_force(self.position);
+        if let Some(prey) = self.nearest_bird(birds) {
+            let desired = normalize_or_zero(prey.position - self.position) * max_speed;
+            acceleration += (desired - self.velocity).cap_magnitude(PREDATOR_FORCE);
+        }
+
+        self.velocity = (self.velocity + acceleration * scale).cap_magnitude(max_speed);
+        self.position += self.velocity * scale;
+        if let Some(position) = config.clamp_point(self.position) {
+            self.position = position;
+        }
+    }
+}
+
+pub fn fear(bird: &Bird, predators: &[Predator]) -> Vector3<f32>
+{
+    let mut away = Vector3::zeros();
+    for predator in predators.iter() {
+        let offset = bird.position - predator.position;
+        let dist = offset.norm();
+        if dist < FEAR_RADIUS && dist > 0.0 {
+            away += offset / (dist * dist);
+        }
+    }
+    away
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::flock::FlockConfig;
+use crate::params::SimParam;
+
+pub const MAX_PRESETS: usize = 9;
+pub const BLEND_SECONDS: f32 = 2.0;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset
+{
+    pub name: String,
+    pub separation_weight: Option<f32>,
+    pub alignment_weight: Option<f32>,
+    pub cohesion_weight: Option<f32>,
+    pub neighbour_radius: Option<f32>,
+    pub max_speed: Option<f32>,
+    pub view_angle: Option<f32>,
+}
+
+impl Preset
+{
+    pub fn targets(&self) -> Vec<(SimParam, f32)>
+    {
+        let values = [
+            (SimParam::SeparationWeight, self.separation_weight),
+            (SimParam::AlignmentWeight, self.alignment_weight),
+            (SimParam::CohesionWeight, self.cohesion_weight),
+            (SimParam::NeighbourRadius, self.neighbour_radius),
+            (SimParam::MaxSpeed, self.max_speed),
+            (SimParam::ViewAngle, self.view_angle),
+        ];
+        values.iter().filter_map(|&(param, value)| value.map(|value| (param, value))).collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if self.name.is_empty() {
+            return Err("presets must have a name".to_string());
+        }
+        for (param, value) in self.targets() {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(format!("preset '{}' sets {} to {}, expected a finite value of at least 0", self.name, param.name().to_lowercase(), value));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn default_presets() -> Vec<Preset>
+{
+    vec![
+        Preset
+        {
+            name: "tight murmuration".to_string(),
+            separation_weight: Some(1.2),
+            alignment_weight: Some(0.0015),
+            cohesion_weight: Some(0.0025),
+            neighbour_radius: Some(0.8),
+            max_speed: Some(0.025),
+            view_angle: Some(300.0),
+        },
+        Preset
+        {
+            name: "loose V formation".to_string(),
+            separation_weight: Some(2.5),
+            alignment_weight: Some(0.003),
+            cohesion_weight: Some(0.0005),
+            neighbour_radius: Some(1.5),
+            max_speed: Some(0.02),
+            view_angle: Some(120.0),
+        },
+        Preset
+        {
+            name: "panic scatter".to_string(),
+            separation_weight: Some(6.0),
+            alignment_weight: Some(0.0002),
+            cohesion_weight: Some(0.0001),
+            neighbour_radius: Some(0.6),
+            max_speed: Some(0.05),
+            view_angle: Some(360.0),
+        },
+    ]
+}
+
+pub fn validate_presets(presets: &[Preset]) -> Result<(), String>
+{
+    if presets.len() > MAX_PRESETS {
+        return Err(format!("at most {} presets can be bound to keys, got {}", MAX_PRESETS, presets.len()));
+    }
+    for preset in presets {
+        preset.validate()?;
+    }
+    Ok(())
+}
+
+pub struct PresetBlend
+{
+    changes: Vec<(SimParam, f32, f32)>,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl PresetBlend
+{
+    pub fn new(preset: &Preset, config: &FlockConfig, duration: f32) -> PresetBlend
+    {
+        let changes = preset.targets().into_iter().map(|(param, target)| (param, param.get(config), target)).collect();
+        PresetBlend { changes, elapsed: 0.0, duration }
+    }
+
+    pub fn advance(&mut self, seconds: f32, config: &mut FlockConfig) -> bool
+    {
+        self.elapsed += seconds;
+        let t = if self.duration > 0.0 { (self.elapsed / self.duration).min(1.0) } else { 1.0 };
+        let eased = t * t * (3.0 - 2.0 * t);
+        for &(param, from, to) in self.changes.iter() {
+            param.set(config, from + (to - from) * eased);
+        }
+        t >= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn default_presets_apply_unclamped_and_valid()
+    {
+        let presets = default_presets();
+        assert!(validate_presets(&presets).is_ok());
+
+        for preset in presets.iter() {
+            let mut config = FlockConfig::default();
+            let mut blend = PresetBlend::new(preset, &config, BLEND_SECONDS);
+            assert!(!blend.advance(BLEND_SECONDS / 2.0, &mut config));
+            assert!(config.validate().is_ok(), "'{}' halfway: {:?}", preset.name, config.validate());
+            assert!(blend.advance(BLEND_SECONDS, &mut config));
+            assert!(config.validate().is_ok(), "'{}': {:?}", preset.name, config.validate());
+            for (param, target) in preset.targets() {
+                let value = param.get(&config);
+                assert!((value - target).abs() <= target * 1e-4, "'{}' set {} to {}, not {}", preset.name, param.name(), value, target);
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+#[cfg(feature = "profile")]
+use std::fs::File;
+#[cfg(feature = "profile")]
+use std::io;
+#[cfg(feature = "profile")]
+use std::path::Path;
+
+#[macro_export]
+macro_rules! profile_scope
+{
+    ($name:expr) => {
+        #[cfg(feature = "profile")]
+        let _profile_span = tracing::info_span!($name).entered();
+    };
+}
+
+#[cfg(feature = "profile")]
+pub fn record_to<P: AsRef<Path>>(path: P) -> io::Result<tracing_chrome::FlushGuard>
+{
+    use tracing_subscriber::prelude::*;
+
+    let file = File::create(path)?;
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().writer(file).build();
+    tracing_subscriber::registry().with(layer).init();
+    Ok(guard)
+}
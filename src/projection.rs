@@ -0,0 +1,112 @@
+use nalgebra::{Matrix4, Orthographic3, Perspective3};
+
+pub struct Projection
+{
+    fov: f32,
+    near: f32,
+    far: f32,
+    aspect: f32,
+    orthographic: Option<f32>,
+    matrix: Matrix4<f32>,
+}
+
+impl Projection
+{
+    pub fn new(width: u32, height: u32, fov: f32) -> Projection
+    {
+        let mut projection = Projection
+        {
+            fov,
+            near: 0.1,
+            far: 100.0,
+            aspect: 1.0,
+            orthographic: None,
+            matrix: Matrix4::identity(),
+        };
+        projection.resize(width, height);
+        projection.rebuild();
+        projection
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32)
+    {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let aspect = width as f32 / height as f32;
+        if aspect != self.aspect {
+            self.aspect = aspect;
+            self.rebuild();
+        }
+    }
+
+    pub fn set_orthographic(&mut self, distance: Option<f32>)
+    {
+        if distance != self.orthographic {
+            self.orthographic = distance;
+            self.rebuild();
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4<f32>
+    {
+        &self.matrix
+    }
+
+    pub fn matrix_for(&self, width: u32, height: u32) -> Matrix4<f32>
+    {
+        if width == 0 || height == 0 {
+            return self.matrix;
+        }
+        self.build(width as f32 / height as f32)
+    }
+
+    fn rebuild(&mut self)
+    {
+        self.matrix = self.build(self.aspect);
+    }
+
+    fn build(&self, aspect: f32) -> Matrix4<f32>
+    {
+        match self.orthographic {
+            Some(distance) => {
+                let half_height = distance * (self.fov / 2.0).tan();
+                let half_width = half_height * aspect;
+                *Orthographic3::new(-half_width, half_width, -half_height, half_height, self.near, self.far).as_matrix()
+            },
+            None => *Perspective3::new(aspect, self.fov, self.near, self.far).as_matrix(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::f32::consts::FRAC_PI_3;
+
+    #[test]
+    fn resize_follows_the_window_aspect()
+    {
+        let mut projection = Projection::new(800, 800, FRAC_PI_3);
+        let square = *projection.matrix();
+
+        projection.resize(1600, 800);
+        let wide = *projection.matrix();
+        assert_ne!(wide, square);
+        assert!((wide[(0, 0)] - square[(0, 0)] / 2.0).abs() < 1e-6);
+        assert_eq!(wide[(1, 1)], square[(1, 1)]);
+    }
+
+    #[test]
+    fn minimized_window_keeps_the_last_matrix()
+    {
+        let mut projection = Projection::new(1200, 600, FRAC_PI_3);
+        let before = *projection.matrix();
+
+        projection.resize(1200, 0);
+        assert_eq!(*projection.matrix(), before);
+        assert!(projection.matrix().iter().all(|v| v.is_finite()));
+    }
+}
@@ -0,0 +1,30 @@
+use glium::backend::Facade;
+use glium::Program;
+use std::fmt::Display;
+
+pub fn compile_program<F: ?Sized + Facade>(facade: &F, name: &str, vertex_shader: &str, fragment_shader: &str) -> Result<Program, String>
+{
+    Program::from_source(facade, vertex_shader, fragment_shader, None)
+        .map_err(|err| format!("could not build the {} shader program: {}", name, err))
+}
+
+pub fn depth_test(write: bool) -> glium::Depth
+{
+    glium::Depth
+    {
+        test: glium::draw_parameters::DepthTest::IfLess,
+        write,
+        .. Default::default()
+    }
+}
+
+pub fn log_draw_error<E: Display>(what: &str, result: Result<(), E>) -> bool
+{
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("warning: could not draw {}: {}", what, err);
+            false
+        },
+    }
+}
@@ -0,0 +1,301 @@
+use nalgebra::Vector3;
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::behaviour::BehaviourState;
+use crate::bird::BirdState;
+use crate::flock::{Bird, Flock, REFERENCE_DT};
+use crate::predator::Predator;
+
+pub const REPLAY_MAGIC: &[u8; 8] = b"FLOCKRPL";
+pub const REPLAY_VERSION: u32 = 2;
+pub const REPLAY_SEEK: usize = 60;
+
+const FRAME_HEADER_BYTES: u64 = 20;
+const BIRD_BYTES: u64 = 9 * 4 + 2;
+const PREDATOR_BYTES: u64 = 6 * 4;
+const LEADER_FLAG: u8 = 1;
+const PERCHED_FLAG: u8 = 2;
+
+fn write_vector<W: Write>(out: &mut W, v: Vector3<f32>) -> io::Result<()>
+{
+    for value in v.iter() {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_bytes<R: Read, const N: usize>(input: &mut R) -> io::Result<[u8; N]>
+{
+    let mut bytes = [0u8; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_vector<R: Read>(input: &mut R) -> io::Result<Vector3<f32>>
+{
+    let x = f32::from_le_bytes(read_bytes(input)?);
+    let y = f32::from_le_bytes(read_bytes(input)?);
+    let z = f32::from_le_bytes(read_bytes(input)?);
+    Ok(Vector3::new(x, y, z))
+}
+
+fn invalid(message: String) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub struct ReplayWriter
+{
+    out: BufWriter<File>,
+    every: u64,
+}
+
+impl ReplayWriter
+{
+    pub fn create<P: AsRef<Path>>(path: P, flock: &Flock, every: u32) -> io::Result<ReplayWriter>
+    {
+        let mut scene = flock.clone();
+        scene.birds.clear();
+        scene.predators.clear();
+        let header = serde_json::to_vec(&scene)?;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(REPLAY_MAGIC)?;
+        out.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        out.write_all(&(header.len() as u64).to_le_bytes())?;
+        out.write_all(&header)?;
+
+        let mut writer = ReplayWriter { out, every: every.max(1) as u64 };
+        writer.write_frame(flock)?;
+        Ok(writer)
+    }
+
+    pub fn record(&mut self, flock: &Flock) -> io::Result<()>
+    {
+        if !flock.steps.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        self.write_frame(flock)
+    }
+
+    fn write_frame(&mut self, flock: &Flock) -> io::Result<()>
+    {
+        let out = &mut self.out;
+        out.write_all(&flock.steps.to_le_bytes())?;
+        out.write_all(&flock.time.to_le_bytes())?;
+        out.write_all(&(flock.birds.len() as u32).to_le_bytes())?;
+        out.write_all(&(flock.predators.len() as u32).to_le_bytes())?;
+
+        for bird in &flock.birds {
+            write_vector(out, bird.position)?;
+            write_vector(out, bird.velocity)?;
+            write_vector(out, bird.steering)?;
+            let mut flags = 0;
+            if bird.leader {
+                flags |= LEADER_FLAG;
+            }
+            if bird.state == BirdState::Perched {
+                flags |= PERCHED_FLAG;
+            }
+            out.write_all(&[bird.species, flags])?;
+        }
+        for predator in &flock.predators {
+            write_vector(out, predator.position)?;
+            write_vector(out, predator.velocity)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()>
+    {
+        self.out.flush()
+    }
+}
+
+pub struct Replay
+{
+    input: BufReader<File>,
+    offsets: Vec<u64>,
+    scene: Flock,
+    position: usize,
+    applied: Option<usize>,
+    clock: f32,
+}
+
+impl Replay
+{
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Replay>
+    {
+        let mut input = BufReader::new(File::open(path)?);
+        if &read_bytes::<_, 8>(&mut input)? != REPLAY_MAGIC {
+            return Err(invalid("not a flock replay".to_string()));
+        }
+        let version = u32::from_le_bytes(read_bytes(&mut input)?);
+        if version != REPLAY_VERSION {
+            return Err(invalid(format!("unsupported replay version {}, expected {}", version, REPLAY_VERSION)));
+        }
+
+        let header_len = u64::from_le_bytes(read_bytes(&mut input)?);
+        let mut header = Vec::new();
+        (&mut input).take(header_len).read_to_end(&mut header)?;
+        let scene: Flock = serde_json::from_slice(&header)?;
+
+        let end = input.get_ref().metadata()?.len();
+        let mut offset = input.stream_position()?;
+        let mut offsets = Vec::new();
+        while offset + FRAME_HEADER_BYTES <= end {
+            input.seek(SeekFrom::Start(offset + 12))?;
+            let birds = u32::from_le_bytes(read_bytes(&mut input)?) as u64;
+            let predators = u32::from_le_bytes(read_bytes(&mut input)?) as u64;
+            let next = offset + FRAME_HEADER_BYTES + birds * BIRD_BYTES + predators * PREDATOR_BYTES;
+            if next > end {
+                break;
+            }
+            offsets.push(offset);
+            offset = next;
+        }
+        if offsets.is_empty() {
+            return Err(invalid("replay has no frames".to_string()));
+        }
+
+        Ok(Replay { input, offsets, scene, position: 0, applied: None, clock: 0.0 })
+    }
+
+    pub fn scene(&self) -> Flock
+    {
+        self.scene.clone()
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.offsets.is_empty()
+    }
+
+    pub fn position(&self) -> usize
+    {
+        self.position
+    }
+
+    pub fn seek(&mut self, position: usize)
+    {
+        self.position = position.min(self.len() - 1);
+        self.clock = 0.0;
+    }
+
+    pub fn seek_by(&mut self, frames: isize)
+    {
+        let position = (self.position as isize + frames).max(0) as usize;
+        self.seek(position);
+    }
+
+    pub fn play(&mut self, speed: f32) -> bool
+    {
+        self.clock += speed;
+        let frames = self.clock.floor();
+        self.clock -= frames;
+        let target = (self.position + frames as usize).min(self.len() - 1);
+        let moved = target != self.position;
+        self.position = target;
+        moved
+    }
+
+    pub fn apply(&mut self, flock: &mut Flock) -> io::Result<()>
+    {
+        let continuous = self.applied.is_some_and(|applied| applied + 1 == self.position);
+        self.input.seek(SeekFrom::Start(self.offsets[self.position]))?;
+        let input = &mut self.input;
+        let tick = u64::from_le_bytes(read_bytes(input)?);
+        let time = f32::from_le_bytes(read_bytes(input)?);
+        let bird_count = u32::from_le_bytes(read_bytes(input)?) as usize;
+        let predator_count = u32::from_le_bytes(read_bytes(input)?) as usize;
+
+        let continuous = continuous && flock.birds.len() == bird_count;
+        let mut birds = Vec::with_capacity(bird_count);
+        for index in 0..bird_count {
+            let position = read_vector(input)?;
+            let velocity = read_vector(input)?;
+            let steering = read_vector(input)?;
+            let [species, flags] = read_bytes(input)?;
+            let mut bird = Bird
+            {
+                position,
+                velocity,
+                acceleration: Vector3::zeros(),
+                neighbour_count: 0,
+                previous_position: if continuous { flock.birds[index].position } else { position },
+                species,
+                state: if flags & PERCHED_FLAG != 0 { BirdState::Perched } else { BirdState::Flying },
+                perch_timer: 0.0,
+                steering,
+                leader: flags & LEADER_FLAG != 0,
+                wing_phase: index as f32 % TAU,
+                energy: 1.0,
+                wander: Vector3::zeros(),
+                mass: 1.0,
+                speed_factor: 1.0,
+                sociability: 1.0,
+                behaviour: BehaviourState::Flocking,
+            };
+            if continuous {
+                let phase = flock.birds[index].wing_phase + bird.flap_frequency(flock.config.max_speed) * TAU * REFERENCE_DT;
+                bird.wing_phase = phase % TAU;
+            }
+            birds.push(bird);
+        }
+
+        let continuous = continuous && flock.predators.len() == predator_count;
+        let mut predators = Vec::with_capacity(predator_count);
+        for index in 0..predator_count {
+            let position = read_vector(input)?;
+            let velocity = read_vector(input)?;
+            let previous_position = if continuous { flock.predators[index].position } else { position };
+            predators.push(Predator { position, velocity, previous_position });
+        }
+
+        flock.birds = birds;
+        flock.predators = predators;
+        flock.steps = tick;
+        flock.time = time;
+        self.applied = Some(self.position);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::flock::FlockConfig;
+
+    #[test]
+    fn apply_restores_scaled_simulation_time()
+    {
+        let path = std::env::temp_dir().join(format!("flock-replay-time-{}.replay", std::process::id()));
+        let mut flock = Flock::with_seed(FlockConfig { num_birds: 20, ..FlockConfig::default() }, 3);
+        let mut writer = ReplayWriter::create(&path, &flock, 1).unwrap();
+        for _ in 0..4 {
+            flock.step(REFERENCE_DT * 0.5);
+            writer.record(&flock).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut replay = Replay::open(&path).unwrap();
+        let mut played = replay.scene();
+        replay.seek(replay.len() - 1);
+        replay.apply(&mut played).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(played.steps, 4);
+        assert_eq!(played.time, flock.time);
+        assert_eq!(played.time, 2.0);
+        assert_eq!(played.birds.iter().map(|bird| bird.position).collect::<Vec<_>>(), flock.birds.iter().map(|bird| bird.position).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,152 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::thread;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+
+use bird_flock::Flock;
+
+const QUEUED_FRAMES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamFormat
+{
+    Binary,
+    Json,
+}
+
+impl Default for StreamFormat
+{
+    fn default() -> StreamFormat
+    {
+        StreamFormat::Binary
+    }
+}
+
+impl FromStr for StreamFormat
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StreamFormat, String>
+    {
+        match s {
+            "binary" => Ok(StreamFormat::Binary),
+            "json" => Ok(StreamFormat::Json),
+            _ => Err(format!("unknown stream format '{}', expected binary or json", s)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFrame
+{
+    tick: u64,
+    positions: Vec<[f32; 3]>,
+    velocities: Vec<[f32; 3]>,
+}
+
+pub fn encode_binary(flock: &Flock) -> Vec<u8>
+{
+    let mut frame = Vec::with_capacity(12 + flock.birds.len() * 24);
+    frame.extend_from_slice(&flock.steps.to_le_bytes());
+    frame.extend_from_slice(&(flock.birds.len() as u32).to_le_bytes());
+    for bird in &flock.birds {
+        for value in bird.position.iter().chain(bird.velocity.iter()) {
+            frame.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    frame
+}
+
+pub fn encode_json(flock: &Flock) -> String
+{
+    let frame = JsonFrame
+    {
+        tick: flock.steps,
+        positions: flock.birds.iter().map(|bird| bird.position.into()).collect(),
+        velocities: flock.birds.iter().map(|bird| bird.velocity.into()).collect(),
+    };
+    serde_json::to_string(&frame).unwrap_or_default()
+}
+
+pub struct FlockServer
+{
+    frames: broadcast::Sender<Message>,
+    format: StreamFormat,
+}
+
+impl FlockServer
+{
+    pub fn start(address: SocketAddr, format: StreamFormat) -> Result<FlockServer, String>
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .map_err(|err| format!("could not start the stream server: {}", err))?;
+        let listener = runtime.block_on(TcpListener::bind(address)).map_err(|err| format!("could not listen on {}: {}", address, err))?;
+        let (frames, _) = broadcast::channel(QUEUED_FRAMES);
+        let sender = frames.clone();
+        thread::Builder::new()
+            .name("flock-server".to_string())
+            .spawn(move || runtime.block_on(accept(listener, sender)))
+            .map_err(|err| format!("could not start the stream server: {}", err))?;
+        Ok(FlockServer { frames, format })
+    }
+
+    pub fn publish(&self, flock: &Flock)
+    {
+        if self.frames.receiver_count() == 0 {
+            return;
+        }
+        let message = match self.format {
+            StreamFormat::Binary => Message::Binary(encode_binary(flock)),
+            StreamFormat::Json => Message::Text(encode_json(flock)),
+        };
+        let _ = self.frames.send(message);
+    }
+}
+
+async fn accept(listener: TcpListener, frames: broadcast::Sender<Message>)
+{
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(serve(stream, peer, frames.subscribe()));
+            },
+            Err(err) => eprintln!("warning: could not accept a stream connection: {}", err),
+        }
+    }
+}
+
+async fn serve(stream: TcpStream, peer: SocketAddr, mut frames: broadcast::Receiver<Message>)
+{
+    let socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            eprintln!("warning: WebSocket handshake with {} failed: {}", peer, err);
+            return;
+        },
+    };
+    let (mut outgoing, mut incoming) = socket.split();
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(message) => {
+                    if outgoing.send(message).await.is_err() {
+                        break;
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => (),
+            },
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pub struct ShaderSource
+{
+    pub vertex: String,
+    pub fragment: String,
+}
+
+pub struct ShaderSet
+{
+    pub bird: ShaderSource,
+    pub bird_points: ShaderSource,
+    pub ground: ShaderSource,
+    pub sky: ShaderSource,
+}
+
+impl ShaderSet
+{
+    pub fn builtin() -> ShaderSet
+    {
+        ShaderSet
+        {
+            bird: ShaderSource { vertex: include_str!("../shaders/bird.vert").to_string(), fragment: include_str!("../shaders/bird.frag").to_string() },
+            bird_points: ShaderSource { vertex: include_str!("../shaders/bird_points.vert").to_string(), fragment: include_str!("../shaders/bird_points.frag").to_string() },
+            ground: ShaderSource { vertex: include_str!("../shaders/ground.vert").to_string(), fragment: include_str!("../shaders/ground.frag").to_string() },
+            sky: ShaderSource { vertex: include_str!("../shaders/sky.vert").to_string(), fragment: include_str!("../shaders/sky.frag").to_string() },
+        }
+    }
+}
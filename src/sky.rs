@@ -0,0 +1,174 @@
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{CubeLayer, Cubemap, RawImage2d, Texture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{BlitTarget, Frame, Program, Surface, VertexBuffer};
+use image::{DynamicImage, Rgb, RgbImage};
+use nalgebra::Vector3;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::mesh::{self, Vertex3};
+use crate::render;
+use crate::shaders::ShaderSource;
+
+pub const FOG_COLOUR: [f32; 3] = [0.5, 0.56, 0.64];
+pub const FOG_DENSITY: f32 = 0.08;
+
+const FACES: [(&str, CubeLayer); 6] = [
+    ("px", CubeLayer::PositiveX),
+    ("nx", CubeLayer::NegativeX),
+    ("py", CubeLayer::PositiveY),
+    ("ny", CubeLayer::NegativeY),
+    ("pz", CubeLayer::PositiveZ),
+    ("nz", CubeLayer::NegativeZ),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fog
+{
+    pub colour: [f32; 3],
+    pub density: f32,
+}
+
+impl Fog
+{
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if !(self.density >= 0.0 && self.density.is_finite()) {
+            return Err(format!("fog-density must be zero or positive, got {}", self.density));
+        }
+        if !self.colour.iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err(format!("fog-colour components must be between 0 and 1, got {:?}", self.colour));
+        }
+        Ok(())
+    }
+}
+
+fn face_direction(layer: CubeLayer, u: f32, v: f32) -> Vector3<f32>
+{
+    match layer {
+        CubeLayer::PositiveX => Vector3::new(1.0, -v, -u),
+        CubeLayer::NegativeX => Vector3::new(-1.0, -v, u),
+        CubeLayer::PositiveY => Vector3::new(u, 1.0, v),
+        CubeLayer::NegativeY => Vector3::new(u, -1.0, -v),
+        CubeLayer::PositiveZ => Vector3::new(u, -v, 1.0),
+        CubeLayer::NegativeZ => Vector3::new(-u, -v, -1.0),
+    }
+}
+
+fn display_image(image: DynamicImage) -> RgbImage
+{
+    let tone = |c: f32| ((c.max(0.0) / (1.0 + c.max(0.0))).powf(1.0 / 2.2) * 255.0).round() as u8;
+    match image {
+        DynamicImage::ImageRgb32F(hdr) => RgbImage::from_fn(hdr.width(), hdr.height(), |x, y| {
+            let pixel = hdr.get_pixel(x, y);
+            Rgb([tone(pixel[0]), tone(pixel[1]), tone(pixel[2])])
+        }),
+        other => other.to_rgb8(),
+    }
+}
+
+fn load_image(path: &Path) -> Result<RgbImage, String>
+{
+    let image = image::open(path).map_err(|err| format!("could not load skybox image {}: {}", path.display(), err))?;
+    Ok(display_image(image))
+}
+
+fn equirect_faces(panorama: &RgbImage) -> Vec<RgbImage>
+{
+    let (width, height) = panorama.dimensions();
+    let size = (width / 4).max(1);
+    FACES.iter().map(|&(_, layer)| {
+        RgbImage::from_fn(size, size, |x, y| {
+            let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+            let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+            let direction = face_direction(layer, u, v).normalize();
+            let longitude = direction.x.atan2(-direction.z);
+            let latitude = direction.y.asin();
+            let column = ((longitude / (2.0 * PI) + 0.5) * width as f32) as u32;
+            let row = ((0.5 - latitude / PI) * height as f32) as u32;
+            *panorama.get_pixel(column.min(width - 1), row.min(height - 1))
+        })
+    }).collect()
+}
+
+fn face_images(dir: &Path) -> Result<Vec<RgbImage>, String>
+{
+    let entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|err| format!("could not read skybox directory {}: {}", dir.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+
+    FACES.iter().map(|&(name, _)| {
+        let path = entries.iter()
+            .find(|path| path.file_stem().is_some_and(|stem| stem == name))
+            .ok_or_else(|| format!("skybox directory {} has no '{}' face image", dir.display(), name))?;
+        load_image(path)
+    }).collect()
+}
+
+pub struct Skybox
+{
+    cubemap: Cubemap,
+    vertex_buffer: VertexBuffer<Vertex3>,
+    program: Program,
+}
+
+impl Skybox
+{
+    pub fn load<F: ?Sized + Facade>(facade: &F, path: &Path, shaders: &ShaderSource) -> Result<Skybox, String>
+    {
+        let faces = if path.is_dir() {
+            face_images(path)?
+        } else {
+            equirect_faces(&load_image(path)?)
+        };
+
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.dimensions() != (size, size)) {
+            return Err(format!("skybox {} faces must all be square and the same size", path.display()));
+        }
+
+        let cubemap = Cubemap::empty(facade, size).map_err(|err| err.to_string())?;
+        let target = BlitTarget { left: 0, bottom: 0, width: size as i32, height: size as i32 };
+        for (face, &(_, layer)) in faces.into_iter().zip(FACES.iter()) {
+            let texture = Texture2d::new(facade, RawImage2d::from_raw_rgb(face.into_raw(), (size, size))).map_err(|err| err.to_string())?;
+            let framebuffer = SimpleFrameBuffer::new(facade, cubemap.main_level().image(layer)).map_err(|err| err.to_string())?;
+            texture.as_surface().blit_whole_color_to(&framebuffer, &target, MagnifySamplerFilter::Linear);
+        }
+
+        Ok(Skybox
+        {
+            cubemap,
+            vertex_buffer: VertexBuffer::new(facade, &mesh::skybox_cube()).map_err(|err| err.to_string())?,
+            program: render::compile_program(facade, "sky", &shaders.vertex, &shaders.fragment)?,
+        })
+    }
+
+    #[cfg(feature = "hot-reload")]
+    pub fn reload<F: ?Sized + Facade>(&mut self, facade: &F, shaders: &ShaderSource) -> Result<(), String>
+    {
+        self.program = render::compile_program(facade, "sky", &shaders.vertex, &shaders.fragment)?;
+        Ok(())
+    }
+
+    pub fn draw(&self, target: &mut Frame, view: [[f32; 4]; 4], projection: [[f32; 4]; 4], brightness: f32, viewport: Option<glium::Rect>) -> Result<(), String>
+    {
+        let uniforms = uniform! {
+            view: view,
+            projection: projection,
+            brightness: brightness,
+            sky: self.cubemap.sampled()
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .wrap_function(SamplerWrapFunction::Clamp),
+        };
+        let triangles = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+
+        let params = glium::DrawParameters { viewport, .. Default::default() };
+
+        target.draw(&self.vertex_buffer, triangles, &self.program, &uniforms, &params).map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,433 @@
+use nalgebra::Vector3;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::bird::{Bird, BirdState};
+use crate::flock::{normalize_or_zero, positive, FlockConfig, MAX_FORCE};
+use crate::octree::Aggregate;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Neighbourhood
+{
+    pub separation: Vector3<f32>,
+    pub repulsion: Vector3<f32>,
+    pub alignment: Vector3<f32>,
+    pub cohesion: Vector3<f32>,
+    pub neighbours: u32,
+    pub flockmates: u32,
+    pub far_weight: f32,
+}
+
+impl Neighbourhood
+{
+    pub fn add(&mut self, bird: &Bird, other: &Bird, dist: f32)
+    {
+        let away = if dist > 0.0 { (bird.position - other.position) / (dist * dist) } else { Vector3::zeros() };
+        self.separation += away;
+        self.neighbours += 1;
+
+        if other.species == bird.species && other.state == BirdState::Flying {
+            self.alignment += other.velocity;
+            self.cohesion += other.position;
+            self.flockmates += 1;
+        } else {
+            self.repulsion += away;
+        }
+    }
+
+    /// Adds a distant group of flying flockmates to alignment and cohesion only.
+    /// Each bird in it weighs `(radius / dist)^2` of a neighbour, so far groups
+    /// pull the flock together without drowning out the local neighbourhood.
+    pub fn add_far_group(&mut self, bird: &Bird, group: &Aggregate, radius: f32)
+    {
+        if group.count == 0 {
+            return;
+        }
+        let centroid = group.centroid();
+        let dist = (centroid - bird.position).norm().max(radius);
+        let weight = group.count as f32 * (radius / dist).powi(2);
+        self.alignment += group.velocity_sum * (weight / group.count as f32);
+        self.cohesion += centroid * weight;
+        self.far_weight += weight;
+    }
+
+    fn flockmate_weight(&self) -> f32
+    {
+        self.flockmates as f32 + self.far_weight
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SteeringForces
+{
+    pub separation: Vector3<f32>,
+    pub alignment: Vector3<f32>,
+    pub cohesion: Vector3<f32>,
+}
+
+pub fn steer(desired: Vector3<f32>, velocity: Vector3<f32>, max_speed: f32) -> Vector3<f32>
+{
+    let desired = normalize_or_zero(desired);
+    if desired == Vector3::zeros() {
+        return Vector3::zeros();
+    }
+
+    (desired * max_speed - velocity).cap_magnitude(MAX_FORCE)
+}
+
+pub fn separation(bird: &Bird, neighbourhood: &Neighbourhood, max_speed: f32) -> Vector3<f32>
+{
+    if neighbourhood.neighbours == 0 {
+        return Vector3::zeros();
+    }
+    steer(neighbourhood.separation, bird.velocity, max_speed)
+}
+
+pub fn repulsion(bird: &Bird, neighbourhood: &Neighbourhood, max_speed: f32) -> Vector3<f32>
+{
+    if neighbourhood.neighbours == 0 {
+        return Vector3::zeros();
+    }
+    steer(neighbourhood.repulsion, bird.velocity, max_speed)
+}
+
+pub fn alignment(bird: &Bird, neighbourhood: &Neighbourhood, max_speed: f32) -> Vector3<f32>
+{
+    let weight = neighbourhood.flockmate_weight();
+    if !positive(weight) {
+        return Vector3::zeros();
+    }
+    steer(neighbourhood.alignment / weight, bird.velocity, max_speed)
+}
+
+pub fn cohesion(bird: &Bird, neighbourhood: &Neighbourhood, max_speed: f32) -> Vector3<f32>
+{
+    let weight = neighbourhood.flockmate_weight();
+    if !positive(weight) {
+        return Vector3::zeros();
+    }
+    steer(neighbourhood.cohesion / weight - bird.position, bird.velocity, max_speed)
+}
+
+pub struct SteeringContext<'a>
+{
+    pub config: &'a FlockConfig,
+    pub time: f32,
+    pub waypoint: Option<Vector3<f32>>,
+}
+
+pub trait SteeringBehaviour: Send + Sync
+{
+    fn name(&self) -> &str;
+    fn force(&self, bird: &Bird, neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>;
+}
+
+#[derive(Clone)]
+pub struct WeightedBehaviour
+{
+    pub behaviour: Arc<dyn SteeringBehaviour>,
+    pub weight: f32,
+}
+
+impl fmt::Debug for WeightedBehaviour
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} x{}", self.behaviour.name(), self.weight)
+    }
+}
+
+pub struct Separation;
+/// Alignment and cohesion steer along a unit direction scaled by their weight,
+/// so the weight alone sets their magnitude, however far off the bird is.
+pub struct Alignment;
+pub struct Cohesion;
+pub struct Gravity;
+pub struct Boundary;
+
+impl SteeringBehaviour for Separation
+{
+    fn name(&self) -> &str
+    {
+        "separation"
+    }
+
+    fn force(&self, bird: &Bird, neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>
+    {
+        separation(bird, neighbours, ctx.config.max_speed) * ctx.config.separation_weight
+    }
+}
+
+impl SteeringBehaviour for Alignment
+{
+    fn name(&self) -> &str
+    {
+        "alignment"
+    }
+
+    fn force(&self, bird: &Bird, neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>
+    {
+        if bird.leader {
+            return Vector3::zeros();
+        }
+        normalize_or_zero(alignment(bird, neighbours, ctx.config.max_speed)) * ctx.config.alignment_weight * bird.sociability
+    }
+}
+
+impl SteeringBehaviour for Cohesion
+{
+    fn name(&self) -> &str
+    {
+        "cohesion"
+    }
+
+    fn force(&self, bird: &Bird, neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>
+    {
+        if bird.leader {
+            return Vector3::zeros();
+        }
+        normalize_or_zero(cohesion(bird, neighbours, ctx.config.max_speed)) * ctx.config.cohesion_weight * bird.sociability
+    }
+}
+
+impl SteeringBehaviour for Gravity
+{
+    fn name(&self) -> &str
+    {
+        "gravity"
+    }
+
+    fn force(&self, _bird: &Bird, _neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>
+    {
+        ctx.config.gravity
+    }
+}
+
+impl SteeringBehaviour for Boundary
+{
+    fn name(&self) -> &str
+    {
+        "boundary"
+    }
+
+    fn force(&self, bird: &Bird, _neighbours: &Neighbourhood, ctx: &SteeringContext) -> Vector3<f32>
+    {
+        ctx.config.boundary_force(bird.position)
+    }
+}
+
+pub fn default_behaviours() -> Vec<WeightedBehaviour>
+{
+    let behaviours: [Arc<dyn SteeringBehaviour>; 5] = [Arc::new(Separation), Arc::new(Alignment), Arc::new(Cohesion), Arc::new(Gravity), Arc::new(Boundary)];
+    behaviours.iter().map(|behaviour| WeightedBehaviour { behaviour: behaviour.clone(), weight: 1.0 }).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn bird_at(position: Vector3<f32>, velocity: Vector3<f32>) -> Bird
+    {
+        let mut bird = Bird::with_rng(&FlockConfig::default(), &mut StdRng::seed_from_u64(0));
+        bird.position = position;
+        bird.velocity = velocity;
+        bird.sociability = 1.0;
+        bird.leader = false;
+        bird
+    }
+
+    fn neighbourhood_of(bird: &Bird, others: &[Bird]) -> Neighbourhood
+    {
+        let mut neighbourhood = Neighbourhood::default();
+        for other in others.iter() {
+            neighbourhood.add(bird, other, (bird.position - other.position).norm());
+        }
+        neighbourhood
+    }
+
+    #[test]
+    fn cohesion_magnitude_does_not_depend_on_distance()
+    {
+        let config = FlockConfig::default();
+        let bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        let near = bird_at(Vector3::new(0.1, 0.0, 0.0), Vector3::zeros());
+        let far = bird_at(Vector3::new(0.9, 0.0, 0.0), Vector3::zeros());
+
+        let pull_near = cohesion(&bird, &neighbourhood_of(&bird, &[near]), config.max_speed);
+        let pull_far = cohesion(&bird, &neighbourhood_of(&bird, &[far]), config.max_speed);
+
+        assert!((pull_near - Vector3::new(MAX_FORCE, 0.0, 0.0)).norm() < 1e-7);
+        assert!((pull_far - pull_near).norm() < 1e-7);
+    }
+
+    #[test]
+    fn alignment_and_cohesion_magnitude_is_their_weight()
+    {
+        let config = FlockConfig { alignment_weight: 0.0025, cohesion_weight: 0.0005, ..FlockConfig::default() };
+        let ctx = SteeringContext { config: &config, time: 0.0, waypoint: None };
+        let other = bird_at(Vector3::new(0.0, 0.0, 0.5), Vector3::new(config.max_speed, 0.0, 0.0));
+
+        // Nearly matched already, so the raw steering is far below MAX_FORCE.
+        let cruising = bird_at(Vector3::zeros(), Vector3::new(config.max_speed * 0.99, 0.0, 0.0));
+        let neighbourhood = neighbourhood_of(&cruising, &[other]);
+        assert!(alignment(&cruising, &neighbourhood, config.max_speed).norm() < MAX_FORCE);
+        let aligned = Alignment.force(&cruising, &neighbourhood, &ctx);
+        assert!((aligned.normalize() - Vector3::x()).norm() < 1e-6);
+        assert!((aligned.norm() - 0.0025).abs() < 1e-7);
+
+        let resting = bird_at(Vector3::zeros(), Vector3::zeros());
+        let neighbourhood = neighbourhood_of(&resting, &[other]);
+        let gathered = Cohesion.force(&resting, &neighbourhood, &ctx);
+        assert!((gathered.normalize() - Vector3::z()).norm() < 1e-6);
+        assert!((gathered.norm() - 0.0005).abs() < 1e-7);
+    }
+
+    #[test]
+    fn leaders_ignore_alignment_and_cohesion()
+    {
+        let config = FlockConfig::default();
+        let ctx = SteeringContext { config: &config, time: 0.0, waypoint: None };
+        let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        let other = bird_at(Vector3::new(0.0, 0.0, 0.5), Vector3::new(config.max_speed, 0.0, 0.0));
+        let neighbourhood = neighbourhood_of(&bird, &[other]);
+        assert_ne!(Alignment.force(&bird, &neighbourhood, &ctx), Vector3::zeros());
+        assert_ne!(Cohesion.force(&bird, &neighbourhood, &ctx), Vector3::zeros());
+
+        bird.leader = true;
+        assert_eq!(Alignment.force(&bird, &neighbourhood, &ctx), Vector3::zeros());
+        assert_eq!(Cohesion.force(&bird, &neighbourhood, &ctx), Vector3::zeros());
+        assert_ne!(Separation.force(&bird, &neighbourhood, &ctx), Vector3::zeros());
+    }
+
+    #[test]
+    fn other_species_repel_but_do_not_attract()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::new(0.01, 0.0, 0.0));
+        let others: Vec<Bird> = [Vector3::x(), -Vector3::x(), Vector3::y() * 0.8, -Vector3::z() * 0.6].iter().map(|&offset| {
+            let mut other = bird_at(offset * 0.5, Vector3::new(0.0, 0.01, 0.0));
+            other.species = 1;
+            other
+        }).collect();
+        let neighbourhood = neighbourhood_of(&bird, &others);
+
+        assert_eq!(neighbourhood.neighbours, 4);
+        assert_eq!(neighbourhood.flockmates, 0);
+        assert_ne!(repulsion(&bird, &neighbourhood, 0.02), Vector3::zeros());
+        assert_eq!(alignment(&bird, &neighbourhood, 0.02), Vector3::zeros());
+        assert_eq!(cohesion(&bird, &neighbourhood, 0.02), Vector3::zeros());
+    }
+
+    #[test]
+    fn separation_steers_away_from_the_closest_neighbour()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        let close = bird_at(Vector3::new(0.1, 0.0, 0.0), Vector3::zeros());
+        let far = bird_at(Vector3::new(0.0, -0.8, 0.0), Vector3::zeros());
+
+        let away = separation(&bird, &neighbourhood_of(&bird, &[close, far]), 0.02);
+        assert!(away.x < 0.0 && away.y > 0.0);
+        assert!(away.x.abs() > away.y * 5.0, "{:?} is not dominated by the closer bird", away);
+        assert!((away.norm() - MAX_FORCE).abs() < 1e-7);
+    }
+
+    #[test]
+    fn alignment_steers_toward_the_average_heading()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::new(0.0, 0.0, 0.02));
+        let others = [
+            bird_at(Vector3::new(0.3, 0.0, 0.0), Vector3::new(0.02, 0.0, 0.0)),
+            bird_at(Vector3::new(-0.3, 0.0, 0.0), Vector3::new(0.0, 0.02, 0.0)),
+        ];
+
+        let turn = alignment(&bird, &neighbourhood_of(&bird, &others), 0.02);
+        let desired = Vector3::new(1.0, 1.0, 0.0).normalize() * 0.02 - bird.velocity;
+        assert!((turn - desired.cap_magnitude(MAX_FORCE)).norm() < 1e-7);
+        assert_eq!(alignment(&others[0], &neighbourhood_of(&others[0], &[others[0]]), 0.02), Vector3::zeros());
+    }
+
+    #[test]
+    fn cohesion_steers_toward_the_centroid()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::zeros());
+        let others = [
+            bird_at(Vector3::new(0.4, 0.4, 0.0), Vector3::zeros()),
+            bird_at(Vector3::new(0.4, -0.4, 0.0), Vector3::zeros()),
+            bird_at(Vector3::new(0.4, 0.0, 0.6), Vector3::zeros()),
+        ];
+
+        let pull = cohesion(&bird, &neighbourhood_of(&bird, &others), 0.02);
+        assert!((pull.normalize() - Vector3::new(0.4, 0.0, 0.2).normalize()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn speed_is_clamped_once_per_update()
+    {
+        let config = FlockConfig { gravity: Vector3::zeros(), ..FlockConfig::default() };
+        let mut bird = bird_at(Vector3::zeros(), Vector3::new(config.max_speed, 0.0, 0.0));
+        bird.speed_factor = 1.0;
+        bird.mass = 1.0;
+        bird.apply_force(Vector3::new(1.0, 1.0, 0.0));
+        bird.update(&config, 1.0, 1.0);
+
+        assert!((bird.speed() - config.max_speed).abs() < 1e-7);
+        assert!((bird.velocity.normalize() - Vector3::new(1.0 + config.max_speed, 1.0, 0.0).normalize()).norm() < 1e-6);
+        assert_eq!(bird.acceleration, Vector3::zeros());
+    }
+
+    #[test]
+    fn distance_to_is_euclidean()
+    {
+        let a = bird_at(Vector3::new(1.0, 2.0, 3.0), Vector3::zeros());
+        let b = bird_at(Vector3::new(4.0, 6.0, 3.0), Vector3::zeros());
+
+        assert_eq!(a.distance_to(&b), 5.0);
+        assert_eq!(b.distance_to(&a), 5.0);
+        assert_eq!(a.distance_to(&a), 0.0);
+    }
+
+    #[test]
+    fn steering_never_exceeds_max_force()
+    {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut random = |scale: f32| Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * scale;
+
+        for _ in 0..1000 {
+            let force = steer(random(10.0), random(0.05), 0.05);
+            assert!(force.norm() <= MAX_FORCE * (1.0 + 1e-5), "{:?} exceeds MAX_FORCE", force);
+        }
+        assert_eq!(steer(Vector3::zeros(), random(0.05), 0.05), Vector3::zeros());
+    }
+
+    #[test]
+    fn seeking_a_point_converges_on_it()
+    {
+        let max_speed = FlockConfig::default().max_speed;
+        let target = Vector3::new(3.0, -1.0, 2.0);
+        let mut bird = bird_at(Vector3::zeros(), Vector3::zeros());
+
+        let mut closest = f32::MAX;
+        for _ in 0..1000 {
+            bird.velocity = (bird.velocity + bird.steer(target - bird.position, max_speed)).cap_magnitude(max_speed);
+            bird.position += bird.velocity;
+            closest = closest.min((target - bird.position).norm());
+        }
+
+        assert!(closest < max_speed, "came no closer than {}", closest);
+        assert!((target - bird.position).norm() < max_speed * max_speed / MAX_FORCE, "drifted to {:?}", bird.position);
+    }
+
+    #[test]
+    fn empty_neighbourhood_gives_no_force()
+    {
+        let bird = bird_at(Vector3::zeros(), Vector3::new(0.01, 0.0, 0.0));
+        let empty = Neighbourhood::default();
+
+        assert_eq!(separation(&bird, &empty, 0.02), Vector3::zeros());
+        assert_eq!(repulsion(&bird, &empty, 0.02), Vector3::zeros());
+        assert_eq!(alignment(&bird, &empty, 0.02), Vector3::zeros());
+        assert_eq!(cohesion(&bird, &empty, 0.02), Vector3::zeros());
+    }
+}
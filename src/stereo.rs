@@ -0,0 +1,48 @@
+use nalgebra::{Matrix4, Vector3};
+
+use crate::camera::Camera;
+use crate::projection::Projection;
+
+pub const EYE_SEPARATION: f32 = 0.1;
+const MIN_CONVERGENCE: f32 = 0.1;
+
+pub struct EyeView
+{
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub viewport: Option<glium::Rect>,
+}
+
+pub fn validate_separation(separation: f32) -> Result<(), String>
+{
+    if !(separation > 0.0 && separation.is_finite()) {
+        return Err(format!("eye-separation must be positive, got {}", separation));
+    }
+    Ok(())
+}
+
+pub fn eye_views(camera: &Camera, projection: &Projection, size: (u32, u32), separation: Option<f32>) -> Vec<EyeView>
+{
+    let view = camera.view_matrix();
+    let separation = match separation {
+        Some(separation) => separation,
+        None => return vec![EyeView { view: *view.as_ref(), projection: *projection.matrix().as_ref(), viewport: None }],
+    };
+
+    let (width, height) = size;
+    let half_width = width / 2;
+    let eye_projection = projection.matrix_for(half_width, height);
+    let convergence = (camera.focus() - camera.eye()).norm().max(MIN_CONVERGENCE);
+
+    [-0.5f32, 0.5].iter().enumerate().map(|(index, side)| {
+        let offset = side * separation;
+        let mut shifted = eye_projection;
+        shifted[(0, 2)] = -eye_projection[(0, 0)] * offset / convergence;
+        EyeView
+        {
+            view: *(Matrix4::new_translation(&Vector3::new(-offset, 0.0, 0.0)) * view).as_ref(),
+            projection: *shifted.as_ref(),
+            viewport: Some(glium::Rect { left: half_width * index as u32, bottom: 0, width: half_width, height }),
+        }
+    }).collect()
+}
@@ -0,0 +1,119 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::bird::Bird;
+use crate::flock::{non_negative, positive};
+
+pub const GROUND_EXTENT: f32 = 4.0;
+pub const TERRAIN_HEIGHT: f32 = 1.0;
+pub const GROUND_LOOK_AHEAD: f32 = 30.0;
+pub const GROUND_MARGIN: f32 = 0.5;
+pub const GROUND_AVOID_WEIGHT: f32 = 0.01;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Terrain
+{
+    pub size: f32,
+    pub base: f32,
+    pub relief: f32,
+    pub resolution: usize,
+    pub heights: Vec<f32>,
+}
+
+impl Terrain
+{
+    pub fn flat(size: f32, base: f32) -> Terrain
+    {
+        Terrain { size, base, relief: 0.0, resolution: 2, heights: vec![0.0; 4] }
+    }
+
+    pub fn from_heights(size: f32, base: f32, relief: f32, resolution: usize, heights: Vec<f32>) -> Result<Terrain, String>
+    {
+        let terrain = Terrain { size, base, relief, resolution, heights };
+        terrain.validate()?;
+        Ok(terrain)
+    }
+
+    pub fn validate(&self) -> Result<(), String>
+    {
+        if !positive(self.size) {
+            return Err(format!("terrain size must be positive, got {}", self.size));
+        }
+        if !self.base.is_finite() {
+            return Err(format!("terrain base must be finite, got {}", self.base));
+        }
+        if !non_negative(self.relief) {
+            return Err(format!("terrain-height must not be negative, got {}", self.relief));
+        }
+        if self.resolution < 2 {
+            return Err(format!("terrain resolution must be at least 2, got {}", self.resolution));
+        }
+        if self.heights.len() != self.resolution * self.resolution {
+            return Err(format!("terrain needs {} heights for a resolution of {}, got {}", self.resolution * self.resolution, self.resolution, self.heights.len()));
+        }
+        if !self.heights.iter().all(|h| h.is_finite()) {
+            return Err("terrain heights must be finite".to_string());
+        }
+        Ok(())
+    }
+
+    fn sample(&self, column: usize, row: usize) -> f32
+    {
+        self.heights[row * self.resolution + column]
+    }
+
+    pub fn height_at(&self, x: f32, z: f32) -> f32
+    {
+        let last = (self.resolution - 1) as f32;
+        let u = ((x / self.size + 0.5) * last).max(0.0).min(last);
+        let v = ((z / self.size + 0.5) * last).max(0.0).min(last);
+
+        let column = (u.floor() as usize).min(self.resolution - 2);
+        let row = (v.floor() as usize).min(self.resolution - 2);
+        let (fu, fv) = (u - column as f32, v - row as f32);
+
+        let top = self.sample(column, row) * (1.0 - fu) + self.sample(column + 1, row) * fu;
+        let bottom = self.sample(column, row + 1) * (1.0 - fu) + self.sample(column + 1, row + 1) * fu;
+        self.base + (top * (1.0 - fv) + bottom * fv) * self.relief
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> Vector3<f32>
+    {
+        let step = self.size / (self.resolution - 1) as f32;
+        let dx = self.height_at(x + step, z) - self.height_at(x - step, z);
+        let dz = self.height_at(x, z + step) - self.height_at(x, z - step);
+        Vector3::new(-dx, 2.0 * step, -dz).normalize()
+    }
+
+    pub fn clearance(&self, position: Vector3<f32>) -> f32
+    {
+        position.y - self.height_at(position.x, position.z)
+    }
+
+    pub fn avoidance_force(&self, bird: &Bird) -> Vector3<f32>
+    {
+        let ahead = bird.position + bird.velocity * GROUND_LOOK_AHEAD;
+        let clearance = self.clearance(bird.position).min(self.clearance(ahead));
+        if clearance >= GROUND_MARGIN {
+            return Vector3::zeros();
+        }
+
+        let urgency = (1.0 - clearance / GROUND_MARGIN).min(2.0);
+        Vector3::new(0.0, GROUND_AVOID_WEIGHT * urgency, 0.0)
+    }
+
+    pub fn push_out(&self, bird: &mut Bird)
+    {
+        let ground = self.height_at(bird.position.x, bird.position.z);
+        if bird.position.y >= ground {
+            return;
+        }
+
+        bird.position.y = ground;
+        let normal = self.normal_at(bird.position.x, bird.position.z);
+        let inward = bird.velocity.dot(&normal);
+        if inward < 0.0 {
+            bird.velocity -= normal * inward;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+pub struct FixedTimestep
+{
+    step: Duration,
+    max_steps: u32,
+    accumulator: Duration,
+}
+
+impl FixedTimestep
+{
+    pub fn new(hz: f64, max_steps: u32) -> FixedTimestep
+    {
+        FixedTimestep
+        {
+            step: Duration::from_secs_f64(1.0 / hz),
+            max_steps,
+            accumulator: Duration::from_secs(0),
+        }
+    }
+
+    pub fn step(&self) -> Duration
+    {
+        self.step
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) -> u32
+    {
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if self.accumulator >= self.step {
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+        }
+
+        steps
+    }
+
+    pub fn alpha(&self) -> f32
+    {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+}
+
+pub struct FrameLimiter
+{
+    interval: Option<Duration>,
+    next_frame: Instant,
+}
+
+impl FrameLimiter
+{
+    pub fn new(max_fps: f64) -> FrameLimiter
+    {
+        let interval = if max_fps > 0.0 { Some(Duration::from_secs_f64(1.0 / max_fps)) } else { None };
+        FrameLimiter { interval, next_frame: Instant::now() }
+    }
+
+    pub fn interval(&self) -> Option<Duration>
+    {
+        self.interval
+    }
+
+    pub fn wait(&self, now: Instant) -> Option<Instant>
+    {
+        if self.interval.is_some() && now < self.next_frame {
+            Some(self.next_frame)
+        } else {
+            None
+        }
+    }
+
+    pub fn frame(&mut self, now: Instant)
+    {
+        if let Some(interval) = self.interval {
+            self.next_frame += interval;
+            if self.next_frame < now {
+                self.next_frame = now + interval;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn ms(millis: u64) -> Duration
+    {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn advance_counts_whole_steps_and_keeps_the_remainder()
+    {
+        let mut timestep = FixedTimestep::new(100.0, 10);
+
+        assert_eq!(timestep.advance(ms(25)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+
+        assert_eq!(timestep.advance(ms(5)), 1);
+        assert!(timestep.alpha().abs() < 1e-6);
+
+        assert_eq!(timestep.advance(ms(3)), 0);
+        assert!((timestep.alpha() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn advance_caps_steps_after_a_long_stall()
+    {
+        let mut timestep = FixedTimestep::new(100.0, 5);
+
+        assert_eq!(timestep.advance(Duration::from_secs(2) + ms(4)), 5);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-6);
+        assert_eq!(timestep.advance(ms(0)), 0);
+    }
+
+    #[test]
+    fn frame_limiter_waits_until_the_next_frame()
+    {
+        let mut limiter = FrameLimiter::new(50.0);
+        let start = limiter.next_frame;
+        assert_eq!(limiter.wait(start), None);
+
+        limiter.frame(start);
+        assert_eq!(limiter.wait(start), Some(start + ms(20)));
+        assert_eq!(limiter.wait(start + ms(20)), None);
+
+        let unlimited = FrameLimiter::new(0.0);
+        assert_eq!(unlimited.interval(), None);
+        assert_eq!(unlimited.wait(start), None);
+    }
+}
@@ -0,0 +1,68 @@
+use nalgebra::Vector3;
+
+use crate::flock::Bird;
+
+pub const TRAIL_LENGTH: usize = 30;
+
+pub struct Trails
+{
+    length: usize,
+    bird_count: usize,
+    head: usize,
+    filled: usize,
+    positions: Vec<Vector3<f32>>,
+}
+
+impl Trails
+{
+    pub fn new(length: usize) -> Trails
+    {
+        Trails { length: length.max(2), bird_count: 0, head: 0, filled: 0, positions: Vec::new() }
+    }
+
+    pub fn length(&self) -> usize
+    {
+        self.length
+    }
+
+    pub fn bird_count(&self) -> usize
+    {
+        self.bird_count
+    }
+
+    pub fn filled(&self) -> usize
+    {
+        self.filled
+    }
+
+    pub fn record(&mut self, birds: &[Bird])
+    {
+        if birds.len() != self.bird_count {
+            self.bird_count = birds.len();
+            self.positions = vec![Vector3::zeros(); self.bird_count * self.length];
+            self.clear();
+        }
+
+        for (index, bird) in birds.iter().enumerate() {
+            self.positions[index * self.length + self.head] = bird.position;
+        }
+
+        self.head = (self.head + 1) % self.length;
+        self.filled = (self.filled + 1).min(self.length);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.head = 0;
+        self.filled = 0;
+    }
+
+    pub fn points(&self, bird: usize) -> impl Iterator<Item = Vector3<f32>> + '_
+    {
+        let base = bird * self.length;
+        (0..self.filled).map(move |age| {
+            let slot = (self.head + self.length - 1 - age) % self.length;
+            self.positions[base + slot]
+        })
+    }
+}
@@ -0,0 +1,134 @@
+use glium::backend::Facade;
+use glium::{Frame, Program, Surface, VertexBuffer};
+
+use bird_flock::trail::Trails;
+
+use crate::render;
+
+const TRAIL_TINT: [f32; 3] = [0.8, 0.85, 1.0];
+
+#[derive(Copy, Clone)]
+struct TrailVertex
+{
+    position: [f32; 3],
+    alpha: f32,
+}
+
+implement_vertex!(TrailVertex, position, alpha);
+
+pub struct TrailRenderer
+{
+    pub visible: bool,
+    vertices: Vec<TrailVertex>,
+    vertex_buffer: Option<VertexBuffer<TrailVertex>>,
+    program: Program,
+}
+
+impl TrailRenderer
+{
+    pub fn new<F: ?Sized + Facade>(facade: &F) -> Result<TrailRenderer, String>
+    {
+        let vertex_shader_src = r#"
+            #version 140
+
+            in vec3 position;
+            in float alpha;
+
+            out float v_alpha;
+
+            uniform mat4 view;
+            uniform mat4 projection;
+
+            void main() {
+                v_alpha = alpha;
+                gl_Position = projection * view * vec4(position, 1.0);
+            }
+        "#;
+
+        let fragment_shader_src = r#"
+            #version 140
+
+            in float v_alpha;
+            out vec4 color;
+
+            uniform vec3 tint;
+
+            void main() {
+                color = vec4(tint, v_alpha);
+            }
+        "#;
+
+        let program = render::compile_program(facade, "trail", vertex_shader_src, fragment_shader_src)?;
+
+        Ok(TrailRenderer
+        {
+            visible: false,
+            vertices: Vec::new(),
+            vertex_buffer: None,
+            program,
+        })
+    }
+
+    pub fn toggle(&mut self)
+    {
+        self.visible = !self.visible;
+    }
+
+    pub fn draw<F: ?Sized + Facade>(&mut self, facade: &F, target: &mut Frame, trails: &Trails, view: [[f32; 4]; 4], projection: [[f32; 4]; 4], viewport: Option<glium::Rect>) -> Result<(), String>
+    {
+        if !self.visible || trails.filled() < 2 {
+            return Ok(());
+        }
+
+        self.vertices.clear();
+        let fade = 1.0 / (trails.length() - 1) as f32;
+
+        for bird in 0..trails.bird_count() {
+            let mut previous: Option<TrailVertex> = None;
+
+            for (age, position) in trails.points(bird).enumerate() {
+                let vertex = TrailVertex { position: position.into(), alpha: 1.0 - age as f32 * fade };
+                if let Some(previous) = previous {
+                    self.vertices.push(previous);
+                    self.vertices.push(vertex);
+                }
+                previous = Some(vertex);
+            }
+        }
+
+        let count = self.vertices.len();
+        let needs_buffer = match &self.vertex_buffer {
+            Some(buffer) => buffer.len() < count,
+            None => true,
+        };
+        if needs_buffer {
+            self.vertex_buffer = Some(VertexBuffer::empty_dynamic(facade, count).map_err(|err| err.to_string())?);
+        }
+        let slice = match self.vertex_buffer.as_ref().and_then(|buffer| buffer.slice(0..count)) {
+            Some(slice) => slice,
+            None => return Ok(()),
+        };
+        slice.write(&self.vertices);
+
+        let uniforms = uniform! {
+            view: view,
+            projection: projection,
+            tint: TRAIL_TINT,
+        };
+
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            depth: render::depth_test(false),
+            viewport,
+            .. Default::default()
+        };
+
+        target.draw(
+            slice,
+            glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+            &self.program,
+            &uniforms,
+            &params,
+        ).map_err(|err| err.to_string())
+    }
+}
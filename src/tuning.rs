@@ -0,0 +1,54 @@
+use egui_glium::egui_winit::egui;
+
+use bird_flock::flock::{Flock, FlockConfig};
+use bird_flock::params::{SimParam, MAX_MAX_SPEED, MAX_STEERING_WEIGHT, MAX_VIEW_ANGLE, MAX_WEIGHT, MIN_MAX_SPEED, MIN_NEIGHBOUR_RADIUS, MIN_VIEW_ANGLE};
+
+pub const MAX_GRAVITY: f32 = 0.005;
+pub const SPAWN_BATCH: usize = 100;
+
+fn param_slider<F: FnOnce(&mut f32) -> egui::Slider<'_>>(ui: &mut egui::Ui, flock: &mut Flock, param: SimParam, slider: F)
+{
+    let mut value = param.get(&flock.config);
+    if ui.add(slider(&mut value)).changed() {
+        flock.set_param(param, value);
+    }
+}
+
+pub fn tuning_panel(ctx: &egui::Context, flock: &mut Flock, max_birds: Option<usize>)
+{
+    egui::Window::new("Flock tuning").default_width(260.0).show(ctx, |ui| {
+        if let Some(max_birds) = max_birds {
+            let config = &mut flock.config;
+            ui.add(egui::Slider::new(&mut config.num_birds, 1..=max_birds).logarithmic(true).text("birds"));
+            ui.horizontal(|ui| {
+                if ui.button(format!("-{}", SPAWN_BATCH)).clicked() {
+                    config.num_birds = config.num_birds.saturating_sub(SPAWN_BATCH).max(1);
+                }
+                if ui.button(format!("+{}", SPAWN_BATCH)).clicked() {
+                    config.num_birds = (config.num_birds + SPAWN_BATCH).min(max_birds);
+                }
+            });
+        }
+        let boundary_size = flock.config.boundary_size;
+        param_slider(ui, flock, SimParam::SeparationWeight, |value| egui::Slider::new(value, 0.0..=MAX_WEIGHT).text("separation"));
+        param_slider(ui, flock, SimParam::AlignmentWeight, |value| egui::Slider::new(value, 0.0..=MAX_STEERING_WEIGHT).text("alignment"));
+        param_slider(ui, flock, SimParam::CohesionWeight, |value| egui::Slider::new(value, 0.0..=MAX_STEERING_WEIGHT).text("cohesion"));
+        param_slider(ui, flock, SimParam::MaxSpeed, |value| egui::Slider::new(value, MIN_MAX_SPEED..=MAX_MAX_SPEED).logarithmic(true).text("max speed"));
+        param_slider(ui, flock, SimParam::NeighbourRadius, |value| egui::Slider::new(value, MIN_NEIGHBOUR_RADIUS..=boundary_size).text("neighbour radius"));
+        param_slider(ui, flock, SimParam::ViewAngle, |value| egui::Slider::new(value, MIN_VIEW_ANGLE..=MAX_VIEW_ANGLE).suffix("°").text("view angle"));
+        let mut gravity = flock.config.gravity;
+        let x = ui.add(egui::Slider::new(&mut gravity.x, -MAX_GRAVITY..=MAX_GRAVITY).text("gravity x")).changed();
+        let y = ui.add(egui::Slider::new(&mut gravity.y, -MAX_GRAVITY..=MAX_GRAVITY).text("gravity y")).changed();
+        let z = ui.add(egui::Slider::new(&mut gravity.z, -MAX_GRAVITY..=MAX_GRAVITY).text("gravity z")).changed();
+        if x || y || z {
+            flock.set_gravity(gravity);
+        }
+
+        if ui.button("Reset defaults").clicked() {
+            for param in SimParam::ALL.iter() {
+                flock.reset_param(*param);
+            }
+            flock.set_gravity(FlockConfig::default().gravity);
+        }
+    });
+}
@@ -0,0 +1,92 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::shaders::{ShaderSet, ShaderSource};
+
+pub const SHADER_DIR: &str = "shaders";
+
+fn load_source(dir: &Path, name: &str) -> Result<ShaderSource, String>
+{
+    let read = |extension: &str| {
+        let path = dir.join(format!("{}.{}", name, extension));
+        fs::read_to_string(&path).map_err(|err| format!("could not read {}: {}", path.display(), err))
+    };
+    Ok(ShaderSource { vertex: read("vert")?, fragment: read("frag")? })
+}
+
+pub fn load_shaders(dir: &Path) -> Result<ShaderSet, String>
+{
+    Ok(ShaderSet { bird: load_source(dir, "bird")?, bird_points: load_source(dir, "bird_points")?, ground: load_source(dir, "ground")?, sky: load_source(dir, "sky")? })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Changes
+{
+    pub config: bool,
+    pub shaders: bool,
+}
+
+pub struct FileWatcher
+{
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    config: PathBuf,
+    shader_dir: PathBuf,
+}
+
+fn directory_of(path: &Path) -> &Path
+{
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, String>
+{
+    path.canonicalize().map_err(|err| format!("could not watch {}: {}", path.display(), err))
+}
+
+impl FileWatcher
+{
+    pub fn new(config: &Path, shader_dir: &Path) -> Result<FileWatcher, String>
+    {
+        let config_dir = canonical(directory_of(config))?;
+        let shader_dir = canonical(shader_dir)?;
+        let file_name = config.file_name().ok_or_else(|| format!("could not watch {}: not a file", config.display()))?;
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }).map_err(|err| format!("could not start the file watcher: {}", err))?;
+        for dir in [&config_dir, &shader_dir].iter() {
+            watcher.watch(dir, RecursiveMode::NonRecursive).map_err(|err| format!("could not watch {}: {}", dir.display(), err))?;
+        }
+
+        Ok(FileWatcher { _watcher: watcher, events, config: config_dir.join(file_name), shader_dir })
+    }
+
+    pub fn poll(&self) -> Changes
+    {
+        let mut changes = Changes::default();
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                    for path in &event.paths {
+                        if *path == self.config {
+                            changes.config = true;
+                        } else if path.parent() == Some(self.shader_dir.as_path()) && matches!(path.extension().and_then(OsStr::to_str), Some("vert") | Some("frag")) {
+                            changes.shaders = true;
+                        }
+                    }
+                },
+                Ok(_) => (),
+                Err(err) => eprintln!("warning: file watcher error: {}", err),
+            }
+        }
+        changes
+    }
+}
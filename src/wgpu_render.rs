@@ -0,0 +1,324 @@
+use std::mem;
+use std::sync::Arc;
+use winit::window::Window;
+
+use crate::draw::{self, BirdInstance, FlockRenderer, FrameUniforms, MeshVertex};
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const UNIFORM_FLOATS: usize = 40;
+
+// nalgebra projections map depth to -1..1 as OpenGL does; wgpu clips depth to 0..1.
+const DEPTH_CORRECTION: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 0.5, 0.0],
+    [0.0, 0.0, 0.5, 1.0],
+];
+
+const SHADER: &str = r#"
+struct Frame {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    light_dir: vec3<f32>,
+    ambient: f32,
+    light_colour: vec3<f32>,
+    flap_time: f32,
+};
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+
+struct VertexOut {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) colour: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) offset: vec3<f32>,
+    @location(3) axis_x: vec3<f32>,
+    @location(4) axis_y: vec3<f32>,
+    @location(5) axis_z: vec3<f32>,
+    @location(6) colour: vec3<f32>,
+    @location(7) wing: vec2<f32>,
+) -> VertexOut {
+    let orientation = mat3x3<f32>(axis_x, axis_y, axis_z);
+    let flap = sin(wing.x + wing.y * frame.flap_time) * abs(position.x) * 0.8;
+    var out: VertexOut;
+    out.clip = frame.projection * frame.view * vec4<f32>(orientation * (position + vec3<f32>(0.0, 0.0, flap)) + offset, 1.0);
+    out.normal = orientation * normal;
+    out.colour = colour;
+    return out;
+}
+
+@fragment
+fn fs_main(vertex: VertexOut) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(vertex.normal), frame.light_dir), 0.0);
+    return vec4<f32>(vertex.colour * (frame.ambient + (1.0 - frame.ambient) * diffuse * frame.light_colour), 1.0);
+}
+"#;
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8]
+{
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * mem::size_of::<T>()) }
+}
+
+fn multiply(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4]
+{
+    let mut product = [[0.0; 4]; 4];
+    for column in 0..4 {
+        for row in 0..4 {
+            product[column][row] = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    product
+}
+
+fn uniform_data(uniforms: &FrameUniforms) -> [f32; UNIFORM_FLOATS]
+{
+    let projection = multiply(&DEPTH_CORRECTION, &uniforms.projection);
+    let mut data = [0.0; UNIFORM_FLOATS];
+    for column in 0..4 {
+        data[column * 4..column * 4 + 4].copy_from_slice(&uniforms.view[column]);
+        data[16 + column * 4..16 + column * 4 + 4].copy_from_slice(&projection[column]);
+    }
+    data[32..35].copy_from_slice(&uniforms.light_dir);
+    data[35] = uniforms.ambient;
+    data[36..39].copy_from_slice(&uniforms.light_colour);
+    data[39] = uniforms.flap_time;
+    data
+}
+
+pub struct WgpuRenderer
+{
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    depth_view: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+    mesh_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl WgpuRenderer
+{
+    pub async fn new(window: Arc<Window>) -> Result<WgpuRenderer, String>
+    {
+        let size = window.inner_size();
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let surface = instance.create_surface(window).map_err(|err| format!("could not create the surface: {}", err))?;
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions
+        {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }).await.ok_or_else(|| "no compatible graphics adapter was found".to_string())?;
+        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor::default(), None).await
+            .map_err(|err| format!("could not open the graphics device: {}", err))?;
+
+        let config = surface.get_default_config(&adapter, size.width.max(1), size.height.max(1))
+            .ok_or_else(|| "the surface is not supported by the adapter".to_string())?;
+        surface.configure(&device, &config);
+        let depth_view = WgpuRenderer::depth_view(&device, &config);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor
+        {
+            label: Some("frame uniforms"),
+            size: (UNIFORM_FLOATS * mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor
+        {
+            label: Some("frame"),
+            entries: &[wgpu::BindGroupLayoutEntry
+            {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor
+        {
+            label: Some("frame"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor { label: Some("bird"), source: wgpu::ShaderSource::Wgsl(SHADER.into()) });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor
+        {
+            label: Some("bird"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let mesh_attributes = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+        let instance_attributes = wgpu::vertex_attr_array![2 => Float32x3, 3 => Float32x3, 4 => Float32x3, 5 => Float32x3, 6 => Float32x3, 7 => Float32x2];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor
+        {
+            label: Some("bird"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState
+            {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[
+                    wgpu::VertexBufferLayout { array_stride: mem::size_of::<MeshVertex>() as u64, step_mode: wgpu::VertexStepMode::Vertex, attributes: &mesh_attributes },
+                    wgpu::VertexBufferLayout { array_stride: mem::size_of::<BirdInstance>() as u64, step_mode: wgpu::VertexStepMode::Instance, attributes: &instance_attributes },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState
+            {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState { format: config.format, blend: Some(wgpu::BlendState::REPLACE), write_mask: wgpu::ColorWrites::ALL })],
+            }),
+            primitive: wgpu::PrimitiveState { cull_mode: Some(wgpu::Face::Back), ..Default::default() },
+            depth_stencil: Some(wgpu::DepthStencilState
+            {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let mesh = draw::bird_mesh();
+        let mesh_buffer = device.create_buffer(&wgpu::BufferDescriptor
+        {
+            label: Some("bird mesh"),
+            size: (mesh.len() * mem::size_of::<MeshVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&mesh_buffer, 0, as_bytes(&mesh));
+        let instance_buffer = WgpuRenderer::instance_buffer(&device, 1);
+
+        Ok(WgpuRenderer
+        {
+            surface,
+            device,
+            queue,
+            config,
+            depth_view,
+            pipeline,
+            mesh_buffer,
+            vertex_count: mesh.len() as u32,
+            instance_buffer,
+            capacity: 1,
+            uniform_buffer,
+            bind_group,
+        })
+    }
+
+    fn depth_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView
+    {
+        device.create_texture(&wgpu::TextureDescriptor
+        {
+            label: Some("depth"),
+            size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer
+    {
+        device.create_buffer(&wgpu::BufferDescriptor
+        {
+            label: Some("bird instances"),
+            size: (capacity * mem::size_of::<BirdInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32)
+    {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+        self.depth_view = WgpuRenderer::depth_view(&self.device, &self.config);
+    }
+
+    pub fn begin_frame(&mut self) -> Result<wgpu::SurfaceTexture, String>
+    {
+        match self.surface.get_current_texture() {
+            Ok(frame) => Ok(frame),
+            Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                self.surface.get_current_texture().map_err(|err| err.to_string())
+            },
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+impl FlockRenderer for WgpuRenderer
+{
+    type Frame = wgpu::SurfaceTexture;
+
+    fn draw_birds(&mut self, frame: &mut wgpu::SurfaceTexture, birds: &[BirdInstance], uniforms: &FrameUniforms) -> Result<(), String>
+    {
+        if birds.len() > self.capacity {
+            self.capacity = birds.len().next_power_of_two();
+            self.instance_buffer = WgpuRenderer::instance_buffer(&self.device, self.capacity);
+        }
+        self.queue.write_buffer(&self.instance_buffer, 0, as_bytes(birds));
+        self.queue.write_buffer(&self.uniform_buffer, 0, as_bytes(&uniform_data(uniforms)));
+
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("birds") });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor
+            {
+                label: Some("birds"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment
+                {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment
+                {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: wgpu::StoreOp::Store }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if !birds.is_empty() {
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &self.bind_group, &[]);
+                pass.set_vertex_buffer(0, self.mesh_buffer.slice(..));
+                pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                pass.draw(0..self.vertex_count, 0..birds.len() as u32);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+use nalgebra::Vector3;
+
+use crate::flock::normalize_or_zero;
+
+pub const TURBULENCE_LAYERS: usize = 4;
+pub const TURBULENCE: f32 = 0.75;
+pub const TURBULENCE_SCALE: f32 = 2.0;
+
+pub trait WindField: Send + Sync
+{
+    fn sample(&self, position: Vector3<f32>, time: f32) -> Vector3<f32>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindMode
+{
+    #[default]
+    Constant,
+    Turbulent,
+}
+
+impl WindMode
+{
+    pub fn next(self) -> WindMode
+    {
+        match self {
+            WindMode::Constant => WindMode::Turbulent,
+            WindMode::Turbulent => WindMode::Constant,
+        }
+    }
+}
+
+impl FromStr for WindMode
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<WindMode, String>
+    {
+        match s {
+            "constant" => Ok(WindMode::Constant),
+            "turbulent" => Ok(WindMode::Turbulent),
+            _ => Err(format!("unknown wind mode '{}', expected constant or turbulent", s)),
+        }
+    }
+}
+
+pub struct ConstantWind
+{
+    pub force: Vector3<f32>,
+}
+
+impl WindField for ConstantWind
+{
+    fn sample(&self, _position: Vector3<f32>, _time: f32) -> Vector3<f32>
+    {
+        self.force
+    }
+}
+
+struct Layer
+{
+    wave: Vector3<f32>,
+    frequency: f32,
+    phase: f32,
+    axis: Vector3<f32>,
+    amplitude: f32,
+}
+
+pub struct TurbulentWind
+{
+    pub base: Vector3<f32>,
+    pub strength: f32,
+    layers: [Layer; TURBULENCE_LAYERS],
+}
+
+impl TurbulentWind
+{
+    pub fn new(base: Vector3<f32>, strength: f32, scale: f32, seed: u64) -> TurbulentWind
+    {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let layers = std::array::from_fn(|octave| {
+            let spatial = (1 << octave) as f32 / scale;
+            Layer
+            {
+                wave: random_unit(&mut rng) * spatial,
+                frequency: rng.gen_range(0.005..0.03) * (octave + 1) as f32,
+                phase: rng.gen::<f32>() * TAU,
+                axis: random_unit(&mut rng),
+                amplitude: 0.5 / (1 << octave) as f32,
+            }
+        });
+
+        TurbulentWind { base, strength, layers }
+    }
+}
+
+impl WindField for TurbulentWind
+{
+    fn sample(&self, position: Vector3<f32>, time: f32) -> Vector3<f32>
+    {
+        let mut gust = Vector3::zeros();
+        for layer in self.layers.iter() {
+            let wave = (layer.wave.dot(&position) + layer.frequency * time + layer.phase).sin();
+            gust += layer.axis * (wave * layer.amplitude);
+        }
+
+        self.base + gust * (self.base.norm() * self.strength)
+    }
+}
+
+pub enum Wind
+{
+    Constant(ConstantWind),
+    Turbulent(TurbulentWind),
+}
+
+impl WindField for Wind
+{
+    fn sample(&self, position: Vector3<f32>, time: f32) -> Vector3<f32>
+    {
+        match self {
+            Wind::Constant(wind) => wind.sample(position, time),
+            Wind::Turbulent(wind) => wind.sample(position, time),
+        }
+    }
+}
+
+fn random_unit<R: Rng + ?Sized>(rng: &mut R) -> Vector3<f32>
+{
+    let direction = Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+    let unit = normalize_or_zero(direction);
+    if unit == Vector3::zeros() { Vector3::new(1.0, 0.0, 0.0) } else { unit }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn turbulence_is_continuous_in_space_and_time()
+    {
+        let wind = TurbulentWind::new(Vector3::new(0.005, 0.0, 0.002), TURBULENCE, TURBULENCE_SCALE, 9);
+        let mut rng = StdRng::seed_from_u64(2);
+
+        for _ in 0..100 {
+            let point = Vector3::new(rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0));
+            let time = rng.gen_range(0.0..1000.0);
+            let here = wind.sample(point, time);
+
+            let nudged = wind.sample(point + Vector3::new(1e-3, -1e-3, 1e-3), time);
+            let later = wind.sample(point, time + 1e-2);
+            assert!((nudged - here).norm() < 1e-5, "jump of {} near {:?}", (nudged - here).norm(), point);
+            assert!((later - here).norm() < 1e-5, "jump of {} at t = {}", (later - here).norm(), time);
+        }
+    }
+
+    #[test]
+    fn turbulence_depends_only_on_its_seed()
+    {
+        let base = Vector3::new(0.005, 0.0, 0.0);
+        let point = Vector3::new(3.0, -1.0, 2.0);
+        let first = TurbulentWind::new(base, TURBULENCE, TURBULENCE_SCALE, 4);
+        let again = TurbulentWind::new(base, TURBULENCE, TURBULENCE_SCALE, 4);
+        let other = TurbulentWind::new(base, TURBULENCE, TURBULENCE_SCALE, 5);
+
+        assert_eq!(first.sample(point, 12.0), again.sample(point, 12.0));
+        assert_ne!(first.sample(point, 12.0), other.sample(point, 12.0));
+    }
+
+    #[test]
+    fn calm_turbulent_wind_is_still()
+    {
+        let wind = TurbulentWind::new(Vector3::zeros(), TURBULENCE, TURBULENCE_SCALE, 1);
+        assert_eq!(wind.sample(Vector3::new(1.0, 2.0, 3.0), 5.0), Vector3::zeros());
+    }
+}
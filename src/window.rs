@@ -0,0 +1,92 @@
+use glium::backend::glutin::Display;
+use glium::glutin::config::ConfigTemplateBuilder;
+use glium::glutin::context::{ContextAttributesBuilder, NotCurrentGlContext};
+use glium::glutin::display::{GetGlDisplay, GlDisplay};
+use glium::glutin::surface::{GlSurface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
+use glium::winit::dpi::PhysicalPosition;
+use glium::winit::event_loop::EventLoop;
+use glium::winit::monitor::MonitorHandle;
+use glium::winit::window::{Fullscreen, Window};
+use glutin_winit::DisplayBuilder;
+use raw_window_handle::HasWindowHandle;
+use std::num::NonZeroU32;
+
+use crate::config::WindowConfig;
+
+pub fn create_window(event_loop: &EventLoop<()>, settings: &WindowConfig, vsync: bool) -> Result<(Window, Display<WindowSurface>), String>
+{
+    let mut template = ConfigTemplateBuilder::new().with_depth_size(24);
+    if settings.samples > 0 {
+        template = template.with_multisampling(settings.samples);
+    }
+    let attributes = Window::default_attributes()
+        .with_title("Bird Flock Simulation")
+        .with_inner_size(glium::winit::dpi::PhysicalSize::new(settings.width, settings.height));
+
+    let (window, gl_config) = DisplayBuilder::new()
+        .with_window_attributes(Some(attributes))
+        .build(event_loop, template, |mut configs| configs.next().expect("no OpenGL configurations to pick from"))
+        .map_err(|err| format!("could not find an OpenGL configuration: {}", err))?;
+    let window = window.ok_or_else(|| "could not create the window".to_string())?;
+    let handle = window.window_handle().map_err(|err| format!("could not get the window handle: {}", err))?;
+
+    let (width, height): (u32, u32) = window.inner_size().into();
+    let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+        handle.as_raw(),
+        NonZeroU32::new(width).unwrap_or(NonZeroU32::MIN),
+        NonZeroU32::new(height).unwrap_or(NonZeroU32::MIN),
+    );
+    let surface = unsafe { gl_config.display().create_window_surface(&gl_config, &surface_attributes) }
+        .map_err(|err| format!("could not create the window surface: {}", err))?;
+    let context_attributes = ContextAttributesBuilder::new().build(Some(handle.as_raw()));
+    let context = unsafe { gl_config.display().create_context(&gl_config, &context_attributes) }
+        .map_err(|err| format!("could not create the OpenGL context: {}", err))?
+        .make_current(&surface)
+        .map_err(|err| format!("could not make the OpenGL context current: {}", err))?;
+
+    let interval = if vsync { SwapInterval::Wait(NonZeroU32::MIN) } else { SwapInterval::DontWait };
+    if let Err(err) = surface.set_swap_interval(&context, interval) {
+        eprintln!("warning: could not turn vsync {}: {}", if vsync { "on" } else { "off" }, err);
+    }
+
+    let display = Display::from_context_surface(context, surface).map_err(|err| err.to_string())?;
+    Ok((window, display))
+}
+
+pub fn select_monitor(window: &Window, index: usize) -> Result<MonitorHandle, String>
+{
+    let monitors: Vec<MonitorHandle> = window.available_monitors().collect();
+    if let Some(monitor) = monitors.get(index) {
+        return Ok(monitor.clone());
+    }
+
+    let names: Vec<String> = monitors.iter().enumerate().map(|(i, monitor)| describe_monitor(i, monitor)).collect();
+    Err(format!("monitor {} does not exist, available monitors are: {}", index, names.join(", ")))
+}
+
+fn describe_monitor(index: usize, monitor: &MonitorHandle) -> String
+{
+    let size = monitor.size();
+    let name = monitor.name().unwrap_or_else(|| "unnamed".to_string());
+    format!("{} ({} {}x{} at {:.2}x)", index, name, size.width, size.height, monitor.scale_factor())
+}
+
+pub fn move_to_monitor(window: &Window, monitor: &MonitorHandle)
+{
+    let origin = monitor.position();
+    let size = monitor.size();
+    let outer = window.outer_size();
+    let x = origin.x + (size.width as i32 - outer.width as i32).max(0) / 2;
+    let y = origin.y + (size.height as i32 - outer.height as i32).max(0) / 2;
+    window.set_outer_position(PhysicalPosition::new(x, y));
+}
+
+pub fn set_fullscreen(window: &Window, fullscreen: bool, monitor: Option<MonitorHandle>)
+{
+    window.set_fullscreen(if fullscreen { Some(Fullscreen::Borderless(monitor)) } else { None });
+}
+
+pub fn toggle_fullscreen(window: &Window)
+{
+    set_fullscreen(window, window.fullscreen().is_none(), window.current_monitor());
+}
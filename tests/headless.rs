@@ -0,0 +1,51 @@
+use bird_flock::flock::{BoundaryShape, NeighbourSearch};
+use bird_flock::{Flock, FlockConfig};
+
+fn run(config: FlockConfig, steps: usize) -> Flock
+{
+    let mut flock = Flock::with_seed(config, 21);
+    for _ in 0..steps {
+        flock.update();
+    }
+    flock
+}
+
+#[test]
+fn seeded_flock_stays_finite_and_inside_the_cube()
+{
+    let config = FlockConfig { num_birds: 100, seed: Some(21), ..FlockConfig::default() };
+    let half_size = config.boundary_size / 2.0;
+    let flock = run(config, 200);
+
+    assert_eq!(flock.birds.len(), 100);
+    for bird in flock.birds.iter() {
+        assert!(bird.position.iter().chain(bird.velocity.iter()).all(|v| v.is_finite()), "{:?}", bird);
+        assert!(bird.position.iter().all(|p| p.abs() <= half_size + 1e-4), "escaped to {:?}", bird.position);
+    }
+}
+
+#[test]
+fn seeded_flock_stays_inside_the_sphere()
+{
+    let config = FlockConfig
+    {
+        num_birds: 100,
+        boundary_shape: BoundaryShape::Sphere { radius: 4.0 },
+        neighbour_search: NeighbourSearch::Octree,
+        seed: Some(21),
+        ..FlockConfig::default()
+    };
+    let flock = run(config, 200);
+
+    for bird in flock.birds.iter() {
+        assert!(bird.position.iter().chain(bird.velocity.iter()).all(|v| v.is_finite()), "{:?}", bird);
+        assert!(bird.position.norm() <= 4.0 + 1e-4, "escaped to {:?}", bird.position);
+    }
+}
+
+#[test]
+fn same_seed_gives_the_same_run()
+{
+    let config = FlockConfig { num_birds: 50, seed: Some(21), ..FlockConfig::default() };
+    assert_eq!(run(config.clone(), 60).birds, run(config, 60).birds);
+}
@@ -0,0 +1,173 @@
+#![cfg(target_arch = "wasm32")]
+
+mod renderer;
+
+use nalgebra::{Perspective3, Vector3};
+use std::f32::consts::FRAC_PI_3;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys, WindowExtWebSys};
+use winit::window::{Window, WindowId};
+
+use bird_flock::draw::{BirdInstance, FlockRenderer, FrameUniforms, OrbitCamera};
+use bird_flock::timestep::FixedTimestep;
+use bird_flock::{Flock, FlockConfig};
+use renderer::GlowRenderer;
+
+const WEB_BIRDS: usize = 1000;
+const SIM_HZ: f64 = 60.0;
+const MAX_STEPS: u32 = 5;
+const LIGHT_DIRECTION: [f32; 3] = [0.4, 1.0, 0.3];
+const BIRD_COLOUR: [f32; 3] = [1.0, 0.0, 0.0];
+
+struct Running
+{
+    window: Window,
+    gl: glow::Context,
+    renderer: GlowRenderer,
+}
+
+struct App
+{
+    running: Option<Running>,
+    flock: Flock,
+    timestep: FixedTimestep,
+    last_frame: Option<f64>,
+    instances: Vec<BirdInstance>,
+    camera: OrbitCamera,
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+}
+
+fn now() -> f64
+{
+    web_sys::window().and_then(|window| window.performance()).map_or(0.0, |performance| performance.now())
+}
+
+impl App
+{
+    fn new() -> App
+    {
+        App
+        {
+            running: None,
+            flock: Flock::new(FlockConfig { num_birds: WEB_BIRDS, ..FlockConfig::default() }),
+            timestep: FixedTimestep::new(SIM_HZ, MAX_STEPS),
+            last_frame: None,
+            instances: Vec::new(),
+            camera: OrbitCamera::default(),
+            dragging: false,
+            cursor: None,
+        }
+    }
+
+    fn start(&mut self, event_loop: &ActiveEventLoop) -> Result<Running, String>
+    {
+        let attributes = Window::default_attributes().with_title("Bird Flock Simulation").with_append(true);
+        let window = event_loop.create_window(attributes).map_err(|err| err.to_string())?;
+        let canvas = window.canvas().ok_or_else(|| "the window has no canvas".to_string())?;
+        let context = canvas.get_context("webgl2")
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into::<web_sys::WebGl2RenderingContext>().ok())
+            .ok_or_else(|| "WebGL2 is not available".to_string())?;
+        let gl = glow::Context::from_webgl2_context(context);
+        let renderer = GlowRenderer::new(&gl)?;
+        window.request_redraw();
+        Ok(Running { window, gl, renderer })
+    }
+
+    fn redraw(&mut self)
+    {
+        let running = match &mut self.running {
+            Some(running) => running,
+            None => return,
+        };
+
+        let time = now();
+        let elapsed = self.last_frame.map_or(0.0, |last| (time - last).max(0.0) / 1000.0);
+        self.last_frame = Some(time);
+        for _ in 0..self.timestep.advance(Duration::from_secs_f64(elapsed)) {
+            self.flock.step(self.timestep.step().as_secs_f32());
+        }
+        let alpha = self.timestep.alpha();
+
+        let max_speed = self.flock.config.max_speed;
+        self.instances.clear();
+        self.instances.extend(self.flock.birds.iter().map(|bird| {
+            BirdInstance::from_bird(bird, alpha, max_speed, BIRD_COLOUR, 1.0)
+        }));
+
+        let size = running.window.inner_size();
+        let aspect = size.width.max(1) as f32 / size.height.max(1) as f32;
+        let view = self.camera.view();
+        let projection = Perspective3::new(aspect, FRAC_PI_3, 0.1, 100.0).to_homogeneous();
+        let uniforms = FrameUniforms
+        {
+            view: *view.as_ref(),
+            projection: *projection.as_ref(),
+            light_dir: Vector3::from(LIGHT_DIRECTION).normalize().into(),
+            light_colour: [1.0, 0.96, 0.9],
+            ambient: 0.3,
+            flap_time: alpha * self.timestep.step().as_secs_f32(),
+        };
+
+        running.renderer.clear(&running.gl, size.width, size.height);
+        if let Err(err) = running.renderer.draw_birds(&mut running.gl, &self.instances, &uniforms) {
+            web_sys::console::error_1(&err.into());
+        }
+        running.window.request_redraw();
+    }
+}
+
+impl ApplicationHandler for App
+{
+    fn resumed(&mut self, event_loop: &ActiveEventLoop)
+    {
+        if self.running.is_some() {
+            return;
+        }
+        match self.start(event_loop) {
+            Ok(running) => self.running = Some(running),
+            Err(err) => {
+                web_sys::console::error_1(&err.into());
+                event_loop.exit();
+            },
+        }
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent)
+    {
+        match event {
+            WindowEvent::RedrawRequested => self.redraw(),
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => self.dragging = state == ElementState::Pressed,
+            WindowEvent::CursorMoved { position, .. } => {
+                if let (true, Some((x, y))) = (self.dragging, self.cursor) {
+                    self.camera.drag((position.x - x) as f32, (position.y - y) as f32);
+                }
+                self.cursor = Some((position.x, position.y));
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 50.0,
+                };
+                self.camera.zoom(steps);
+            },
+            _ => (),
+        }
+    }
+}
+
+#[wasm_bindgen(start)]
+pub fn start() -> Result<(), JsValue>
+{
+    console_error_panic_hook::set_once();
+    let event_loop = EventLoop::new().map_err(|err| JsValue::from_str(&err.to_string()))?;
+    event_loop.spawn_app(App::new());
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use glow::HasContext;
+use std::mem;
+
+use bird_flock::draw::{self, BirdInstance, FlockRenderer, FrameUniforms, MeshVertex};
+
+const VERTEX_SHADER: &str = r#"#version 300 es
+    layout(location = 0) in vec3 position;
+    layout(location = 1) in vec3 normal;
+    layout(location = 2) in vec3 offset;
+    layout(location = 3) in mat3 orientation;
+    layout(location = 6) in vec3 colour;
+    layout(location = 7) in vec2 wing;
+
+    out vec3 v_normal;
+    out vec3 v_colour;
+
+    uniform mat4 view;
+    uniform mat4 projection;
+    uniform float flap_time;
+
+    void main() {
+        float flap = sin(wing.x + wing.y * flap_time) * abs(position.x) * 0.8;
+        v_normal = transpose(inverse(orientation)) * normal;
+        v_colour = colour;
+        gl_Position = projection * view * vec4(orientation * (position + vec3(0.0, 0.0, flap)) + offset, 1.0);
+    }
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 300 es
+    precision mediump float;
+
+    in vec3 v_normal;
+    in vec3 v_colour;
+    out vec4 color;
+
+    uniform vec3 light_dir;
+    uniform vec3 light_color;
+    uniform float ambient;
+
+    void main() {
+        float diffuse = max(dot(normalize(v_normal), light_dir), 0.0);
+        color = vec4(v_colour * (ambient + (1.0 - ambient) * diffuse * light_color), 1.0);
+    }
+"#;
+
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8]
+{
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * mem::size_of::<T>()) }
+}
+
+fn flatten(matrix: &[[f32; 4]; 4]) -> [f32; 16]
+{
+    let mut flat = [0.0; 16];
+    for (column, values) in matrix.iter().enumerate() {
+        flat[column * 4..column * 4 + 4].copy_from_slice(values);
+    }
+    flat
+}
+
+unsafe fn compile(gl: &glow::Context, kind: u32, source: &str) -> Result<glow::Shader, String>
+{
+    let shader = gl.create_shader(kind)?;
+    gl.shader_source(shader, source);
+    gl.compile_shader(shader);
+    if !gl.get_shader_compile_status(shader) {
+        let log = gl.get_shader_info_log(shader);
+        gl.delete_shader(shader);
+        return Err(format!("could not compile the bird shader: {}", log));
+    }
+    Ok(shader)
+}
+
+pub struct GlowRenderer
+{
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    instance_buffer: glow::Buffer,
+    capacity: usize,
+    vertex_count: i32,
+}
+
+impl GlowRenderer
+{
+    pub fn new(gl: &glow::Context) -> Result<GlowRenderer, String>
+    {
+        unsafe {
+            let vertex_shader = compile(gl, glow::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment_shader = compile(gl, glow::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
+            let program = gl.create_program()?;
+            gl.attach_shader(program, vertex_shader);
+            gl.attach_shader(program, fragment_shader);
+            gl.link_program(program);
+            gl.delete_shader(vertex_shader);
+            gl.delete_shader(fragment_shader);
+            if !gl.get_program_link_status(program) {
+                return Err(format!("could not link the bird shader: {}", gl.get_program_info_log(program)));
+            }
+
+            let vertex_array = gl.create_vertex_array()?;
+            gl.bind_vertex_array(Some(vertex_array));
+
+            let mesh = draw::bird_mesh();
+            let mesh_buffer = gl.create_buffer()?;
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(mesh_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, as_bytes(&mesh), glow::STATIC_DRAW);
+            let stride = mem::size_of::<MeshVertex>() as i32;
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, stride, 0);
+            gl.enable_vertex_attrib_array(1);
+            gl.vertex_attrib_pointer_f32(1, 3, glow::FLOAT, false, stride, 12);
+
+            let instance_buffer = gl.create_buffer()?;
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(instance_buffer));
+            let stride = mem::size_of::<BirdInstance>() as i32;
+            for location in 2..7 {
+                gl.enable_vertex_attrib_array(location);
+                gl.vertex_attrib_pointer_f32(location, 3, glow::FLOAT, false, stride, (location as i32 - 2) * 12);
+                gl.vertex_attrib_divisor(location, 1);
+            }
+            gl.enable_vertex_attrib_array(7);
+            gl.vertex_attrib_pointer_f32(7, 2, glow::FLOAT, false, stride, 60);
+            gl.vertex_attrib_divisor(7, 1);
+            gl.bind_vertex_array(None);
+
+            Ok(GlowRenderer { program, vertex_array, instance_buffer, capacity: 0, vertex_count: mesh.len() as i32 })
+        }
+    }
+
+    pub fn clear(&self, gl: &glow::Context, width: u32, height: u32)
+    {
+        unsafe {
+            gl.viewport(0, 0, width as i32, height as i32);
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+        }
+    }
+}
+
+impl FlockRenderer for GlowRenderer
+{
+    type Frame = glow::Context;
+
+    fn draw_birds(&mut self, gl: &mut glow::Context, birds: &[BirdInstance], uniforms: &FrameUniforms) -> Result<(), String>
+    {
+        if birds.is_empty() {
+            return Ok(());
+        }
+
+        unsafe {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.instance_buffer));
+            if birds.len() > self.capacity {
+                self.capacity = birds.len().next_power_of_two();
+                gl.buffer_data_size(glow::ARRAY_BUFFER, (self.capacity * mem::size_of::<BirdInstance>()) as i32, glow::DYNAMIC_DRAW);
+            }
+            gl.buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, as_bytes(birds));
+
+            gl.enable(glow::DEPTH_TEST);
+            gl.enable(glow::CULL_FACE);
+            gl.front_face(glow::CCW);
+            gl.use_program(Some(self.program));
+            let location = |name: &str| gl.get_uniform_location(self.program, name);
+            gl.uniform_matrix_4_f32_slice(location("view").as_ref(), false, &flatten(&uniforms.view));
+            gl.uniform_matrix_4_f32_slice(location("projection").as_ref(), false, &flatten(&uniforms.projection));
+            let [x, y, z] = uniforms.light_dir;
+            gl.uniform_3_f32(location("light_dir").as_ref(), x, y, z);
+            let [r, g, b] = uniforms.light_colour;
+            gl.uniform_3_f32(location("light_color").as_ref(), r, g, b);
+            gl.uniform_1_f32(location("ambient").as_ref(), uniforms.ambient);
+            gl.uniform_1_f32(location("flap_time").as_ref(), uniforms.flap_time);
+
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays_instanced(glow::TRIANGLES, 0, self.vertex_count, birds.len() as i32);
+            gl.bind_vertex_array(None);
+        }
+        Ok(())
+    }
+}